@@ -0,0 +1,200 @@
+// render_c is an example render server written in C
+//
+// It renders nothing, each pushed state is checked and its drawables are counted,
+// and a frame with nothing to draw is reported to the frame callback as skipped.
+// The conformance tests of render-api build it against include/render_api.h and check it like any other server.
+//
+// Build it with: cc -std=c99 -shared -fPIC -I render-api/include render-api/examples/c/render_c.c -o librender_c.so
+
+#include <stdlib.h>
+#include <string.h>
+#include "render_api.h"
+
+// VERSION is the only protocol version this server speaks
+static const char VERSION[] = "0";
+
+// Server is the persistent state of the server, handed to the client as RenderState
+typedef struct Server {
+    int32_t frame;
+    FrameCallback frame_callback;
+    void *frame_data;
+    UserCallback user_callback;
+    void *user_data;
+} Server;
+
+// copy allocates a copy of message for the client, which releases it through expr_free
+static int8_t *copy(const char *message) {
+    char *copied = malloc(strlen(message) + 1);
+    if (copied != NULL) {
+        strcpy(copied, message);
+    }
+    return (int8_t *)copied;
+}
+
+static ExprError none(void) {
+    ExprError error = { ErrorCode_None, true, NULL };
+    return error;
+}
+
+static ExprError error(ErrorCode code, bool recoverable, const char *message) {
+    ExprError error = { code, recoverable, copy(message) };
+    return error;
+}
+
+// strings_valid is whether every string of a c_array is non-null, the array may only be null when empty
+static bool strings_valid(int8_t *const *strings, intptr_t length) {
+    if (length < 0 || (strings == NULL && length > 0)) {
+        return false;
+    }
+    for (intptr_t i = 0; i < length; i++) {
+        if (strings[i] == NULL) {
+            return false;
+        }
+    }
+    return true;
+}
+
+static bool drawable_valid(const Drawable *draw) {
+    return draw->kind != NULL && strings_valid(draw->attrs, draw->attrs_length);
+}
+
+static bool section_valid(const Section *section) {
+    if (section->title == NULL || section->subsections_length < 0
+        || (section->subsections == NULL && section->subsections_length > 0)) {
+        return false;
+    }
+    for (intptr_t i = 0; i < section->subsections_length; i++) {
+        if (!section_valid(&section->subsections[i])) {
+            return false;
+        }
+    }
+    return strings_valid(section->items, section->items_length) && strings_valid(section->attrs, section->attrs_length);
+}
+
+// count_drawables checks state and counts what would be drawn, -1 if the state is invalid
+static intptr_t count_drawables(const State *state) {
+    const WorldState *world = &state->world_state;
+    if (world->terrain_len_x < 0 || world->terrain_len_y < 0 || world->terrain_len_z < 0) {
+        return -1;
+    }
+    intptr_t terrain_length = 1;
+    int64_t dimensions[] = { world->terrain_len_x, world->terrain_len_y, world->terrain_len_z };
+    for (int i = 0; i < 3; i++) {
+        if (dimensions[i] != 0 && terrain_length > INTPTR_MAX / dimensions[i]) {
+            return -1;
+        }
+        terrain_length *= (intptr_t)dimensions[i];
+    }
+    if (world->terrain == NULL && terrain_length > 0) {
+        return -1;
+    }
+    for (intptr_t i = 0; i < terrain_length; i++) {
+        const Terrain *terrain = &world->terrain[i];
+        if (!drawable_valid(&terrain->draw) || !strings_valid(terrain->attrs, terrain->attrs_length)) {
+            return -1;
+        }
+    }
+
+    const ActorState *actors = &state->actor_state;
+    if (actors->actors_length < 0 || (actors->actors == NULL && actors->actors_length > 0)) {
+        return -1;
+    }
+    for (intptr_t i = 0; i < actors->actors_length; i++) {
+        const Actor *actor = &actors->actors[i];
+        if (actor->name == NULL || !drawable_valid(&actor->draw) || !strings_valid(actor->attrs, actor->attrs_length)) {
+            return -1;
+        }
+    }
+
+    const MenuState *menu = &state->menu_state;
+    if (menu->kind == NULL || menu->sections_length < 0 || (menu->sections == NULL && menu->sections_length > 0)) {
+        return -1;
+    }
+    for (intptr_t i = 0; i < menu->sections_length; i++) {
+        if (!section_valid(&menu->sections[i])) {
+            return -1;
+        }
+    }
+
+    bool attrs_valid = strings_valid(world->attrs, world->attrs_length)
+        && strings_valid(actors->attrs, actors->attrs_length)
+        && strings_valid(menu->attrs, menu->attrs_length)
+        && strings_valid(state->attrs, state->attrs_length);
+    return attrs_valid ? terrain_length + actors->actors_length + menu->sections_length : -1;
+}
+
+InitResult expr_init(IncomingMetadata client_metadata) {
+    InitResult result = { NULL, NULL, NULL, 0, NULL, 0, { ErrorCode_None, true, NULL } };
+    bool supported = false;
+    for (intptr_t i = 0; i < client_metadata.supported_versions_length; i++) {
+        const char *version = (const char *)client_metadata.supported_versions[i];
+        supported = supported || (version != NULL && strcmp(version, VERSION) == 0);
+    }
+    if (!supported) {
+        result.error = error(ErrorCode_UnsupportedVersion, false, "render_c supports none of the client's versions");
+        return result;
+    }
+    Server *server = calloc(1, sizeof(Server));
+    if (server == NULL) {
+        result.error = error(ErrorCode_Unknown, false, "out of memory");
+        return result;
+    }
+    result.server_version = (int8_t *)VERSION;
+    result.server_state = (RenderState *)server;
+    return result;
+}
+
+ExprError expr_push_state(RenderState *render_state, State *game_state) {
+    Server *server = (Server *)render_state;
+    if (server == NULL) {
+        return error(ErrorCode_InvalidRenderState, true, "render state is null");
+    }
+    if (game_state == NULL) {
+        return error(ErrorCode_InvalidState, true, "game state is null");
+    }
+    intptr_t drawables = count_drawables(game_state);
+    if (drawables < 0) {
+        return error(ErrorCode_InvalidState, true, "game state is invalid");
+    }
+    server->frame++;
+    if (drawables == 0 && server->frame_callback != NULL) {
+        // NOTE: message is only valid for the duration of the callback
+        char message[] = "nothing to draw";
+        RenderResult result = { RenderEvent_FrameSkipped, false, (int8_t *)message };
+        server->frame_callback(result, server->frame, game_state, (int8_t *)message, server->frame_data);
+    }
+    return none();
+}
+
+ExprError expr_frame_callback(RenderState *render_state, FrameCallback callback, void *user_data) {
+    Server *server = (Server *)render_state;
+    if (server == NULL) {
+        return error(ErrorCode_InvalidRenderState, true, "render state is null");
+    }
+    server->frame_callback = callback;
+    server->frame_data = user_data;
+    return none();
+}
+
+ExprError expr_user_callback(RenderState *render_state, UserCallback callback, void *user_data) {
+    Server *server = (Server *)render_state;
+    if (server == NULL) {
+        return error(ErrorCode_InvalidRenderState, true, "render state is null");
+    }
+    // NOTE: render_c reads no input, the callback is kept but never called
+    server->user_callback = callback;
+    server->user_data = user_data;
+    return none();
+}
+
+ExprError expr_disconnect(RenderState *render_state) {
+    return render_state == NULL ? error(ErrorCode_InvalidRenderState, true, "render state is null") : none();
+}
+
+ExprError expr_reconnect(RenderState *render_state) {
+    return render_state == NULL ? error(ErrorCode_InvalidRenderState, true, "render state is null") : none();
+}
+
+void expr_free(int8_t *message) {
+    free(message);
+}
@@ -0,0 +1,393 @@
+// render_api.h declares the render API for render servers written in C (or anything else that speaks the C ABI)
+//
+// This file is generated from the render-api crate by tests/header.rs, do not edit it by hand.
+// Strings are nul terminated and declared int8_t * to match the crate, ownership is described in alloc.rs.
+
+#ifndef RENDER_API_H
+#define RENDER_API_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+// c_array_Section is declared ahead of Section, which refers to it
+typedef struct Section *c_array_Section;
+
+// ErrorCode identifies what went wrong in an entry point
+//
+// # Variants
+// * None - no error occurred
+// * Unknown - an error no other code describes, the message should explain it
+// * UnsupportedVersion - the server speaks none of the protocol versions the client supports
+// * InvalidState - the game state pushed was rejected
+// * InvalidRenderState - the render state is null or was not returned by expr_init
+// * DeviceLost - the device doing the actual rendering is no longer available
+// * DisplayError - a frame was rendered but could not be displayed
+// * Unsupported - the server does not support what was asked of it
+// * NeedsReinit - expr_init must be rerun before the server can be used again, returned by expr_reconnect
+// * NotLoaded - the server library or one of its symbols could not be loaded, only reported by the client
+//
+// # Notes
+// Codes are never renumbered, new codes are only ever added at the end
+typedef enum ErrorCode {
+  ErrorCode_None = 0,
+  ErrorCode_Unknown = 1,
+  ErrorCode_UnsupportedVersion = 2,
+  ErrorCode_InvalidState = 3,
+  ErrorCode_InvalidRenderState = 4,
+  ErrorCode_DeviceLost = 5,
+  ErrorCode_DisplayError = 6,
+  ErrorCode_Unsupported = 7,
+  ErrorCode_NeedsReinit = 8,
+  ErrorCode_NotLoaded = 9,
+} ErrorCode;
+
+// RenderContext communicates to the server what kind of scene needs to be rendered
+//
+// # Variants
+// * WorldTraversal - The scene takes place on a map, outside any buildings
+// * BuildingTraversal - The scene takes place inside a building with no map context
+// * Battle - The scene takes place inside a battle map
+typedef enum RenderContext {
+  RenderContext_WorldTraversal,
+  RenderContext_BuildingTraversal,
+  RenderContext_Battle,
+} RenderContext;
+
+// RenderEvent indicates what event, if any, happened when rendering a frame
+//
+// # Variants
+// * FrameSkipped - the frame was skipped, not necessarily a failing error
+// * RenderError - the frame should have been rendered but was not
+// * DisplayError - the frame was rendered but could not be displayed
+// * DeviceError - an error occured in the driver doing the actual rendering
+typedef enum RenderEvent {
+  RenderEvent_FrameSkipped,
+  RenderEvent_RenderError,
+  RenderEvent_DisplayError,
+  RenderEvent_DeviceError,
+} RenderEvent;
+
+// UserEvent indicates what kind of user input was received
+//
+// # Variants
+// * Input - literal button input, keyboard, joystick, controller button, etc
+// * Command - text command entered in a field of some kind
+typedef enum UserEvent {
+  UserEvent_Input,
+  UserEvent_Command,
+} UserEvent;
+
+// ExtensionMetadata is a blob that contains the metadata for any enabled extension
+// The layout of the blob is declared by the extension (see extension::Extension)
+typedef struct ExtensionMetadata ExtensionMetadata;
+
+// Render state is a blob that server use to persist state between calls.
+// The engine in no way guarantees that a server will remain in memory indefinitely, therefore state should be stored here.
+typedef struct RenderState RenderState;
+
+// c_array indicates that a pointer refers to an array, not just a single item
+typedef int8_t **c_array_string;
+
+// c_array indicates that a pointer refers to an array, not just a single item
+typedef struct ExtensionMetadata **c_array_ExtensionMetadata;
+
+// IncomingMetadata is a struct supplying metadata for the target server
+// # Fields
+// * client_version is the version of the core client
+// * supported_versions is a list of server versions the client supports
+// * supported_extensions is a list of extensions supported by the client
+// * enabled_extensions lists which of the supported extensions are enabled
+//   note that enabled_extensions\[some x\] will always be equal to supported_extensions\[some y\]
+// * extension_metadata is an array of pointers to the metadata of each enabled extension
+//   note that extension_metadata\[idx\] refers to extension at enabled_extensions\[idx\]
+//
+// # Notes
+// This struct may change while on version 0 of the API.
+// Once version 1 is released this struct definition will not change
+typedef struct IncomingMetadata {
+  int8_t *client_version;
+  c_array_string supported_versions;
+  intptr_t supported_versions_length;
+  c_array_string supported_extensions;
+  intptr_t supported_extensions_length;
+  c_array_string enabled_extensions;
+  intptr_t enabled_extensions_length;
+  c_array_ExtensionMetadata extension_metadata;
+} IncomingMetadata;
+
+// ExprError is the outcome of an entry point
+//
+// # Fields
+// * code is what went wrong, ErrorCode::None if nothing did
+// * recoverable is whether the server can still be used, if not it has to be reinitialized or unloaded
+// * message describes the error, it may be null
+//
+// # Notes
+// message is handed to the client, which releases it through expr_free if the server exposes it (see alloc)
+typedef struct ExprError {
+  enum ErrorCode code;
+  bool recoverable;
+  int8_t *message;
+} ExprError;
+
+// InitResult is a struct consisting of all necessary information for the client to utilize the server
+// # Fields
+// * server_version is the version of the render API the server is using
+// * server_state is the render state the server uses as persistent memory
+// * server_extensions is a list of extensions to the render API protocol the server can respond to,
+//   these extensions determine additional functions callable from the client to the server
+// * accepted_extensions is the list of client extensions the server can display,
+//   these extensions determine additional information a client can send through v*::state::State
+// * error is the error that occured during initialization, if any
+//
+// # Notes
+// * This struct may change while on version 0 of the API.
+//   Once version 1 is released this struct definition will not change
+// * If error's code is not ErrorCode::None that indicates an error occurred
+// * If server_version or server_state is null that means the error is unrecoverable
+// * error's message is released by the client through expr_free (if exposed),
+//   every other string stays owned by the server, see alloc
+typedef struct InitResult {
+  int8_t *server_version;
+  struct RenderState *server_state;
+  c_array_string server_extensions;
+  intptr_t server_extensions_length;
+  c_array_string accepted_extensions;
+  intptr_t accepted_extensions_length;
+  struct ExprError error;
+} InitResult;
+
+// Attribute is a string of the format: "name:value"
+// The first section identifies what attribute is being read, and the value denotes the value.
+// If an attriubte has more than one argument, they will be separated by further :.
+// A : inside an argument is escaped as \:, and a \ as \\.
+// Examples of attributes: "status:burn", "items:5", "movement:5:5"
+//
+// See the attribute module for a typed form of attributes.
+//
+// # Notes
+// * Extensions can add new attributes, therefore the list of attributes for any given type
+//   may not be complete.
+// * Any value that can have items added by an extension will usually be an attribute
+//   (noteable exception: MenuState kind)
+typedef int8_t *Attribute;
+
+// c_array indicates that a pointer refers to an array, not just a single item
+typedef Attribute *c_array_Attribute;
+
+// Drawable represents a tile to be drawn on the world
+//
+// # Fields
+// * kind is the name of the kind of thing being drawn, details depend on what contains this drawable
+// * pos_* is the position of this tile
+// * span_* is the size of this tile
+// * attrs is an attribute set
+typedef struct Drawable {
+  int8_t *kind;
+  int64_t pos_x;
+  int64_t pos_y;
+  int64_t pos_z;
+  int64_t span_x;
+  int64_t span_y;
+  int64_t span_z;
+  c_array_Attribute attrs;
+  intptr_t attrs_length;
+} Drawable;
+
+// Terrain represents a world tile that may or may not be traversable
+//
+// # Fields
+// * draw is the tile information for this terrain (valid kinds listed in Valid Kinds section, NOTE: extensions can add new kinds)
+// * attrs is an attribute set
+//
+// # Valid Kinds
+// * terminal - indicates this terrain can be stood on
+// * entrance - indicates this terrain is an entrance to another world (building, tunnel, etc)
+// * no_entrance - indicates this terrain is an entrance that cannot currently be used
+// * passable - indicates this terrain can be passed through but connect be a stopping location
+// * impassable - indicates this terrain cannot be passed through
+//
+// # Standard Client Attributes
+// * note:\<text\> - A plaintext note with details about this attribute (may occur more than once)
+// * kind_note:\<text\> - A note indicating why this terrain is not terminal or entrance
+// * type:\<text\> - indicates what type of terrain this is (e.g. plains, fields, mountains, etc)
+// * status:\<text\> - An effect on this terrain (may occur more than once)
+// * resource:\<name\>:\<value\> - The quantity of a given resource available on this terrain (may occur more than once)
+typedef struct Terrain {
+  struct Drawable draw;
+  c_array_Attribute attrs;
+  intptr_t attrs_length;
+} Terrain;
+
+// c_array indicates that a pointer refers to an array, not just a single item
+typedef struct Terrain *c_array_Terrain;
+
+// WorldState is the collective game state of all terrain in the current world
+//
+// # Fields
+// * terrain is a flattened array of length terrain_len_x * terrain_len_y * terrain_len_z,
+//   the terrain at (x, y, z) is at index x + y * terrain_len_x + z * terrain_len_x * terrain_len_y
+// * terrain_len_* is the length of the terrain along a given axis
+// * attrs is an attribute set
+typedef struct WorldState {
+  c_array_Terrain terrain;
+  int64_t terrain_len_x;
+  int64_t terrain_len_y;
+  int64_t terrain_len_z;
+  c_array_Attribute attrs;
+  intptr_t attrs_length;
+} WorldState;
+
+// Actor represents an in-world character, either a player or an AI
+//
+// # Fields
+// * name is the actors name, how this is displayed is up to the server
+// * description is the actors description text, how this is displayed is up to the server
+// * draw is the tile information for this actor (valid kind details in the Valid Kinds section, NOTE: extensions can add new kinds)
+// * attrs is an attribute set
+//
+// # Valid Kinds
+// * player - indicates this actor is controllable by the player
+// * computer - indicates this actor is not controllable by the player
+//
+// # Standard Client Attributes
+// * control:\<current|standby|distant\> - indicates whether this actor is currently being controlled,
+//   they are able to be selected for control, or they are controllable but not currently selectable
+//   (only given for kind: player)
+// * status:\<text\> - indicates actor has status effect (may occur more than once)
+// * affinity:\<text\> - indicates one of the actors strongest attack types (may occur more than once)
+// * affinity_interaction:\<name\>:\<value\> - indicates what affinities this actor is strong or weak against
+// * stat:\<name\>:\<value\>\[:delta\] - indicates the name of each stat to display (if needed) and it's value.
+//   If delta is provided, that indicates what changes are occurring to this stat,
+//   positive delta means stat increase and negative delta means stat decrease
+typedef struct Actor {
+  int8_t *name;
+  int8_t *description;
+  struct Drawable draw;
+  c_array_Attribute attrs;
+  intptr_t attrs_length;
+} Actor;
+
+// c_array indicates that a pointer refers to an array, not just a single item
+typedef struct Actor *c_array_Actor;
+
+// ActorState is the collective game state of all actors in the current world
+//
+// # Fields
+// * actors is an array of all actors in the current world
+// * attrs is an attribute set
+typedef struct ActorState {
+  c_array_Actor actors;
+  intptr_t actors_length;
+  c_array_Attribute attrs;
+  intptr_t attrs_length;
+} ActorState;
+
+// MenuState is the current state of the game menu
+//
+// # Fields
+// * kind is the kind of menu this is, one of the MenuContext variants in lowercase (e.g. "pause"),
+//   NOTE: extensions can add new kinds
+// * sections is a list of top level sections in this menu
+// * selected_section is the currently active top level section, -1 if there are no sections
+// * attrs is an attribute set
+typedef struct MenuState {
+  int8_t *kind;
+  c_array_Section sections;
+  intptr_t sections_length;
+  intptr_t selected_section;
+  c_array_Attribute attrs;
+  intptr_t attrs_length;
+} MenuState;
+
+// State is the aggregated state of the current world
+//
+// See the documentation for each type for a description as to their purpose
+typedef struct State {
+  enum RenderContext render_context;
+  struct WorldState world_state;
+  struct ActorState actor_state;
+  struct MenuState menu_state;
+  c_array_Attribute attrs;
+  intptr_t attrs_length;
+} State;
+
+// Section represents an individual section of a menu
+//
+// In summary, loadout, and inventory menus an intuitive way to represent this would be tabs, since those menus don't have subsections.
+//
+// In main and pause menus an intuitive way to represent this would be a menu tree, as those menus do have (potentially nested) subsections
+// # Fields
+// * title is the title of the section, must not be null
+// * description is text describing the section, may be null
+// * subsections is a list of sections reachable from this section and under it in the hierarchy
+// * items is a list of items in this section,
+//   item may be of the form "t:<string>" in which case it is a plaintext string,
+//   or it may be of the form "s:<index>" in which case it is a subsection at a given index
+//   (see the menu module for a typed form of items)
+// * selected item indicates which item is selected, -1 if there are no items
+// * attrs is an attribute set
+//
+// # Standard Client Attributes
+// * open - the user has descended into this subsection, the section the user is in is the deepest open
+//   subsection reached by following selected items from the selected top level section
+typedef struct Section {
+  int8_t *title;
+  int8_t *description;
+  c_array_Section subsections;
+  intptr_t subsections_length;
+  c_array_Attribute items;
+  intptr_t items_length;
+  intptr_t selected_item;
+  c_array_Attribute attrs;
+  intptr_t attrs_length;
+} Section;
+
+// RenderResult indicates event details for a given frame
+//
+// # Fields
+// * event is the type of event that occured
+// * critical is whether or not the event indicates an unrecoverable error in the renderer
+// * message is the event message, if any
+typedef struct RenderResult {
+  enum RenderEvent event;
+  bool critical;
+  int8_t *message;
+} RenderResult;
+
+typedef void (*FrameCallback)(struct RenderResult, int32_t, struct State*, int8_t*, void*);
+
+typedef void (*UserCallback)(enum UserEvent, int8_t*, void*);
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+// expr_init initializes the server, the server picks one of the client's supported versions
+InitResult expr_init(IncomingMetadata client_metadata);
+
+// expr_push_state renders game_state, invalid states are rejected with ErrorCode_InvalidState
+ExprError expr_push_state(RenderState *render_state, State *game_state);
+
+// expr_frame_callback registers the callback frame events are reported to, replacing the previous one
+ExprError expr_frame_callback(RenderState *render_state, FrameCallback callback, void *user_data);
+
+// expr_user_callback registers the callback user events are reported to, replacing the previous one
+ExprError expr_user_callback(RenderState *render_state, UserCallback callback, void *user_data);
+
+// expr_disconnect is optional, it notifies the server that it is about to be stopped
+// afterwards render_state is only passed to expr_reconnect, once expr_init is rerun instead it is abandoned
+// and the server may free it
+ExprError expr_disconnect(RenderState *render_state);
+
+// expr_reconnect is optional, ErrorCode_NeedsReinit asks the client to rerun expr_init
+ExprError expr_reconnect(RenderState *render_state);
+
+// expr_free is optional, it releases the message of an ExprError returned by the server once the client copied it
+void expr_free(int8_t *message);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif
+
+#endif  // RENDER_API_H
@@ -0,0 +1,83 @@
+//! alloc defines who owns the strings that cross the render API, and implements it for servers written in rust
+//!
+//! # Ownership
+//! * The message of every ExprError returned by an expr_* function (including InitResult's error) is handed
+//!   to the client. If the server exposes expr_free the client passes every one of them to it exactly once,
+//!   after copying it. Servers without expr_free keep ownership, their messages must stay valid until the server
+//!   is unloaded.
+//! * Every other string in InitResult (the server version and the extension lists) is owned by the server
+//!   and stays valid until the server is unloaded, the client never releases them.
+//! * Strings passed to callbacks are owned by whoever calls the callback, and are only valid until it returns.
+//! * Strings in IncomingMetadata and State are owned by the client.
+//!
+//! # Leak checking
+//! Allocations counts the strings handed out on behalf of one owner, such as a server's render state,
+//! so a server's tests can check its count is back to zero after a session. free releases a string
+//! whichever owner allocated it.
+
+use std::collections::BTreeMap;
+use std::ffi::CString;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// FreeFn is the signature of expr_free
+pub type FreeFn = unsafe extern "C" fn(*mut i8);
+
+/// OWNERS maps every string handed out by Allocations::string and not yet released to the count of its owner
+///
+/// # Notes
+/// Only owners are kept here, the counts themselves live in the Allocations
+static OWNERS: Mutex<BTreeMap<usize, Allocations>> = Mutex::new(BTreeMap::new());
+
+/// Allocations counts the strings handed out on behalf of one owner and not yet released by free
+///
+/// # Notes
+/// Clones share the same count
+#[derive(Debug, Clone, Default)]
+pub struct Allocations(Arc<AtomicUsize>);
+
+impl Allocations {
+    /// new creates an owner with no outstanding strings
+    pub fn new() -> Allocations {
+        Allocations::default()
+    }
+
+    /// string allocates message for the client like string, counting it in self until it is released with free
+    pub fn string(&self, message: impl ToString) -> *mut i8 {
+        let string = string(message);
+        self.0.fetch_add(1, Ordering::Relaxed);
+        OWNERS.lock().unwrap_or_else(|e| e.into_inner()).insert(string as usize, self.clone());
+        string
+    }
+
+    /// outstanding is the number of strings allocated through self and not yet released by free
+    pub fn outstanding(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// owns reports whether string was allocated through self (or a clone) and has not been released yet
+    pub fn owns(&self, string: *const i8) -> bool {
+        OWNERS.lock().unwrap_or_else(|e| e.into_inner()).get(&(string as usize)).is_some_and(|owner| Arc::ptr_eq(&owner.0, &self.0))
+    }
+}
+
+/// string allocates message for the client, to be released with free (nul bytes are replaced by spaces)
+///
+/// # Notes
+/// The string is not counted anywhere, it is meant for errors raised before there is an owner to count it in
+pub fn string(message: impl ToString) -> *mut i8 {
+    CString::new(message.to_string().replace('\0', " ")).unwrap_or_default().into_raw()
+}
+
+/// free releases a string allocated by string or Allocations::string, null is ignored
+///
+/// # Safety
+/// string must be null or a pointer returned by string or Allocations::string that has not been released yet
+pub unsafe fn free(string: *mut i8) {
+    if !string.is_null() {
+        if let Some(owner) = OWNERS.lock().unwrap_or_else(|e| e.into_inner()).remove(&(string as usize)) {
+            owner.0.fetch_sub(1, Ordering::Relaxed);
+        }
+        drop(CString::from_raw(string));
+    }
+}
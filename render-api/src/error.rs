@@ -0,0 +1,98 @@
+//! error is the structured error every entry point of the render API returns
+//!
+//! An ExprError pairs an ErrorCode the client can act on with a recoverability flag and an optional message.
+//! Entry points that succeed return ExprError::none.
+
+use std::ffi::CStr;
+use std::ptr::null_mut;
+use crate::c_str;
+
+/// ErrorCode identifies what went wrong in an entry point
+///
+/// # Variants
+/// * None - no error occurred
+/// * Unknown - an error no other code describes, the message should explain it
+/// * UnsupportedVersion - the server speaks none of the protocol versions the client supports
+/// * InvalidState - the game state pushed was rejected
+/// * InvalidRenderState - the render state is null or was not returned by expr_init
+/// * DeviceLost - the device doing the actual rendering is no longer available
+/// * DisplayError - a frame was rendered but could not be displayed
+/// * Unsupported - the server does not support what was asked of it
+/// * NeedsReinit - expr_init must be rerun before the server can be used again, returned by expr_reconnect
+/// * NotLoaded - the server library or one of its symbols could not be loaded, only reported by the client
+///
+/// # Notes
+/// Codes are never renumbered, new codes are only ever added at the end
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ErrorCode {
+    None = 0,
+    Unknown = 1,
+    UnsupportedVersion = 2,
+    InvalidState = 3,
+    InvalidRenderState = 4,
+    DeviceLost = 5,
+    DisplayError = 6,
+    Unsupported = 7,
+    NeedsReinit = 8,
+    NotLoaded = 9
+}
+
+/// ExprError is the outcome of an entry point
+///
+/// # Fields
+/// * code is what went wrong, ErrorCode::None if nothing did
+/// * recoverable is whether the server can still be used, if not it has to be reinitialized or unloaded
+/// * message describes the error, it may be null
+///
+/// # Notes
+/// message is handed to the client, which releases it through expr_free if the server exposes it (see alloc)
+#[repr(C)]
+#[derive(Debug)]
+pub struct ExprError {
+    code: ErrorCode,
+    recoverable: bool,
+    message: *mut i8
+}
+
+impl ExprError {
+    /// none is the result of an entry point that succeeded
+    pub const fn none() -> ExprError {
+        ExprError { code: ErrorCode::None, recoverable: true, message: null_mut() }
+    }
+
+    /// new is a recoverable error, message may be null
+    pub fn new(code: ErrorCode, message: *mut i8) -> ExprError {
+        ExprError { code, recoverable: true, message }
+    }
+
+    /// fatal is an error after which the server cannot be used until it is reinitialized, message may be null
+    pub fn fatal(code: ErrorCode, message: *mut i8) -> ExprError {
+        ExprError { code, recoverable: false, message }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+
+    /// is_error is whether the entry point failed
+    pub fn is_error(&self) -> bool {
+        self.code != ErrorCode::None
+    }
+
+    pub fn recoverable(&self) -> bool {
+        self.recoverable
+    }
+
+    /// # Safety
+    /// message must not have been released yet
+    pub unsafe fn message(&self) -> Option<&CStr> {
+        c_str(self.message)
+    }
+
+    /// message_ptr is the message as returned by the server, to be released once copied
+    pub fn message_ptr(&self) -> *mut i8 {
+        self.message
+    }
+}
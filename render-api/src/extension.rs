@@ -0,0 +1,175 @@
+//! extension defines how extensions to the render protocol are declared and negotiated
+//!
+//! An extension is identified by its name and may
+//! * add symbols (expr_* functions) the client can call on the server,
+//!   a server advertises these through InitResult's server_extensions
+//! * add attributes and draw kinds the client can send through v*::state::State,
+//!   a server advertises these through InitResult's accepted_extensions
+//! * carry metadata from the client to the server through IncomingMetadata's extension_metadata
+//!
+//! The client registers the extensions it supports in an ExtensionRegistry,
+//! only extensions that both sides agree on are exposed after expr_init.
+
+use std::any::Any;
+use std::ffi::CString;
+use std::mem::{align_of, size_of};
+use crate::ExtensionMetadata;
+
+/// Extension is implemented by a type describing a single protocol extension
+///
+/// # Notes
+/// * Metadata is passed to the server by pointer, it should be #[repr(C)]
+///   (use () for extensions without metadata)
+/// * SYMBOLS lists the extra symbols a server must expose to implement the extension
+/// * ATTRIBUTES lists the attribute names (the part before the first :) the extension adds to State
+/// * KINDS lists the draw kinds the extension adds to State, on top of the standard ones (see v0::state::Terrain)
+pub trait Extension {
+    const NAME: &'static str;
+    const SYMBOLS: &'static [&'static str];
+    const ATTRIBUTES: &'static [&'static str];
+    const KINDS: &'static [&'static str] = &[];
+    type Metadata: 'static;
+}
+
+/// ExtensionDescriptor is the runtime description of an Extension
+///
+/// # Fields
+/// * name is the extension name sent across the protocol
+/// * symbols are the symbols the extension adds
+/// * attributes are the attribute names the extension adds
+/// * kinds are the draw kinds the extension adds
+/// * metadata_size and metadata_align describe the layout of the extension metadata
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionDescriptor {
+    pub name: &'static str,
+    pub symbols: &'static [&'static str],
+    pub attributes: &'static [&'static str],
+    pub kinds: &'static [&'static str],
+    pub metadata_size: usize,
+    pub metadata_align: usize
+}
+
+impl ExtensionDescriptor {
+    /// of describes extension E
+    pub fn of<E: Extension>() -> ExtensionDescriptor {
+        ExtensionDescriptor {
+            name: E::NAME,
+            symbols: E::SYMBOLS,
+            attributes: E::ATTRIBUTES,
+            kinds: E::KINDS,
+            metadata_size: size_of::<E::Metadata>(),
+            metadata_align: align_of::<E::Metadata>()
+        }
+    }
+
+    /// is_protocol reports whether this extension adds symbols to the protocol
+    pub fn is_protocol(&self) -> bool {
+        !self.symbols.is_empty()
+    }
+
+    /// is_state reports whether this extension adds attributes or kinds to State
+    pub fn is_state(&self) -> bool {
+        !self.attributes.is_empty() || !self.kinds.is_empty()
+    }
+
+    /// accepted_by reports whether a server advertising the given lists can use this extension
+    ///
+    /// Protocol extensions must be in server_extensions, state extensions must be in accepted_extensions,
+    /// an extension that is neither only needs to be present in one of the two
+    pub fn accepted_by<S: AsRef<str>>(&self, server_extensions: &[S], accepted_extensions: &[S]) -> bool {
+        let contains = |list: &[S]| list.iter().any(|e| e.as_ref() == self.name);
+        match (self.is_protocol(), self.is_state()) {
+            (false, false) => contains(server_extensions) || contains(accepted_extensions),
+            (protocol, state) => (!protocol || contains(server_extensions)) && (!state || contains(accepted_extensions))
+        }
+    }
+}
+
+/// RegisteredExtension is an extension known to the client
+struct RegisteredExtension {
+    descriptor: ExtensionDescriptor,
+    name: CString,
+    metadata: Option<Box<dyn Any>>
+}
+
+/// ExtensionRegistry is the set of extensions supported by the client
+///
+/// Extensions registered with metadata are enabled, extensions registered through support are
+/// advertised as supported but not enabled.
+#[derive(Default)]
+pub struct ExtensionRegistry {
+    extensions: Vec<RegisteredExtension>
+}
+
+impl ExtensionRegistry {
+    pub fn new() -> ExtensionRegistry {
+        ExtensionRegistry::default()
+    }
+
+    /// register adds E as a supported and enabled extension, replacing any earlier registration
+    pub fn register<E: Extension>(&mut self, metadata: E::Metadata) -> &mut ExtensionRegistry {
+        self.insert::<E>(Some(Box::new(metadata)))
+    }
+
+    /// support adds E as a supported extension without enabling it
+    pub fn support<E: Extension>(&mut self) -> &mut ExtensionRegistry {
+        self.insert::<E>(None)
+    }
+
+    fn insert<E: Extension>(&mut self, metadata: Option<Box<dyn Any>>) -> &mut ExtensionRegistry {
+        self.extensions.retain(|e| e.descriptor.name != E::NAME);
+        self.extensions.push(RegisteredExtension {
+            descriptor: ExtensionDescriptor::of::<E>(),
+            name: CString::new(E::NAME).expect("extension name contains a nul byte"),
+            metadata
+        });
+        self
+    }
+
+    /// supported lists every registered extension
+    pub fn supported(&self) -> impl Iterator<Item = &ExtensionDescriptor> {
+        self.extensions.iter().map(|e| &e.descriptor)
+    }
+
+    /// enabled lists every registered extension that is enabled
+    pub fn enabled(&self) -> impl Iterator<Item = &ExtensionDescriptor> {
+        self.extensions.iter().filter(|e| e.metadata.is_some()).map(|e| &e.descriptor)
+    }
+
+    /// metadata is the metadata E was registered with, if E is enabled
+    pub fn metadata<E: Extension>(&self) -> Option<&E::Metadata> {
+        self.extensions.iter()
+            .find(|e| e.descriptor.name == E::NAME)
+            .and_then(|e| e.metadata.as_ref())
+            .and_then(|m| m.downcast_ref())
+    }
+
+    /// negotiate lists the enabled extensions the server can use (see ExtensionDescriptor::accepted_by)
+    pub fn negotiate<S: AsRef<str>>(&self, server_extensions: &[S], accepted_extensions: &[S]) -> Vec<ExtensionDescriptor> {
+        self.enabled()
+            .filter(|e| e.accepted_by(server_extensions, accepted_extensions))
+            .cloned()
+            .collect()
+    }
+
+    /// supported_names are the C strings of every supported extension name
+    pub(crate) fn supported_names(&self) -> Vec<*mut i8> {
+        self.extensions.iter().map(|e| e.name.as_ptr() as *mut i8).collect()
+    }
+
+    /// enabled_names are the C strings of every enabled extension name
+    pub(crate) fn enabled_names(&self) -> Vec<*mut i8> {
+        self.extensions.iter()
+            .filter(|e| e.metadata.is_some())
+            .map(|e| e.name.as_ptr() as *mut i8)
+            .collect()
+    }
+
+    /// enabled_metadata are pointers to the metadata of every enabled extension, in enabled_names order
+    pub(crate) fn enabled_metadata(&mut self) -> Vec<*mut ExtensionMetadata> {
+        self.extensions.iter_mut()
+            .filter_map(|e| e.metadata.as_mut())
+            .map(|m| m.as_mut() as *mut dyn Any as *mut ExtensionMetadata)
+            .collect()
+    }
+}
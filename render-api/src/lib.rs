@@ -1,10 +1,21 @@
 #![allow(non_upper_case_globals)]
 
+use std::ffi::CStr;
+use std::ptr::null_mut;
+use error::ExprError;
+#[cfg(feature = "client")]
+use error::ErrorCode;
 #[cfg(feature = "client")]
 use lazy_static::lazy_static;
 #[cfg(feature = "client")]
 use libloading::Library;
 
+pub mod alloc;
+pub mod error;
+pub mod extension;
+#[cfg(feature = "client")]
+pub mod locate;
+pub mod negotiate;
 pub mod v0;
 
 #[cfg(all(feature = "client", target_os = "windows"))]
@@ -16,9 +27,25 @@ const DYLIB_PATH: &str = "render.dylib";
 #[cfg(all(feature = "client", not(any(target_os = "windows", target_os = "macos", target_os = "ios"))))]
 const DYLIB_PATH: &str = "render.so";
 
+// NOTE: only EXPLORITRON_RENDER selects the library, explicit and configured paths are not known here (see v0::client)
 #[cfg(feature = "client")]
 lazy_static!{
-    static ref library: Library = unsafe { Library::new(DYLIB_PATH).expect("failed to load library") };
+    static ref library: Result<Library, String> = unsafe {
+        Library::new(locate::select_server(None, None)).map_err(|e| e.to_string())
+    };
+}
+
+/// LOAD_ERROR is returned by client functions when the server library or symbol could not be loaded
+///
+/// # Notes
+/// This string is owned by the client and must never be freed
+#[cfg(feature = "client")]
+const LOAD_ERROR: &std::ffi::CStr = c"failed to load render server";
+
+/// load_error is LOAD_ERROR in the form client functions return errors
+#[cfg(feature = "client")]
+fn load_error() -> ExprError {
+    ExprError::fatal(ErrorCode::NotLoaded, LOAD_ERROR.as_ptr() as *mut i8)
 }
 
 /// Render state is a blob that server use to persist state between calls.
@@ -26,12 +53,38 @@ lazy_static!{
 pub type RenderState = ();
 
 /// ExtensionMetadata is a blob that contains the metadata for any enabled extension
+/// The layout of the blob is declared by the extension (see extension::Extension)
 pub type ExtensionMetadata = ();
 
 /// c_array indicates that a pointer refers to an array, not just a single item
 #[allow(non_camel_case_types)]
 pub type c_array<T> = *mut T;
 
+/// c_slice reads a c_array of length items, null arrays and non-positive lengths read as empty
+///
+/// # Safety
+/// If array is non-null and length is positive, array must point to length valid items
+/// that outlive 'a
+pub(crate) unsafe fn c_slice<'a, T>(array: c_array<T>, length: isize) -> &'a [T] {
+    if array.is_null() || length <= 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(array, length as usize)
+    }
+}
+
+/// c_str reads a nullable C string
+///
+/// # Safety
+/// If ptr is non-null it must point to a nul terminated string that outlives 'a
+pub(crate) unsafe fn c_str<'a>(ptr: *const i8) -> Option<&'a CStr> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(ptr))
+    }
+}
+
 /// IncomingMetadata is a struct supplying metadata for the target server
 /// # Fields
 /// * client_version is the version of the core client
@@ -39,7 +92,7 @@ pub type c_array<T> = *mut T;
 /// * supported_extensions is a list of extensions supported by the client
 /// * enabled_extensions lists which of the supported extensions are enabled
 ///   note that enabled_extensions\[some x\] will always be equal to supported_extensions\[some y\]
-/// * extension_metadata is an array of metadata for each extension
+/// * extension_metadata is an array of pointers to the metadata of each enabled extension
 ///   note that extension_metadata\[idx\] refers to extension at enabled_extensions\[idx\]
 ///
 /// # Notes
@@ -54,7 +107,58 @@ pub struct IncomingMetadata {
     supported_extensions_length: isize,
     enabled_extensions: c_array<*mut i8>,
     enabled_extensions_length: isize,
-    extension_metadata: c_array<ExtensionMetadata>
+    extension_metadata: c_array<*mut ExtensionMetadata>
+}
+
+impl IncomingMetadata {
+    /// client_version is the version of the core client, if it was provided
+    ///
+    /// # Safety
+    /// The metadata must be the one passed to expr_init and still be in scope
+    pub unsafe fn client_version(&self) -> Option<&CStr> {
+        c_str(self.client_version)
+    }
+
+    /// supported_versions is the list of server versions the client supports
+    ///
+    /// # Safety
+    /// The metadata must be the one passed to expr_init and still be in scope
+    pub unsafe fn supported_versions(&self) -> Vec<&CStr> {
+        negotiate::string_array(self.supported_versions, self.supported_versions_length)
+    }
+
+    /// supported_extensions is the list of extensions supported by the client
+    ///
+    /// # Safety
+    /// The metadata must be the one passed to expr_init and still be in scope
+    pub unsafe fn supported_extensions(&self) -> Vec<&CStr> {
+        negotiate::string_array(self.supported_extensions, self.supported_extensions_length)
+    }
+
+    /// enabled_extensions is the list of supported extensions the client enabled
+    ///
+    /// # Safety
+    /// The metadata must be the one passed to expr_init and still be in scope
+    pub unsafe fn enabled_extensions(&self) -> Vec<&CStr> {
+        negotiate::string_array(self.enabled_extensions, self.enabled_extensions_length)
+    }
+
+    /// extension_metadata is the metadata the client attached to the enabled extension name
+    ///
+    /// # Safety
+    /// * The metadata must be the one passed to expr_init and still be in scope
+    /// * The returned pointer must only be read as the metadata type of that extension
+    pub unsafe fn extension_metadata(&self, name: &str) -> Option<*mut ExtensionMetadata> {
+        if self.extension_metadata.is_null() || self.enabled_extensions.is_null() {
+            return None;
+        }
+        let idx = (0..self.enabled_extensions_length.max(0) as usize)
+            .find(|idx| {
+                let ext = *self.enabled_extensions.add(*idx);
+                !ext.is_null() && CStr::from_ptr(ext).to_bytes() == name.as_bytes()
+            })?;
+        Some(*self.extension_metadata.add(idx))
+    }
 }
 
 /// InitResult is a struct consisting of all necessary information for the client to utilize the server
@@ -65,13 +169,15 @@ pub struct IncomingMetadata {
 ///   these extensions determine additional functions callable from the client to the server
 /// * accepted_extensions is the list of client extensions the server can display,
 ///   these extensions determine additional information a client can send through v*::state::State
-/// * error is a description of the error the occured during initialization, if any
+/// * error is the error that occured during initialization, if any
 ///
 /// # Notes
 /// * This struct may change while on version 0 of the API.
 ///   Once version 1 is released this struct definition will not change
-/// * If error is non-null that indicates an error occurred
+/// * If error's code is not ErrorCode::None that indicates an error occurred
 /// * If server_version or server_state is null that means the error is unrecoverable
+/// * error's message is released by the client through expr_free (if exposed),
+///   every other string stays owned by the server, see alloc
 #[repr(C)]
 pub struct InitResult {
     server_version: *mut i8,
@@ -80,7 +186,49 @@ pub struct InitResult {
     server_extensions_length: isize,
     accepted_extensions: c_array<*mut i8>,
     accepted_extensions_length: isize,
-    error: *mut i8
+    error: ExprError
+}
+
+impl InitResult {
+    /// new is a successful result using protocol server_version and persisting server_state
+    ///
+    /// Both pointers are owned by the server and must stay valid until the server is unloaded
+    pub fn new(server_version: *mut i8, server_state: *mut RenderState) -> InitResult {
+        InitResult {
+            server_version,
+            server_state,
+            server_extensions: null_mut(),
+            server_extensions_length: 0,
+            accepted_extensions: null_mut(),
+            accepted_extensions_length: 0,
+            error: ExprError::none()
+        }
+    }
+
+    /// failed is an unrecoverable result reporting error
+    pub fn failed(error: ExprError) -> InitResult {
+        InitResult { error, ..InitResult::new(null_mut(), null_mut()) }
+    }
+
+    /// with_error attaches a recoverable error to a result
+    pub fn with_error(self, error: ExprError) -> InitResult {
+        InitResult { error, ..self }
+    }
+
+    /// with_extensions attaches the server's extension lists to a result
+    ///
+    /// Both arrays are owned by the server and must stay valid until the server is unloaded
+    pub fn with_extensions(self,
+                           server_extensions: c_array<*mut i8>, server_extensions_length: isize,
+                           accepted_extensions: c_array<*mut i8>, accepted_extensions_length: isize) -> InitResult {
+        InitResult {
+            server_extensions,
+            server_extensions_length,
+            accepted_extensions,
+            accepted_extensions_length,
+            ..self
+        }
+    }
 }
 
 /// expr_init initializes the render server
@@ -88,9 +236,15 @@ pub struct InitResult {
 ///
 /// The supplied InitResult contains information from implementing expr_init including any errors
 /// that occured
+///
+/// If the server library or expr_init cannot be loaded the InitResult reports an unrecoverable error
 #[cfg(feature = "client")]
 pub unsafe fn init(client_metadata: IncomingMetadata) -> InitResult {
-    library.get::<unsafe extern fn(IncomingMetadata) -> InitResult>(b"expr_init")
-        .expect("failed to load expr_init")(client_metadata)
+    let init = library.as_ref().ok()
+        .and_then(|l| l.get::<unsafe extern fn(IncomingMetadata) -> InitResult>(b"expr_init").ok());
+    match init {
+        Some(init) => init(client_metadata),
+        None => InitResult::failed(load_error())
+    }
 }
 
@@ -0,0 +1,88 @@
+//! locate decides which render server library the client loads
+//!
+//! A server is selected from, in order of precedence:
+//! 1. a path given explicitly by the client (e.g. a CLI flag)
+//! 2. the `EXPLORITRON_RENDER` environment variable
+//! 3. a path read by the client from its configuration
+//! 4. the platform default library name in the working directory
+//!
+//! Candidate servers can be listed from a search path, which defaults to the working directory
+//! and can be overridden with the `EXPLORITRON_RENDER_PATH` environment variable
+//! (a list of directories using the platform path separator).
+
+use std::env;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+/// RENDER_ENV is the environment variable naming the render server library to load
+pub const RENDER_ENV: &str = "EXPLORITRON_RENDER";
+
+/// RENDER_PATH_ENV is the environment variable listing directories searched for render servers
+pub const RENDER_PATH_ENV: &str = "EXPLORITRON_RENDER_PATH";
+
+#[cfg(target_os = "windows")]
+const DYLIB_EXTENSION: &str = "dll";
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+const DYLIB_EXTENSION: &str = "dylib";
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "ios")))]
+const DYLIB_EXTENSION: &str = "so";
+
+/// default_server is the library loaded when no server has been selected
+pub fn default_server() -> PathBuf {
+    PathBuf::from(crate::DYLIB_PATH)
+}
+
+/// select_server picks the render server library to load
+///
+/// # Arguments
+/// * explicit is a path chosen directly by the user, takes precedence over everything else
+/// * configured is a path read from the client configuration, used if no environment override exists
+pub fn select_server(explicit: Option<&Path>, configured: Option<&Path>) -> PathBuf {
+    selected_server(explicit, configured).unwrap_or_else(default_server)
+}
+
+/// selected_server is select_server without the default, None if no server has been selected
+///
+/// Clients with a server linked in (see v0::backend::StaticBackend) use it in place of the default
+pub fn selected_server(explicit: Option<&Path>, configured: Option<&Path>) -> Option<PathBuf> {
+    if let Some(path) = explicit {
+        return Some(path.to_path_buf());
+    }
+    if let Some(path) = env::var_os(RENDER_ENV).filter(|p| !p.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    configured.map(Path::to_path_buf)
+}
+
+/// search_path is the list of directories candidate servers are looked for in
+pub fn search_path() -> Vec<PathBuf> {
+    match env::var_os(RENDER_PATH_ENV).filter(|p| !p.is_empty()) {
+        Some(paths) => env::split_paths(&paths).collect(),
+        None => vec![PathBuf::from(".")]
+    }
+}
+
+/// is_server_library reports whether path names a dynamic library for the current platform
+pub fn is_server_library(path: &Path) -> bool {
+    path.extension() == Some(OsStr::new(DYLIB_EXTENSION))
+}
+
+/// find_servers lists every dynamic library found directly inside the given directories
+///
+/// # Notes
+/// * Directories that cannot be read are skipped
+/// * A library being listed does not mean it conforms to the render API
+pub fn find_servers<I, P>(directories: I) -> Vec<PathBuf>
+    where I: IntoIterator<Item = P>, P: AsRef<Path> {
+    let mut servers: Vec<PathBuf> = directories.into_iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.filter_map(Result::ok))
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && is_server_library(path))
+        .collect();
+    servers.sort();
+    servers.dedup();
+    servers
+}
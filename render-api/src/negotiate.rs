@@ -0,0 +1,154 @@
+//! negotiate contains the logic used by both sides of expr_init to agree on a protocol
+//!
+//! Versions are dotted strings (e.g. "0", "1.2"), components are compared numerically when possible.
+//! The server picks the highest version it shares with the client and reports it as server_version,
+//! the client then rejects any server_version it did not list as supported.
+
+use std::cmp::Ordering;
+use std::ffi::{CStr, CString};
+use crate::{c_array, c_slice, c_str, ExtensionMetadata, IncomingMetadata};
+use crate::extension::ExtensionRegistry;
+
+/// API_VERSIONS is every render API version this crate implements, oldest first
+pub const API_VERSIONS: &[&str] = &["0"];
+
+/// compare_versions orders two dotted version strings
+///
+/// # Notes
+/// Numeric components compare numerically, anything else compares as text,
+/// a version that is a prefix of another is the lesser of the two (e.g. "1" < "1.0")
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let mut a = a.split('.');
+    let mut b = b.split('.');
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => {
+                let ordering = match (x.parse::<u64>(), y.parse::<u64>()) {
+                    (Ok(x), Ok(y)) => x.cmp(&y),
+                    _ => x.cmp(y)
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+        }
+    }
+}
+
+/// negotiate_version picks the highest version supported by both the client and the server
+///
+/// None means the two sides have no version in common
+pub fn negotiate_version<'a, C, S>(client: &[C], server: &'a [S]) -> Option<&'a str>
+    where C: AsRef<str>, S: AsRef<str> {
+    server.iter()
+        .map(AsRef::as_ref)
+        .filter(|version| client.iter().any(|c| c.as_ref() == *version))
+        .max_by(|a, b| compare_versions(a, b))
+}
+
+/// check_server_version is the client side check of the server_version returned by expr_init
+///
+/// Returns the version on success, or a description of why it was rejected
+pub fn check_server_version<'a, C: AsRef<str>>(supported: &[C], server_version: &'a str) -> Result<&'a str, String> {
+    if supported.iter().any(|v| v.as_ref() == server_version) {
+        Ok(server_version)
+    } else {
+        let supported: Vec<&str> = supported.iter().map(AsRef::as_ref).collect();
+        Err(format!("server version {} is not one of the supported versions [{}]",
+                    server_version, supported.join(", ")))
+    }
+}
+
+/// ClientMetadata owns everything an IncomingMetadata points to
+///
+/// The IncomingMetadata returned by as_incoming is only valid while the ClientMetadata is alive
+/// and has not been modified.
+pub struct ClientMetadata {
+    client_version: CString,
+    supported_versions: Vec<CString>,
+    supported_version_ptrs: Vec<*mut i8>,
+    extensions: ExtensionRegistry,
+    supported_extension_ptrs: Vec<*mut i8>,
+    enabled_extension_ptrs: Vec<*mut i8>,
+    extension_metadata_ptrs: Vec<*mut ExtensionMetadata>
+}
+
+impl ClientMetadata {
+    /// new creates metadata for a client supporting every version in API_VERSIONS
+    ///
+    /// # Panics
+    /// Panics if client_version contains a nul byte
+    pub fn new(client_version: &str) -> ClientMetadata {
+        ClientMetadata::with_versions(client_version, API_VERSIONS)
+    }
+
+    /// with_versions creates metadata for a client supporting the given versions
+    ///
+    /// # Panics
+    /// Panics if any version contains a nul byte
+    pub fn with_versions<S: AsRef<str>>(client_version: &str, supported_versions: &[S]) -> ClientMetadata {
+        let supported_versions: Vec<CString> = supported_versions.iter()
+            .map(|v| CString::new(v.as_ref()).expect("version contains a nul byte"))
+            .collect();
+        let supported_version_ptrs = supported_versions.iter()
+            .map(|v| v.as_ptr() as *mut i8)
+            .collect();
+        ClientMetadata {
+            client_version: CString::new(client_version).expect("version contains a nul byte"),
+            supported_versions,
+            supported_version_ptrs,
+            extensions: ExtensionRegistry::new(),
+            supported_extension_ptrs: Vec::new(),
+            enabled_extension_ptrs: Vec::new(),
+            extension_metadata_ptrs: Vec::new()
+        }
+    }
+
+    /// with_extensions replaces the extensions advertised to the server
+    pub fn with_extensions(self, extensions: ExtensionRegistry) -> ClientMetadata {
+        ClientMetadata { extensions, ..self }
+    }
+
+    /// extensions is the registry of extensions advertised to the server
+    pub fn extensions(&self) -> &ExtensionRegistry {
+        &self.extensions
+    }
+
+    /// client_version is the version of the core client
+    pub fn client_version(&self) -> &str {
+        self.client_version.to_str().unwrap_or_default()
+    }
+
+    /// supported_versions is the list of server versions the client supports
+    pub fn supported_versions(&self) -> Vec<&str> {
+        self.supported_versions.iter().map(|v| v.to_str().unwrap_or_default()).collect()
+    }
+
+    /// as_incoming lowers this metadata into the form passed to expr_init
+    pub fn as_incoming(&mut self) -> IncomingMetadata {
+        self.supported_extension_ptrs = self.extensions.supported_names();
+        self.enabled_extension_ptrs = self.extensions.enabled_names();
+        self.extension_metadata_ptrs = self.extensions.enabled_metadata();
+        IncomingMetadata {
+            client_version: self.client_version.as_ptr() as *mut i8,
+            supported_versions: self.supported_version_ptrs.as_mut_ptr(),
+            supported_versions_length: self.supported_version_ptrs.len() as isize,
+            supported_extensions: self.supported_extension_ptrs.as_mut_ptr(),
+            supported_extensions_length: self.supported_extension_ptrs.len() as isize,
+            enabled_extensions: self.enabled_extension_ptrs.as_mut_ptr(),
+            enabled_extensions_length: self.enabled_extension_ptrs.len() as isize,
+            extension_metadata: self.extension_metadata_ptrs.as_mut_ptr()
+        }
+    }
+}
+
+/// string_array reads a c_array of C strings, null arrays and negative lengths read as empty
+///
+/// # Safety
+/// If array is non-null it must point to length valid, nul terminated strings
+pub(crate) unsafe fn string_array<'a>(array: c_array<*mut i8>, length: isize) -> Vec<&'a CStr> {
+    c_slice(array, length).iter().filter_map(|s| c_str(*s)).collect()
+}
@@ -0,0 +1,346 @@
+//! attribute is a typed form of the "name:value" Attribute strings used throughout state
+//!
+//! Arguments are separated by ':', a literal ':' inside an argument is written as "\:"
+//! and a literal '\' as "\\", any other escape is an error. The standard attributes documented on state::Actor and state::Terrain
+//! are parsed and validated, any other attribute (e.g. one added by an extension) is kept as is.
+
+use std::error::Error;
+use std::ffi::{CStr, CString, NulError};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Control is the value of a control attribute
+///
+/// # Variants
+/// * Current - the actor is currently being controlled
+/// * Standby - the actor can be selected for control
+/// * Distant - the actor is controllable but can not currently be selected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Control {
+    Current,
+    Standby,
+    Distant
+}
+
+impl Control {
+    fn name(&self) -> &'static str {
+        match self {
+            Control::Current => "current",
+            Control::Standby => "standby",
+            Control::Distant => "distant"
+        }
+    }
+}
+
+/// Attr is a single parsed attribute
+///
+/// # Variants
+/// * Control - control:\<current|standby|distant\>
+/// * Status - status:\<text\>
+/// * Affinity - affinity:\<text\>
+/// * AffinityInteraction - affinity_interaction:\<name\>:\<value\>
+/// * Stat - stat:\<name\>:\<value\>\[:delta\]
+/// * Note - note:\<text\>
+/// * KindNote - kind_note:\<text\>
+/// * Type - type:\<text\>
+/// * Resource - resource:\<name\>:\<value\>
+/// * Other - any other attribute, name is the text before the first ':' and args are the rest
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Attr {
+    Control(Control),
+    Status(String),
+    Affinity(String),
+    AffinityInteraction { name: String, value: String },
+    Stat { name: String, value: i64, delta: Option<i64> },
+    Note(String),
+    KindNote(String),
+    Type(String),
+    Resource { name: String, value: i64 },
+    Other { name: String, args: Vec<String> }
+}
+
+/// AttributeError is why an attribute could not be parsed or serialized
+///
+/// # Variants
+/// * Empty - the attribute has no name
+/// * InvalidUtf8 - the attribute is not valid utf-8
+/// * Arity - a standard attribute has the wrong number of arguments
+/// * InvalidControl - a control attribute is not current, standby or distant
+/// * InvalidNumber - a numeric argument of a standard attribute is not an integer
+/// * InvalidEscape - the attribute contains a '\' that is not followed by ':' or '\'
+/// * Nul - an attribute contains a nul byte and can not be made into a C string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttributeError {
+    Empty,
+    InvalidUtf8,
+    Arity { name: String, found: usize },
+    InvalidControl(String),
+    InvalidNumber { name: String, value: String },
+    InvalidEscape(String),
+    Nul(String)
+}
+
+impl Display for AttributeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AttributeError::Empty => write!(f, "attribute has no name"),
+            AttributeError::InvalidUtf8 => write!(f, "attribute is not valid utf-8"),
+            AttributeError::Arity { name, found } => write!(f, "{} attribute does not take {} arguments", name, found),
+            AttributeError::InvalidControl(value) => write!(f, "{} is not a valid control", value),
+            AttributeError::InvalidNumber { name, value } => write!(f, "{} attribute expects a number, not {}", name, value),
+            AttributeError::InvalidEscape(attr) => write!(f, "attribute {:?} contains an invalid escape", attr),
+            AttributeError::Nul(attr) => write!(f, "attribute {:?} contains a nul byte", attr)
+        }
+    }
+}
+
+impl Error for AttributeError {}
+
+impl From<NulError> for AttributeError {
+    fn from(e: NulError) -> AttributeError {
+        AttributeError::Nul(String::from_utf8_lossy(&e.into_vec()).into_owned())
+    }
+}
+
+/// split splits an attribute on unescaped ':' and unescapes every part
+fn split(attr: &str) -> Result<Vec<String>, AttributeError> {
+    let mut parts = vec![String::new()];
+    let mut chars = attr.chars();
+    while let Some(c) = chars.next() {
+        let part = parts.last_mut().unwrap();
+        match c {
+            '\\' => match chars.next() {
+                Some(c @ (':' | '\\')) => part.push(c),
+                // NOTE: escape never writes any other escape, so accepting one would not round-trip
+                _ => return Err(AttributeError::InvalidEscape(attr.to_string()))
+            },
+            ':' => parts.push(String::new()),
+            c => part.push(c)
+        }
+    }
+    Ok(parts)
+}
+
+/// escape escapes part so it can be joined with ':'
+fn escape(part: &str) -> String {
+    part.replace('\\', "\\\\").replace(':', "\\:")
+}
+
+fn number(name: &str, value: &str) -> Result<i64, AttributeError> {
+    value.parse().map_err(|_| AttributeError::InvalidNumber { name: name.to_string(), value: value.to_string() })
+}
+
+impl FromStr for Attr {
+    type Err = AttributeError;
+
+    fn from_str(attr: &str) -> Result<Attr, AttributeError> {
+        let mut parts = split(attr)?;
+        let name = parts.remove(0);
+        if name.is_empty() {
+            return Err(AttributeError::Empty);
+        }
+        let arity = |expected: &[usize]| if expected.contains(&parts.len()) {
+            Ok(())
+        } else {
+            Err(AttributeError::Arity { name: name.clone(), found: parts.len() })
+        };
+        let attr = match name.as_str() {
+            "control" => {
+                arity(&[1])?;
+                match parts[0].as_str() {
+                    "current" => Attr::Control(Control::Current),
+                    "standby" => Attr::Control(Control::Standby),
+                    "distant" => Attr::Control(Control::Distant),
+                    other => return Err(AttributeError::InvalidControl(other.to_string()))
+                }
+            }
+            "status" | "affinity" | "note" | "kind_note" | "type" => {
+                arity(&[1])?;
+                let text = parts.remove(0);
+                match name.as_str() {
+                    "status" => Attr::Status(text),
+                    "affinity" => Attr::Affinity(text),
+                    "note" => Attr::Note(text),
+                    "kind_note" => Attr::KindNote(text),
+                    _ => Attr::Type(text)
+                }
+            }
+            "affinity_interaction" => {
+                arity(&[2])?;
+                Attr::AffinityInteraction { value: parts.remove(1), name: parts.remove(0) }
+            }
+            "stat" => {
+                arity(&[2, 3])?;
+                Attr::Stat {
+                    value: number(&name, &parts[1])?,
+                    delta: parts.get(2).map(|delta| number(&name, delta)).transpose()?,
+                    name: parts.remove(0)
+                }
+            }
+            "resource" => {
+                arity(&[2])?;
+                Attr::Resource { value: number(&name, &parts[1])?, name: parts.remove(0) }
+            }
+            _ => Attr::Other { name, args: parts }
+        };
+        Ok(attr)
+    }
+}
+
+impl Display for Attr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Attr::Control(control) => write!(f, "control:{}", control.name()),
+            Attr::Status(text) => write!(f, "status:{}", escape(text)),
+            Attr::Affinity(text) => write!(f, "affinity:{}", escape(text)),
+            Attr::AffinityInteraction { name, value } =>
+                write!(f, "affinity_interaction:{}:{}", escape(name), escape(value)),
+            Attr::Stat { name, value, delta: Some(delta) } => write!(f, "stat:{}:{}:{}", escape(name), value, delta),
+            Attr::Stat { name, value, delta: None } => write!(f, "stat:{}:{}", escape(name), value),
+            Attr::Note(text) => write!(f, "note:{}", escape(text)),
+            Attr::KindNote(text) => write!(f, "kind_note:{}", escape(text)),
+            Attr::Type(text) => write!(f, "type:{}", escape(text)),
+            Attr::Resource { name, value } => write!(f, "resource:{}:{}", escape(name), value),
+            Attr::Other { name, args } => {
+                write!(f, "{}", escape(name))?;
+                args.iter().try_for_each(|arg| write!(f, ":{}", escape(arg)))
+            }
+        }
+    }
+}
+
+impl Attr {
+    /// from_c_str parses an attribute received from a C string
+    pub fn from_c_str(attr: &CStr) -> Result<Attr, AttributeError> {
+        attr.to_str().map_err(|_| AttributeError::InvalidUtf8)?.parse()
+    }
+
+    /// to_c_string serializes this attribute into a C string
+    pub fn to_c_string(&self) -> Result<CString, AttributeError> {
+        Ok(CString::new(self.to_string())?)
+    }
+
+    /// name is the name of this attribute, e.g. "stat" for Attr::Stat
+    pub fn name(&self) -> &str {
+        match self {
+            Attr::Control(_) => "control",
+            Attr::Status(_) => "status",
+            Attr::Affinity(_) => "affinity",
+            Attr::AffinityInteraction { .. } => "affinity_interaction",
+            Attr::Stat { .. } => "stat",
+            Attr::Note(_) => "note",
+            Attr::KindNote(_) => "kind_note",
+            Attr::Type(_) => "type",
+            Attr::Resource { .. } => "resource",
+            Attr::Other { name, .. } => name
+        }
+    }
+}
+
+/// AttributeSet is an ordered set of parsed attributes
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AttributeSet {
+    attrs: Vec<Attr>
+}
+
+impl AttributeSet {
+    pub fn new() -> AttributeSet {
+        AttributeSet::default()
+    }
+
+    /// parse parses every attribute in attrs, failing on the first invalid one
+    pub fn parse<'a>(attrs: impl IntoIterator<Item = &'a str>) -> Result<AttributeSet, AttributeError> {
+        let attrs = attrs.into_iter().map(str::parse).collect::<Result<_, _>>()?;
+        Ok(AttributeSet { attrs })
+    }
+
+    /// from_c_strs is parse for attributes read from C strings (e.g. state::Strings)
+    pub fn from_c_strs<'a>(attrs: impl IntoIterator<Item = &'a CStr>) -> Result<AttributeSet, AttributeError> {
+        let attrs = attrs.into_iter().map(Attr::from_c_str).collect::<Result<_, _>>()?;
+        Ok(AttributeSet { attrs })
+    }
+
+    pub fn push(&mut self, attr: Attr) {
+        self.attrs.push(attr);
+    }
+
+    /// with is push in builder form
+    pub fn with(mut self, attr: Attr) -> AttributeSet {
+        self.push(attr);
+        self
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &Attr> {
+        self.attrs.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.attrs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.attrs.is_empty()
+    }
+
+    /// named lists every attribute called name
+    pub fn named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Attr> {
+        self.attrs.iter().filter(move |a| a.name() == name)
+    }
+
+    /// control is the first control attribute, if any
+    pub fn control(&self) -> Option<Control> {
+        self.attrs.iter().find_map(|a| match a {
+            Attr::Control(control) => Some(*control),
+            _ => None
+        })
+    }
+
+    /// statuses lists the text of every status attribute
+    pub fn statuses(&self) -> impl Iterator<Item = &str> {
+        self.attrs.iter().filter_map(|a| match a {
+            Attr::Status(status) => Some(status.as_str()),
+            _ => None
+        })
+    }
+
+    /// stat is the value and delta of the stat called name, if any
+    pub fn stat(&self, name: &str) -> Option<(i64, Option<i64>)> {
+        self.attrs.iter().find_map(|a| match a {
+            Attr::Stat { name: stat, value, delta } if stat == name => Some((*value, *delta)),
+            _ => None
+        })
+    }
+
+    /// resource is the quantity of the resource called name, if any
+    pub fn resource(&self, name: &str) -> Option<i64> {
+        self.attrs.iter().find_map(|a| match a {
+            Attr::Resource { name: resource, value } if resource == name => Some(*value),
+            _ => None
+        })
+    }
+
+    /// to_strings serializes every attribute, e.g. for the attrs of an owned state
+    pub fn to_strings(&self) -> Vec<String> {
+        self.attrs.iter().map(Attr::to_string).collect()
+    }
+
+    /// to_c_strings serializes every attribute into C strings
+    pub fn to_c_strings(&self) -> Result<Vec<CString>, AttributeError> {
+        self.attrs.iter().map(Attr::to_c_string).collect()
+    }
+}
+
+impl FromIterator<Attr> for AttributeSet {
+    fn from_iter<T: IntoIterator<Item = Attr>>(iter: T) -> AttributeSet {
+        AttributeSet { attrs: iter.into_iter().collect() }
+    }
+}
+
+impl<'a> IntoIterator for &'a AttributeSet {
+    type Item = &'a Attr;
+    type IntoIter = std::slice::Iter<'a, Attr>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.attrs.iter()
+    }
+}
@@ -0,0 +1,308 @@
+//! backend abstracts how RenderServer reaches the entry points of a render server
+//!
+//! DylibBackend loads a server library with libloading, StaticBackend calls a server crate linked into the client
+//! and other backends (such as mock::MockBackend) implement the entry points in process.
+//! Every method mirrors the expr_* symbol of the same name.
+
+use std::ffi::{c_void, OsStr};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use libloading::Library;
+use crate::{IncomingMetadata, InitResult, RenderState};
+use crate::alloc::FreeFn;
+use crate::error::ExprError;
+use super::handle::{FrameCallback, RenderError, UserCallback};
+use super::state::State;
+
+pub type InitFn = unsafe extern "C" fn(IncomingMetadata) -> InitResult;
+pub type LifecycleFn = unsafe extern "C" fn(*mut RenderState) -> ExprError;
+pub type PushStateFn = unsafe extern "C" fn(*mut RenderState, *mut State) -> ExprError;
+pub type FrameCallbackFn = unsafe extern "C" fn(*mut RenderState, FrameCallback, *mut c_void) -> ExprError;
+pub type UserCallbackFn = unsafe extern "C" fn(*mut RenderState, UserCallback, *mut c_void) -> ExprError;
+
+/// RenderBackend is the set of entry points of a render server
+///
+/// # Notes
+/// * Return values follow the render API, an error's message is handed back to free once copied
+///   (see alloc for who owns which strings)
+/// * disconnect and reconnect return None when the server does not implement them
+pub trait RenderBackend {
+    /// init runs expr_init
+    ///
+    /// # Safety
+    /// metadata must stay valid until the server is dropped
+    unsafe fn init(&self, metadata: IncomingMetadata) -> InitResult;
+
+    /// push_state runs expr_push_state
+    ///
+    /// # Safety
+    /// render_state must come from init and game_state must be valid for the duration of the call
+    unsafe fn push_state(&self, render_state: *mut RenderState, game_state: *mut State) -> ExprError;
+
+    /// frame_callback runs expr_frame_callback, user_data is handed back to every call of callback
+    ///
+    /// # Safety
+    /// render_state must come from init and user_data must stay valid until another callback is registered
+    unsafe fn frame_callback(&self, render_state: *mut RenderState, callback: FrameCallback, user_data: *mut c_void) -> ExprError;
+
+    /// user_callback runs expr_user_callback, user_data is handed back to every call of callback
+    ///
+    /// # Safety
+    /// render_state must come from init and user_data must stay valid until another callback is registered
+    unsafe fn user_callback(&self, render_state: *mut RenderState, callback: UserCallback, user_data: *mut c_void) -> ExprError;
+
+    /// disconnect runs expr_disconnect
+    ///
+    /// # Safety
+    /// render_state must come from init
+    unsafe fn disconnect(&self, render_state: *mut RenderState) -> Option<ExprError>;
+
+    /// reconnect runs expr_reconnect
+    ///
+    /// # Safety
+    /// render_state must come from init
+    unsafe fn reconnect(&self, render_state: *mut RenderState) -> Option<ExprError>;
+
+    /// free runs expr_free, servers without it keep their error messages so nothing is done
+    ///
+    /// # Safety
+    /// string must be an error message returned by this backend that has not been freed yet
+    unsafe fn free(&self, _string: *mut i8) {}
+
+    /// symbol resolves an additional symbol (such as an extension's), None if the server does not expose it
+    fn symbol(&self, symbol: &str) -> Option<*const c_void>;
+
+    /// exposes reports whether the server exposes every symbol in symbols
+    fn exposes(&self, symbols: &[&str]) -> bool {
+        symbols.iter().all(|symbol| self.symbol(symbol).is_some())
+    }
+
+    /// path is the file the server was loaded from, if any
+    fn path(&self) -> Option<&Path> {
+        None
+    }
+
+    /// reload loads the server again, the current backend is left untouched
+    fn reload(&self) -> Result<Box<dyn RenderBackend>, RenderError> {
+        Err(RenderError::Load("render server cannot be reloaded".to_string()))
+    }
+}
+
+/// DylibBackend is a server library loaded with libloading and the entry points resolved from it
+pub struct DylibBackend {
+    path: PathBuf,
+    init: InitFn,
+    push_state: PushStateFn,
+    frame_callback: FrameCallbackFn,
+    user_callback: UserCallbackFn,
+    disconnect: Option<LifecycleFn>,
+    reconnect: Option<LifecycleFn>,
+    free: Option<FreeFn>,
+    // NOTE: library must outlive every function pointer above, and be unloaded before its shadow is removed
+    library: Library,
+    _shadow: Option<ShadowCopy>
+}
+
+impl DylibBackend {
+    /// open loads the library at path and resolves every required entry point
+    ///
+    /// # Safety
+    /// Loading a library runs its initialization routines, only servers conforming to the render API should be opened
+    pub unsafe fn open(path: impl AsRef<Path>) -> Result<DylibBackend, RenderError> {
+        DylibBackend::load(path.as_ref(), false)
+    }
+
+    /// load loads the library at path and resolves every entry point
+    ///
+    /// If shadow is set the library is copied to a unique temporary file and the copy is loaded,
+    /// this keeps the platform from handing back a cached library and leaves path free to be rewritten
+    unsafe fn load(path: &Path, shadow: bool) -> Result<DylibBackend, RenderError> {
+        let shadow = if shadow { Some(ShadowCopy::new(path)?) } else { None };
+        let library = Library::new(shadow.as_ref().map_or(path, |s| s.0.as_path()))
+            .map_err(|e| RenderError::Load(e.to_string()))?;
+        Ok(DylibBackend {
+            path: path.to_path_buf(),
+            init: *library.get::<InitFn>(b"expr_init")
+                .map_err(|_| RenderError::MissingSymbol("expr_init"))?,
+            push_state: *library.get::<PushStateFn>(b"expr_push_state")
+                .map_err(|_| RenderError::MissingSymbol("expr_push_state"))?,
+            frame_callback: *library.get::<FrameCallbackFn>(b"expr_frame_callback")
+                .map_err(|_| RenderError::MissingSymbol("expr_frame_callback"))?,
+            user_callback: *library.get::<UserCallbackFn>(b"expr_user_callback")
+                .map_err(|_| RenderError::MissingSymbol("expr_user_callback"))?,
+            disconnect: library.get::<LifecycleFn>(b"expr_disconnect").ok().map(|f| *f),
+            reconnect: library.get::<LifecycleFn>(b"expr_reconnect").ok().map(|f| *f),
+            free: library.get::<FreeFn>(b"expr_free").ok().map(|f| *f),
+            library,
+            _shadow: shadow
+        })
+    }
+}
+
+impl RenderBackend for DylibBackend {
+    unsafe fn init(&self, metadata: IncomingMetadata) -> InitResult {
+        (self.init)(metadata)
+    }
+
+    unsafe fn push_state(&self, render_state: *mut RenderState, game_state: *mut State) -> ExprError {
+        (self.push_state)(render_state, game_state)
+    }
+
+    unsafe fn frame_callback(&self, render_state: *mut RenderState, callback: FrameCallback, user_data: *mut c_void) -> ExprError {
+        (self.frame_callback)(render_state, callback, user_data)
+    }
+
+    unsafe fn user_callback(&self, render_state: *mut RenderState, callback: UserCallback, user_data: *mut c_void) -> ExprError {
+        (self.user_callback)(render_state, callback, user_data)
+    }
+
+    unsafe fn disconnect(&self, render_state: *mut RenderState) -> Option<ExprError> {
+        self.disconnect.map(|disconnect| disconnect(render_state))
+    }
+
+    unsafe fn reconnect(&self, render_state: *mut RenderState) -> Option<ExprError> {
+        self.reconnect.map(|reconnect| reconnect(render_state))
+    }
+
+    unsafe fn free(&self, string: *mut i8) {
+        if let Some(free) = self.free {
+            free(string)
+        }
+    }
+
+    fn symbol(&self, symbol: &str) -> Option<*const c_void> {
+        unsafe { self.library.get::<*const c_void>(symbol.as_bytes()).ok().map(|s| *s) }
+    }
+
+    fn path(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
+
+    /// reload loads a temporary copy of the library, so the platform cannot hand back the loaded one
+    fn reload(&self) -> Result<Box<dyn RenderBackend>, RenderError> {
+        Ok(Box::new(unsafe { DylibBackend::load(&self.path, true)? }))
+    }
+}
+
+/// StaticBackend is a server crate linked into the client, called through its expr_* functions
+///
+/// # Examples
+/// ```ignore
+/// let backend = StaticBackend::new(render::expr_init, render::expr_push_state,
+///                                  render::expr_frame_callback, render::expr_user_callback)
+///     .with_disconnect(render::expr_disconnect)
+///     .with_reconnect(render::expr_reconnect)
+///     .with_free(render::expr_free);
+/// ```
+///
+/// # Notes
+/// A linked server cannot be reloaded, RenderServer::reload fails and leaves it running
+#[derive(Clone)]
+pub struct StaticBackend {
+    init: InitFn,
+    push_state: PushStateFn,
+    frame_callback: FrameCallbackFn,
+    user_callback: UserCallbackFn,
+    disconnect: Option<LifecycleFn>,
+    reconnect: Option<LifecycleFn>,
+    free: Option<FreeFn>,
+    symbols: Vec<(&'static str, *const c_void)>
+}
+
+impl StaticBackend {
+    /// new is a server implementing only the required entry points
+    pub fn new(init: InitFn, push_state: PushStateFn, frame_callback: FrameCallbackFn, user_callback: UserCallbackFn) -> StaticBackend {
+        StaticBackend {
+            init,
+            push_state,
+            frame_callback,
+            user_callback,
+            disconnect: None,
+            reconnect: None,
+            free: None,
+            symbols: Vec::new()
+        }
+    }
+
+    pub fn with_disconnect(self, disconnect: LifecycleFn) -> StaticBackend {
+        StaticBackend { disconnect: Some(disconnect), ..self }
+    }
+
+    pub fn with_reconnect(self, reconnect: LifecycleFn) -> StaticBackend {
+        StaticBackend { reconnect: Some(reconnect), ..self }
+    }
+
+    pub fn with_free(self, free: FreeFn) -> StaticBackend {
+        StaticBackend { free: Some(free), ..self }
+    }
+
+    /// with_symbol exposes an additional symbol, such as an extension's (see RenderServer::extension_symbol)
+    ///
+    /// # Safety
+    /// symbol must be the function the extension documents for name
+    pub unsafe fn with_symbol(mut self, name: &'static str, symbol: *const c_void) -> StaticBackend {
+        self.symbols.push((name, symbol));
+        self
+    }
+}
+
+impl RenderBackend for StaticBackend {
+    unsafe fn init(&self, metadata: IncomingMetadata) -> InitResult {
+        (self.init)(metadata)
+    }
+
+    unsafe fn push_state(&self, render_state: *mut RenderState, game_state: *mut State) -> ExprError {
+        (self.push_state)(render_state, game_state)
+    }
+
+    unsafe fn frame_callback(&self, render_state: *mut RenderState, callback: FrameCallback, user_data: *mut c_void) -> ExprError {
+        (self.frame_callback)(render_state, callback, user_data)
+    }
+
+    unsafe fn user_callback(&self, render_state: *mut RenderState, callback: UserCallback, user_data: *mut c_void) -> ExprError {
+        (self.user_callback)(render_state, callback, user_data)
+    }
+
+    unsafe fn disconnect(&self, render_state: *mut RenderState) -> Option<ExprError> {
+        self.disconnect.map(|disconnect| disconnect(render_state))
+    }
+
+    unsafe fn reconnect(&self, render_state: *mut RenderState) -> Option<ExprError> {
+        self.reconnect.map(|reconnect| reconnect(render_state))
+    }
+
+    unsafe fn free(&self, string: *mut i8) {
+        if let Some(free) = self.free {
+            free(string)
+        }
+    }
+
+    fn symbol(&self, symbol: &str) -> Option<*const c_void> {
+        self.symbols.iter().find(|(name, _)| *name == symbol).map(|(_, symbol)| *symbol)
+    }
+}
+
+/// ShadowCopy is a temporary copy of a server library, removed on drop
+struct ShadowCopy(PathBuf);
+
+impl ShadowCopy {
+    /// new copies the library at path to a unique file in the temporary directory
+    fn new(path: &Path) -> Result<ShadowCopy, RenderError> {
+        static COPIES: AtomicUsize = AtomicUsize::new(0);
+        let stem = path.file_stem().unwrap_or_else(|| OsStr::new("render")).to_string_lossy();
+        let mut name = format!("{}-{}-{}", stem, std::process::id(), COPIES.fetch_add(1, Ordering::Relaxed));
+        if let Some(extension) = path.extension() {
+            name = format!("{}.{}", name, extension.to_string_lossy());
+        }
+        let shadow = std::env::temp_dir().join(name);
+        std::fs::copy(path, &shadow)
+            .map_err(|e| RenderError::Load(format!("{}: {}", path.display(), e)))?;
+        Ok(ShadowCopy(shadow))
+    }
+}
+
+impl Drop for ShadowCopy {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
@@ -1,12 +1,28 @@
+//! client calls the entry points of the server library through free functions, see handle::RenderServer for a safe handle
+//!
+//! # Notes
+//! The library is loaded on first use from locate::select_server(None, None), so it is only selected by
+//! `EXPLORITRON_RENDER` (or the platform default). A path given on the command line or read from the client
+//! configuration is never seen here, clients honouring those open a handle::RenderServer with the path instead.
+//!
+//! # Render state lifetime
+//! * A render state is valid from the expr_init that returned it until it is disconnected.
+//! * A disconnected render state may only be passed to expr_reconnect, which makes it valid again unless
+//!   it asks for expr_init to be rerun.
+//! * Once expr_init is rerun every render state that is still disconnected is abandoned,
+//!   the server may free it and the client must never pass it to the server again.
+
 #![allow(non_upper_case_globals)]
 
-use std::ptr::null_mut;
+use std::ffi::c_void;
 use lazy_static::lazy_static;
 use libloading::Symbol;
 use crate::RenderState;
+use crate::error::ExprError;
 use super::state::*;
-use super::*;
-use crate::library;
+use super::backend::{FrameCallbackFn, UserCallbackFn};
+use super::handle::{FrameCallback, UserCallback};
+use crate::{library, load_error};
 
 /// disconnect notifies the server that it is about to be stopped
 ///
@@ -15,14 +31,15 @@ use crate::library;
 /// * This function may be left unimplemented if the server doesn't need it
 ///
 /// # Notes
-/// The return value is an error message if an error occurs, null otherwise
+/// * The return value is ExprError::none unless an error occurs
+/// * Afterwards render_state may only be passed to reconnect, see the module documentation
 #[inline]
-pub unsafe fn disconnect(render_state: *mut RenderState) -> *mut i8 {
+pub unsafe fn disconnect(render_state: *mut RenderState) -> ExprError {
     lazy_static! {
-        static ref disconnect: Option<Symbol<'static, unsafe extern fn(*mut RenderState) -> *mut i8>>
-            = unsafe { library.get(b"expr_disconnect").ok() };
+        static ref disconnect: Option<Symbol<'static, unsafe extern fn(*mut RenderState) -> ExprError>>
+            = unsafe { library.as_ref().ok().and_then(|l| l.get(b"expr_disconnect").ok()) };
     }
-    disconnect.as_ref().map(|f| f(render_state)).unwrap_or(null_mut())
+    disconnect.as_ref().map(|f| f(render_state)).unwrap_or(ExprError::none())
 }
 
 /// reconnect notifies the server when the client is reconnecting
@@ -32,16 +49,16 @@ pub unsafe fn disconnect(render_state: *mut RenderState) -> *mut i8 {
 /// * This function may be left unimplemented if the server doesn't need it
 ///
 /// # Notes
-/// The return value is an error message if an error occurs,
-/// a negative pointer if expr_init needs to be rerun,
-/// or null
+/// The return value is ExprError::none unless an error occurs,
+/// its code is ErrorCode::NeedsReinit if expr_init needs to be rerun.
+/// render_state must have been disconnected, and expr_init must not have been rerun since
 #[inline]
-pub unsafe fn reconnect(render_state: *mut RenderState) -> *mut i8 {
+pub unsafe fn reconnect(render_state: *mut RenderState) -> ExprError {
     lazy_static! {
-        static ref reconnect: Option<Symbol<'static, unsafe extern fn(*mut RenderState) -> *mut i8>>
-            = unsafe { library.get(b"expr_reconnect").ok() };
+        static ref reconnect: Option<Symbol<'static, unsafe extern fn(*mut RenderState) -> ExprError>>
+            = unsafe { library.as_ref().ok().and_then(|l| l.get(b"expr_reconnect").ok()) };
     }
-    reconnect.as_ref().map(|f| f(render_state)).unwrap_or(null_mut())
+    reconnect.as_ref().map(|f| f(render_state)).unwrap_or(ExprError::none())
 }
 
 /// push_state adds game_state to the list of state changes to be processed by the server
@@ -55,14 +72,15 @@ pub unsafe fn reconnect(render_state: *mut RenderState) -> *mut i8 {
 /// * This function is required to be defined
 ///
 /// # Notes
-/// The return value is an error message if an error occurs, null otherwise
+/// The return value is ExprError::none unless an error occurs.
+/// If the server could not be loaded ErrorCode::NotLoaded is returned with a client owned message.
 #[inline]
-pub unsafe fn push_state(render_state: *mut RenderState, game_state: *mut State) -> *mut i8 {
+pub unsafe fn push_state(render_state: *mut RenderState, game_state: *mut State) -> ExprError {
     lazy_static! {
-        static ref push_state: Symbol<'static, unsafe extern fn(*mut RenderState, *mut State) -> *mut i8>
-            = unsafe { library.get(b"expr_push_state").expect("failed to load expr_push_state") };
+        static ref push_state: Option<Symbol<'static, unsafe extern fn(*mut RenderState, *mut State) -> ExprError>>
+            = unsafe { library.as_ref().ok().and_then(|l| l.get(b"expr_push_state").ok()) };
     }
-    push_state(render_state, game_state)
+    push_state.as_ref().map(|f| f(render_state, game_state)).unwrap_or_else(load_error)
 }
 
 /// frame_callback calls the provided callback after an event occurs while rendering a frame
@@ -70,28 +88,31 @@ pub unsafe fn push_state(render_state: *mut RenderState, game_state: *mut State)
 /// # Arguments
 /// * render_state is an opaque type that the server uses to persist state. (engine managed)
 /// * callback is the callback that is called when an event occurs. (unmanaged)
+/// * user_data is handed back to every call of callback. (engine managed)
 ///
 /// # Callback Arguments
 /// * The RenderEvent indicates which kind of failure has occured.
 /// * The i32 indicates which frame the event occurred on.
 /// * The State pointer indicates what state was being processed when the event happened.
 /// * The *mut i8 is any message accompanying the event
+/// * The *mut c_void is the user_data the callback was registered with
+/// * The State pointer and every string are only valid until the callback returns
 ///
 /// # Link Details
 /// * The target library must expose the symbol `expr_frame_callback` for this function to resolve
 /// * This function is required to be defined
 ///
 /// # Notes
-/// The return value is an error message if an error occurs, null otherwise
+/// The return value is ExprError::none unless an error occurs.
+/// If the server could not be loaded ErrorCode::NotLoaded is returned with a client owned message.
+/// Once another callback is registered the server must not call the previous one again.
 #[inline]
-pub unsafe fn frame_callback(render_state: *mut RenderState, callback: unsafe extern fn(RenderResult, i32, *mut State, *mut i8)) -> *mut i8 {
+pub unsafe fn frame_callback(render_state: *mut RenderState, callback: FrameCallback, user_data: *mut c_void) -> ExprError {
     lazy_static! {
-        static ref frame_callback:
-            Symbol<'static, unsafe extern fn(*mut RenderState,
-                unsafe extern fn(RenderResult, i32, *mut State, *mut i8)) -> *mut i8>
-            = unsafe { library.get(b"expr_frame_callback").expect("failed to load expr_frame_callback") };
+        static ref frame_callback: Option<Symbol<'static, FrameCallbackFn>>
+            = unsafe { library.as_ref().ok().and_then(|l| l.get(b"expr_frame_callback").ok()) };
     }
-    frame_callback(render_state, callback)
+    frame_callback.as_ref().map(|f| f(render_state, callback, user_data)).unwrap_or_else(load_error)
 }
 
 /// expr_user_callback calls the provided callback upon a user-triggered event
@@ -99,25 +120,49 @@ pub unsafe fn frame_callback(render_state: *mut RenderState, callback: unsafe ex
 /// # Arguments
 /// * render_state is an opaque type that the server uses to persist state. (engine managed)
 /// * callback is the callback that is called when an event occurs. (unmanaged)
+/// * user_data is handed back to every call of callback. (engine managed)
 ///
 /// # Callback Arguments
 /// * UserEvent indicates whether the user sent a keystroke or command
 /// * If UserEvent is an input, *mut i8 is the button pressed.
 ///   If UserEvent is a command, *mut i8 is the command text.
+/// * The *mut c_void is the user_data the callback was registered with
 ///
 /// # Link Details
 /// * The target library must expose the symbol `expr_user_callback` for this function to resolve
 /// * This function is required to be defined
 ///
 /// # Notes
-/// The return value is an error message if an error occurs, null otherwise
+/// The return value is ExprError::none unless an error occurs.
+/// If the server could not be loaded ErrorCode::NotLoaded is returned with a client owned message.
+/// Once another callback is registered the server must not call the previous one again.
+#[inline]
+pub unsafe fn user_callback(render_state: *mut RenderState, callback: UserCallback, user_data: *mut c_void) -> ExprError {
+    lazy_static! {
+        static ref user_callback: Option<Symbol<'static, UserCallbackFn>>
+            = unsafe { library.as_ref().ok().and_then(|l| l.get(b"expr_user_callback").ok()) };
+    }
+    user_callback.as_ref().map(|f| f(render_state, callback, user_data)).unwrap_or_else(load_error)
+}
+
+/// free releases the message of an ExprError returned by any function in this module once it has been copied
+///
+/// # Link Details
+/// * The target server must expose the symbol `expr_free` for this function to resolve
+/// * This function may be left unimplemented if the server keeps its errors valid until it is unloaded
+///
+/// # Notes
+/// Null and client owned messages are ignored, see alloc for who owns which strings
 #[inline]
-pub unsafe fn user_callback(render_state: *mut RenderState, callback: unsafe extern fn(UserEvent, *mut i8)) -> *mut i8 {
+pub unsafe fn free(error: *mut i8) {
     lazy_static! {
-        static ref user_callback:
-            Symbol<'static, unsafe extern fn(*mut RenderState,
-                unsafe extern fn(UserEvent, *mut i8)) -> *mut i8>
-            = unsafe { library.get(b"expr_user_callback").expect("failed to load expr_user_callback") };
+        static ref free: Option<Symbol<'static, crate::alloc::FreeFn>>
+            = unsafe { library.as_ref().ok().and_then(|l| l.get(b"expr_free").ok()) };
+    }
+    if error.is_null() || error == load_error().message_ptr() {
+        return;
+    }
+    if let Some(f) = free.as_ref() {
+        f(error)
     }
-    user_callback(render_state, callback)
 }
@@ -0,0 +1,367 @@
+//! conformance checks a render server against the rules of the render API
+//!
+//! check_library loads a candidate server library and check runs any RenderBackend through the same
+//! sequence: init, callback registration, pushes of valid and invalid states, disconnect and reconnect.
+//! Every Rule is reported as passed, failed (with what went wrong) or skipped.
+//!
+//! # Notes
+//! * Signatures cannot be read from a library, they are only exercised by calling each entry point
+//! * A server that crashes on an invalid state takes the harness down with it,
+//!   run untrusted servers in their own process (see the conformance binary of the core)
+
+use std::ffi::{c_void, CStr};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::ptr::null_mut;
+use libloading::Library;
+use crate::error::{ErrorCode, ExprError};
+use crate::negotiate::{check_server_version, ClientMetadata};
+use crate::RenderState;
+use super::backend::{DylibBackend, RenderBackend};
+use super::owned::{Actor, ActorState, MenuState, Section, State, Terrain, WorldState};
+use super::state::{self, RenderContext};
+use super::{RenderResult, UserEvent};
+
+/// REQUIRED_SYMBOLS is every symbol a server must expose
+pub const REQUIRED_SYMBOLS: &[&str] = &["expr_init", "expr_push_state", "expr_frame_callback", "expr_user_callback"];
+
+/// OPTIONAL_SYMBOLS is every symbol a server may leave unimplemented
+pub const OPTIONAL_SYMBOLS: &[&str] = &["expr_disconnect", "expr_reconnect", "expr_free"];
+
+/// Rule is a rule of the render API a server is checked against
+///
+/// # Variants
+/// * RequiredSymbols - the server exposes every symbol in REQUIRED_SYMBOLS
+/// * InitResult - expr_init returns a server version and state, or an error when it returns neither
+/// * SupportedVersion - expr_init picks a version the client supports
+/// * Callbacks - registering the frame and user callbacks succeeds
+/// * AcceptsValidStates - expr_push_state accepts every valid state, including edge cases
+/// * RejectsInvalidStates - expr_push_state returns ErrorCode::InvalidState for invalid states instead of rendering them
+/// * Disconnect - expr_disconnect succeeds
+/// * Reconnect - expr_reconnect succeeds or asks for expr_init to be rerun, and states are accepted afterwards
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    RequiredSymbols,
+    InitResult,
+    SupportedVersion,
+    Callbacks,
+    AcceptsValidStates,
+    RejectsInvalidStates,
+    Disconnect,
+    Reconnect
+}
+
+impl Rule {
+    /// ALL is every rule, in the order they are checked
+    pub const ALL: &'static [Rule] = &[
+        Rule::RequiredSymbols,
+        Rule::InitResult,
+        Rule::SupportedVersion,
+        Rule::Callbacks,
+        Rule::AcceptsValidStates,
+        Rule::RejectsInvalidStates,
+        Rule::Disconnect,
+        Rule::Reconnect
+    ];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Rule::RequiredSymbols => "expr_init, expr_push_state, expr_frame_callback and expr_user_callback are exposed",
+            Rule::InitResult => "expr_init reports an error whenever it returns no server version or state",
+            Rule::SupportedVersion => "expr_init picks a version the client supports",
+            Rule::Callbacks => "expr_frame_callback and expr_user_callback accept a callback",
+            Rule::AcceptsValidStates => "expr_push_state accepts valid states",
+            Rule::RejectsInvalidStates => "expr_push_state returns ErrorCode::InvalidState for invalid states",
+            Rule::Disconnect => "expr_disconnect succeeds",
+            Rule::Reconnect => "expr_reconnect succeeds or asks for reinitialization, then states are accepted"
+        }
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Outcome is the result of checking a rule
+///
+/// # Variants
+/// * Passed - the server follows the rule
+/// * Failed - the server violates the rule, with a description of the violation
+/// * Skipped - the rule could not be checked, with the reason
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    Failed(String),
+    Skipped(String)
+}
+
+/// Report is the outcome of every rule checked against a server
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Report {
+    results: Vec<(Rule, Outcome)>
+}
+
+impl Report {
+    fn set(&mut self, rule: Rule, outcome: Outcome) {
+        self.results.push((rule, outcome));
+    }
+
+    /// skip_rest skips every rule that has no outcome yet
+    fn skip_rest(&mut self, reason: &str) {
+        for rule in Rule::ALL {
+            if self.outcome(*rule).is_none() {
+                self.set(*rule, Outcome::Skipped(reason.to_string()));
+            }
+        }
+    }
+
+    /// results is every checked rule and its outcome, in the order they were checked
+    pub fn results(&self) -> &[(Rule, Outcome)] {
+        &self.results
+    }
+
+    pub fn outcome(&self, rule: Rule) -> Option<&Outcome> {
+        self.results.iter().find(|(r, _)| *r == rule).map(|(_, outcome)| outcome)
+    }
+
+    /// violations is every failed rule and what went wrong
+    pub fn violations(&self) -> impl Iterator<Item = (Rule, &str)> {
+        self.results.iter().filter_map(|(rule, outcome)| match outcome {
+            Outcome::Failed(detail) => Some((*rule, detail.as_str())),
+            _ => None
+        })
+    }
+
+    /// conforms is whether no rule failed
+    pub fn conforms(&self) -> bool {
+        self.violations().next().is_none()
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (rule, outcome) in &self.results {
+            match outcome {
+                Outcome::Passed => writeln!(f, "pass {}: {}", rule, rule.description())?,
+                Outcome::Failed(detail) => writeln!(f, "FAIL {}: {}: {}", rule, rule.description(), detail)?,
+                Outcome::Skipped(reason) => writeln!(f, "skip {}: {}", rule, reason)?
+            }
+        }
+        Ok(())
+    }
+}
+
+/// check_library checks the server library at path
+///
+/// # Notes
+/// Loading a library runs its initialization routines, see RenderServer::open_with
+pub fn check_library(path: impl AsRef<Path>) -> Report {
+    let mut report = Report::default();
+    let missing = match unsafe { Library::new(path.as_ref()) } {
+        Ok(library) => REQUIRED_SYMBOLS.iter()
+            .filter(|symbol| unsafe { library.get::<*const ()>(symbol.as_bytes()).is_err() })
+            .copied()
+            .collect::<Vec<_>>(),
+        Err(e) => {
+            report.set(Rule::RequiredSymbols, Outcome::Failed(e.to_string()));
+            report.skip_rest("the library could not be loaded");
+            return report;
+        }
+    };
+    if !missing.is_empty() {
+        report.set(Rule::RequiredSymbols, Outcome::Failed(format!("missing {}", missing.join(", "))));
+        report.skip_rest("required symbols are missing");
+        return report;
+    }
+    match unsafe { DylibBackend::open(path) } {
+        Ok(backend) => check(&backend),
+        Err(e) => {
+            report.set(Rule::RequiredSymbols, Outcome::Failed(e.to_string()));
+            report.skip_rest("the library could not be loaded");
+            report
+        }
+    }
+}
+
+/// check checks the server reached through backend
+///
+/// # Notes
+/// RequiredSymbols is passed without checking, a backend always provides every required entry point
+pub fn check(backend: &dyn RenderBackend) -> Report {
+    let mut report = Report::default();
+    report.set(Rule::RequiredSymbols, Outcome::Passed);
+    // NOTE: the server may hold on to the metadata, it must outlive every call below
+    let mut metadata = ClientMetadata::new(env!("CARGO_PKG_VERSION"));
+
+    let render_state = match unsafe { init(backend, &mut metadata, &mut report) } {
+        Some(render_state) => render_state,
+        None => return report
+    };
+
+    let callbacks = unsafe {
+        [backend.frame_callback(render_state, ignore_frame, null_mut()), backend.user_callback(render_state, ignore_user, null_mut())]
+    };
+    let errors: Vec<String> = callbacks.into_iter().filter_map(|e| unsafe { message(backend, e) }).collect();
+    report.set(Rule::Callbacks, outcome(errors));
+
+    let errors = valid_states().iter()
+        .filter_map(|(name, state)| {
+            let mut lowered = state.lower().expect("valid states lower");
+            unsafe { message(backend, backend.push_state(render_state, lowered.as_mut_ptr())) }
+                .map(|error| format!("{} was rejected: {}", name, error))
+        })
+        .collect();
+    report.set(Rule::AcceptsValidStates, outcome(errors));
+
+    let errors = invalid_states().into_iter()
+        .filter_map(|(name, invalidate)| {
+            let state = State::new(RenderContext::WorldTraversal)
+                .with_actors(ActorState::new().with_actor(Actor::new("player", "player")))
+                .with_menu(MenuState::new("main"));
+            let mut lowered = state.lower().expect("valid states lower");
+            invalidate(lowered.state_mut());
+            let error = unsafe { backend.push_state(render_state, lowered.as_mut_ptr()) };
+            let code = error.code();
+            match unsafe { message(backend, error) } {
+                None => Some(format!("{} was accepted", name)),
+                Some(error) if code != ErrorCode::InvalidState => Some(format!("{} was rejected with {}", name, error)),
+                Some(_) => None
+            }
+        })
+        .collect();
+    report.set(Rule::RejectsInvalidStates, outcome(errors));
+
+    match unsafe { backend.disconnect(render_state) } {
+        Some(error) => report.set(Rule::Disconnect, outcome(unsafe { message(backend, error) }.into_iter().collect())),
+        None => report.set(Rule::Disconnect, Outcome::Skipped("expr_disconnect is not exposed".to_string()))
+    }
+
+    let reconnected = match unsafe { backend.reconnect(render_state) } {
+        None => None,
+        Some(error) if error.code() == ErrorCode::NeedsReinit => {
+            unsafe { message(backend, error) };
+            let mut reinit = Report::default();
+            unsafe { init(backend, &mut metadata, &mut reinit) }
+                .ok_or_else(|| format!("reinitialization failed: {:?}", reinit.outcome(Rule::InitResult)))
+                .map(Some)
+                .transpose()
+        }
+        Some(error) => match unsafe { message(backend, error) } {
+            Some(error) => Some(Err(error)),
+            None => Some(Ok(render_state))
+        }
+    };
+    match reconnected {
+        Some(Ok(render_state)) => {
+            let state = State::default();
+            let mut lowered = state.lower().expect("valid states lower");
+            let error = unsafe { message(backend, backend.push_state(render_state, lowered.as_mut_ptr())) };
+            report.set(Rule::Reconnect, outcome(error.map(|e| format!("push after reconnect failed: {}", e)).into_iter().collect()));
+            if let Some(error) = unsafe { backend.disconnect(render_state) } {
+                unsafe { message(backend, error) };
+            }
+        }
+        Some(Err(error)) => report.set(Rule::Reconnect, Outcome::Failed(error)),
+        None => report.set(Rule::Reconnect, Outcome::Skipped("expr_reconnect is not exposed".to_string()))
+    }
+    report
+}
+
+/// init runs expr_init and checks InitResult and SupportedVersion
+///
+/// Returns the render state if the server initialized, every other rule is skipped otherwise
+unsafe fn init(backend: &dyn RenderBackend, metadata: &mut ClientMetadata, report: &mut Report) -> Option<*mut RenderState> {
+    let result = backend.init(metadata.as_incoming());
+    let error = message(backend, result.error);
+    if result.server_version.is_null() || result.server_state.is_null() {
+        match error {
+            Some(error) => {
+                report.set(Rule::InitResult, Outcome::Passed);
+                report.skip_rest(&format!("initialization failed: {}", error));
+            }
+            None => {
+                report.set(Rule::InitResult, Outcome::Failed("no server version or state and no error".to_string()));
+                report.skip_rest("initialization failed");
+            }
+        }
+        return None;
+    }
+    report.set(Rule::InitResult, Outcome::Passed);
+    let version = CStr::from_ptr(result.server_version).to_string_lossy();
+    let supported = check_server_version(&metadata.supported_versions(), &version);
+    report.set(Rule::SupportedVersion, outcome(supported.err().into_iter().collect()));
+    Some(result.server_state)
+}
+
+/// outcome is Passed if there are no errors, otherwise the errors fail the rule
+fn outcome(errors: Vec<String>) -> Outcome {
+    if errors.is_empty() {
+        Outcome::Passed
+    } else {
+        Outcome::Failed(errors.join("; "))
+    }
+}
+
+/// message describes an error returned by backend and releases its message, ExprError::none yields None
+unsafe fn message(backend: &dyn RenderBackend, error: ExprError) -> Option<String> {
+    let message = error.message().map(|e| e.to_string_lossy().into_owned());
+    if message.is_some() {
+        backend.free(error.message_ptr());
+    }
+    match (error.is_error(), message) {
+        (false, _) => None,
+        (true, Some(message)) => Some(format!("{:?}: {}", error.code(), message)),
+        (true, None) => Some(format!("{:?}", error.code()))
+    }
+}
+
+unsafe extern "C" fn ignore_frame(_result: RenderResult, _frame: i32, _state: *mut state::State, _message: *mut i8, _user_data: *mut c_void) {}
+
+unsafe extern "C" fn ignore_user(_event: UserEvent, _text: *mut i8, _user_data: *mut c_void) {}
+
+/// valid_states is every valid state pushed by check, named for the report
+fn valid_states() -> Vec<(&'static str, State)> {
+    let terrain = Terrain::new("passable").with_attr("note:grass");
+    vec![
+        ("the default state", State::default()),
+        ("an empty world", State::default().with_world(WorldState::new(0, 0, 0))),
+        ("a world", State::default().with_world(
+            WorldState::fill(3, 2, 2, &terrain)
+                .with_terrain(1, 1, 0, Terrain::new("impassable"))
+                .with_terrain(2, 0, 1, Terrain::new("entrance"))
+                .with_attr("status:raining")
+        )),
+        ("actors", State::new(RenderContext::Battle).with_actors(
+            ActorState::new()
+                .with_actor(Actor::new("player", "player").with_description("").with_attr("control:current"))
+                .with_actor(Actor::new("ゴブリン", "computer").with_position(-1, 4, 0).with_attr("stat:hp:3:-1"))
+        )),
+        ("a menu", State::default().with_menu(
+            MenuState::new("inventory")
+                .with_section(Section::new("items").with_text("sword").with_text("").with_subsection(
+                    Section::new("potions").with_text("healing").with_attr("open")
+                ).with_selected_item(2))
+                .with_section(Section::new("empty"))
+                .with_selected_section(0)
+        )),
+        ("attributes", State::new(RenderContext::BuildingTraversal).with_attr("").with_attr("note:a\\:b"))
+    ]
+}
+
+/// Invalidate turns a valid lowered state into an invalid one
+type Invalidate = fn(&mut state::State);
+
+/// invalid_states is every way check invalidates a state, named for the report
+fn invalid_states() -> Vec<(&'static str, Invalidate)> {
+    vec![
+        ("a negative world dimension", |state| state.world_state.terrain_len_x = -1),
+        ("a negative actor count", |state| {
+            state.actor_state.actors = null_mut();
+            state.actor_state.actors_length = -1;
+        }),
+        ("a null actor kind", |state| unsafe { (*state.actor_state.actors).draw.kind = null_mut() }),
+        ("a null menu kind", |state| state.menu_state.kind = null_mut())
+    ]
+}
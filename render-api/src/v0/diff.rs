@@ -0,0 +1,386 @@
+//! diff computes the changes between two owned states so only those need to be sent to the server
+//!
+//! Deltas are sent through the deltas extension, whose expr_push_delta symbol takes a StateDelta
+//! (see PushDeltaFn). A client enables it by registering Deltas in its ExtensionRegistry,
+//! RenderServer::push then sends a delta against the last pushed state whenever the server accepted
+//! the extension and a full state otherwise.
+//!
+//! # Applying a delta
+//! A server applies a StateDelta to the last state it received, in this order:
+//! 1. every part of state flagged in changed replaces the same part of the last state
+//! 2. every terrain delta replaces the terrain at its index in the flattened terrain
+//! 3. every Moved or Updated actor delta replaces the actor at its index
+//! 4. every Removed actor delta removes the actor at its index, indexes refer to the list before any removal
+//! 5. every Added actor delta is appended to the actors, in order
+
+use std::ffi::NulError;
+use std::marker::PhantomData;
+use std::ptr::null_mut;
+use crate::{c_array, RenderState};
+use crate::error::ExprError;
+use crate::extension::Extension;
+use super::owned::{self, Actor, Arena, MenuState, Terrain, WorldState};
+use super::state::{self, RenderContext};
+
+/// Deltas is the extension adding expr_push_delta
+pub struct Deltas;
+
+impl Extension for Deltas {
+    const NAME: &'static str = "deltas";
+    const SYMBOLS: &'static [&'static str] = &["expr_push_delta"];
+    const ATTRIBUTES: &'static [&'static str] = &[];
+    type Metadata = ();
+}
+
+/// PushDeltaFn is the signature of expr_push_delta, it returns an error like expr_push_state
+pub type PushDeltaFn = unsafe extern "C" fn(*mut RenderState, *mut StateDelta) -> ExprError;
+
+/// DELTA_RENDER_CONTEXT flags that state.render_context changed
+pub const DELTA_RENDER_CONTEXT: u32 = 1;
+/// DELTA_WORLD flags that state.world_state replaces the whole world (its dimensions changed)
+pub const DELTA_WORLD: u32 = 1 << 1;
+/// DELTA_WORLD_ATTRS flags that state.world_state.attrs changed
+pub const DELTA_WORLD_ATTRS: u32 = 1 << 2;
+/// DELTA_ACTOR_ATTRS flags that state.actor_state.attrs changed
+pub const DELTA_ACTOR_ATTRS: u32 = 1 << 3;
+/// DELTA_MENU flags that state.menu_state replaces the whole menu
+pub const DELTA_MENU: u32 = 1 << 4;
+/// DELTA_ATTRS flags that state.attrs changed
+pub const DELTA_ATTRS: u32 = 1 << 5;
+
+/// ActorChange is how an actor changed
+///
+/// # Variants
+/// * Added - the actor is new
+/// * Removed - the actor is gone
+/// * Moved - only the position of the actor changed
+/// * Updated - anything else about the actor changed
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ActorChange {
+    Added,
+    Removed,
+    Moved,
+    Updated
+}
+
+/// TerrainDelta replaces the terrain at index in the flattened terrain
+#[repr(C)]
+pub struct TerrainDelta {
+    pub(crate) index: i64,
+    pub(crate) terrain: state::Terrain
+}
+
+impl TerrainDelta {
+    pub fn index(&self) -> i64 {
+        self.index
+    }
+
+    pub fn terrain(&self) -> &state::Terrain {
+        &self.terrain
+    }
+}
+
+/// ActorDelta is a change to a single actor
+///
+/// # Fields
+/// * change is how the actor changed
+/// * index is the index of the actor before the delta is applied, -1 for Added
+/// * actor is the new actor, every pointer in it is null for Removed
+#[repr(C)]
+pub struct ActorDelta {
+    pub(crate) change: ActorChange,
+    pub(crate) index: isize,
+    pub(crate) actor: state::Actor
+}
+
+impl ActorDelta {
+    pub fn change(&self) -> ActorChange {
+        self.change
+    }
+
+    pub fn index(&self) -> isize {
+        self.index
+    }
+
+    pub fn actor(&self) -> &state::Actor {
+        &self.actor
+    }
+}
+
+/// StateDelta is the #[repr(C)] form of Delta passed to expr_push_delta
+///
+/// # Fields
+/// * changed is a set of DELTA_* flags, the parts of state that are not flagged are empty
+/// * state holds every flagged part
+/// * terrain lists the changed terrain, empty if DELTA_WORLD is set
+/// * actors lists the changed actors
+#[repr(C)]
+pub struct StateDelta {
+    pub(crate) changed: u32,
+    pub(crate) state: state::State,
+    pub(crate) terrain: c_array<TerrainDelta>,
+    pub(crate) terrain_length: isize,
+    pub(crate) actors: c_array<ActorDelta>,
+    pub(crate) actors_length: isize
+}
+
+impl StateDelta {
+    pub fn changed(&self) -> u32 {
+        self.changed
+    }
+
+    pub fn state(&self) -> &state::State {
+        &self.state
+    }
+
+    /// # Safety
+    /// See the state module documentation
+    pub unsafe fn terrain(&self) -> &[TerrainDelta] {
+        crate::c_slice(self.terrain, self.terrain_length)
+    }
+
+    /// # Safety
+    /// See the state module documentation
+    pub unsafe fn actors(&self) -> &[ActorDelta] {
+        crate::c_slice(self.actors, self.actors_length)
+    }
+}
+
+/// ActorUpdate is the owned form of ActorDelta, index is None for Added
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ActorUpdate {
+    pub change: ActorChange,
+    pub index: Option<usize>,
+    pub actor: Actor
+}
+
+/// Delta is the difference between two owned states
+///
+/// # Notes
+/// Actors are matched by name, in order: new actors are matched with the first old actor of the same name
+/// after the previous match until one has no such actor, that actor and every one after it is Added
+/// and every old actor left unmatched is Removed. Applying the delta therefore reproduces the order of the new actors.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Delta {
+    pub render_context: Option<RenderContext>,
+    pub world: Option<WorldState>,
+    pub terrain: Vec<(usize, Terrain)>,
+    pub world_attrs: Option<Vec<String>>,
+    pub actors: Vec<ActorUpdate>,
+    pub actor_attrs: Option<Vec<String>>,
+    pub menu: Option<MenuState>,
+    pub attrs: Option<Vec<String>>
+}
+
+/// changed is Some(new) if new differs from old
+fn changed<T: PartialEq + Clone>(old: &T, new: &T) -> Option<T> {
+    (old != new).then(|| new.clone())
+}
+
+impl Delta {
+    /// between is the delta that turns old into new
+    pub fn between(old: &owned::State, new: &owned::State) -> Delta {
+        let (old_world, new_world) = (&old.world_state, &new.world_state);
+        let (world, terrain) = if old_world.dimensions() != new_world.dimensions() {
+            (Some(new_world.clone()), Vec::new())
+        } else {
+            let terrain = old_world.terrain().iter().zip(new_world.terrain()).enumerate()
+                .filter(|(_, (old, new))| old != new)
+                .map(|(idx, (_, new))| (idx, new.clone()))
+                .collect();
+            (None, terrain)
+        };
+
+        Delta {
+            render_context: changed(&old.render_context, &new.render_context),
+            world_attrs: if world.is_some() { None } else { changed(&old_world.attrs, &new_world.attrs) },
+            world,
+            terrain,
+            actors: actor_updates(&old.actor_state.actors, &new.actor_state.actors),
+            actor_attrs: changed(&old.actor_state.attrs, &new.actor_state.attrs),
+            menu: changed(&old.menu_state, &new.menu_state),
+            attrs: changed(&old.attrs, &new.attrs)
+        }
+    }
+
+    /// is_empty reports whether nothing changed
+    pub fn is_empty(&self) -> bool {
+        *self == Delta::default()
+    }
+
+    /// apply applies this delta to state as documented in the module documentation
+    ///
+    /// # Notes
+    /// Updates that refer to terrain or actors that do not exist in state are ignored
+    pub fn apply(&self, state: &mut owned::State) {
+        if let Some(render_context) = self.render_context {
+            state.render_context = render_context;
+        }
+        if let Some(world) = &self.world {
+            state.world_state = world.clone();
+        }
+        for (idx, terrain) in &self.terrain {
+            if let Some(tile) = state.world_state.terrain_mut().get_mut(*idx) {
+                *tile = terrain.clone();
+            }
+        }
+        if let Some(attrs) = &self.world_attrs {
+            state.world_state.attrs = attrs.clone();
+        }
+
+        let actors = &mut state.actor_state.actors;
+        for update in self.actors.iter().filter(|u| matches!(u.change, ActorChange::Moved | ActorChange::Updated)) {
+            if let Some(actor) = update.index.and_then(|idx| actors.get_mut(idx)) {
+                *actor = update.actor.clone();
+            }
+        }
+        let mut removed: Vec<usize> = self.actors.iter()
+            .filter(|u| u.change == ActorChange::Removed)
+            .filter_map(|u| u.index)
+            .filter(|idx| *idx < actors.len())
+            .collect();
+        removed.sort_unstable();
+        removed.dedup();
+        for idx in removed.into_iter().rev() {
+            actors.remove(idx);
+        }
+        actors.extend(self.actors.iter().filter(|u| u.change == ActorChange::Added).map(|u| u.actor.clone()));
+        if let Some(attrs) = &self.actor_attrs {
+            state.actor_state.attrs = attrs.clone();
+        }
+
+        if let Some(menu) = &self.menu {
+            state.menu_state = menu.clone();
+        }
+        if let Some(attrs) = &self.attrs {
+            state.attrs = attrs.clone();
+        }
+    }
+
+    /// lower builds the #[repr(C)] form of this delta
+    ///
+    /// # Notes
+    /// Fails if any string contains a nul byte
+    pub fn lower(&self) -> Result<LoweredDelta<'_>, NulError> {
+        let mut arena = Arena::default();
+        let mut changed = 0;
+        let mut flag = |set: bool, flag: u32| if set { changed |= flag };
+        flag(self.render_context.is_some(), DELTA_RENDER_CONTEXT);
+        flag(self.world.is_some(), DELTA_WORLD);
+        flag(self.world_attrs.is_some(), DELTA_WORLD_ATTRS);
+        flag(self.actor_attrs.is_some(), DELTA_ACTOR_ATTRS);
+        flag(self.menu.is_some(), DELTA_MENU);
+        flag(self.attrs.is_some(), DELTA_ATTRS);
+
+        // NOTE: the parts that did not change are lowered empty
+        let mut world_state = self.world.clone().unwrap_or_default();
+        if let Some(attrs) = &self.world_attrs {
+            world_state.attrs = attrs.clone();
+        }
+        let partial = owned::State {
+            render_context: self.render_context.unwrap_or_default(),
+            world_state,
+            actor_state: owned::ActorState { actors: Vec::new(), attrs: self.actor_attrs.clone().unwrap_or_default() },
+            menu_state: self.menu.clone().unwrap_or_default(),
+            attrs: self.attrs.clone().unwrap_or_default()
+        };
+        let state = arena.state(&partial)?;
+
+        let terrain = self.terrain.iter()
+            .map(|(index, terrain)| Ok(TerrainDelta { index: *index as i64, terrain: arena.terrain(terrain)? }))
+            .collect::<Result<Vec<_>, NulError>>()?;
+        let (terrain, terrain_length) = arena.keep(terrain);
+        let actors = self.actors.iter()
+            .map(|update| Ok(ActorDelta {
+                change: update.change,
+                index: update.index.map_or(-1, |idx| idx as isize),
+                actor: match update.change {
+                    ActorChange::Removed => removed_actor(),
+                    _ => arena.actor(&update.actor)?
+                }
+            }))
+            .collect::<Result<Vec<_>, NulError>>()?;
+        let (actors, actors_length) = arena.keep(actors);
+
+        Ok(LoweredDelta {
+            delta: StateDelta { changed, state, terrain, terrain_length, actors, actors_length },
+            _arena: arena,
+            _owner: PhantomData
+        })
+    }
+}
+
+/// removed_actor is the actor sent along with an ActorChange::Removed
+fn removed_actor() -> state::Actor {
+    state::Actor {
+        name: null_mut(),
+        description: null_mut(),
+        draw: state::Drawable {
+            kind: null_mut(),
+            pos_x: 0,
+            pos_y: 0,
+            pos_z: 0,
+            span_x: 0,
+            span_y: 0,
+            span_z: 0,
+            attrs: null_mut(),
+            attrs_length: 0
+        },
+        attrs: null_mut(),
+        attrs_length: 0
+    }
+}
+
+/// actor_updates matches the actors of new with those of old by name, as documented on Delta
+fn actor_updates(old: &[Actor], new: &[Actor]) -> Vec<ActorUpdate> {
+    let mut matched = vec![false; old.len()];
+    let mut updates = Vec::new();
+    // NOTE: kept actors stay in place and added ones are appended, so only a prefix of new can be matched
+    let mut next = Some(0);
+    for actor in new {
+        let found = next.and_then(|start| old[start..].iter().position(|a| a.name == actor.name).map(|idx| start + idx));
+        let idx = match found {
+            Some(idx) => idx,
+            None => {
+                next = None;
+                updates.push(ActorUpdate { change: ActorChange::Added, index: None, actor: actor.clone() });
+                continue;
+            }
+        };
+        next = Some(idx + 1);
+        matched[idx] = true;
+        let previous = &old[idx];
+        if previous == actor {
+            continue;
+        }
+        let mut moved = previous.clone();
+        moved.draw.position = actor.draw.position;
+        let change = if moved == *actor { ActorChange::Moved } else { ActorChange::Updated };
+        updates.push(ActorUpdate { change, index: Some(idx), actor: actor.clone() });
+    }
+    for (idx, actor) in old.iter().enumerate().filter(|(idx, _)| !matched[*idx]) {
+        updates.push(ActorUpdate { change: ActorChange::Removed, index: Some(idx), actor: actor.clone() });
+    }
+    updates
+}
+
+/// LoweredDelta is a StateDelta along with every allocation its pointers refer to
+pub struct LoweredDelta<'a> {
+    delta: StateDelta,
+    _arena: Arena,
+    _owner: PhantomData<&'a Delta>
+}
+
+impl LoweredDelta<'_> {
+    pub fn delta(&self) -> &StateDelta {
+        &self.delta
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut StateDelta {
+        &mut self.delta
+    }
+}
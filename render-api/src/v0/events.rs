@@ -0,0 +1,89 @@
+//! events aggregates the events a server reports through the frame callback
+//!
+//! FrameEvents counts every event by kind and turns critical ones into RenderError::Critical,
+//! so a client can tell a server that is merely dropping frames from one that can no longer render.
+
+use std::ffi::CStr;
+use super::handle::RenderError;
+use super::{RenderEvent, RenderResult};
+
+/// EVENTS is every RenderEvent, in the order FrameEvents counts them
+const EVENTS: [RenderEvent; 4] = [
+    RenderEvent::FrameSkipped,
+    RenderEvent::RenderError,
+    RenderEvent::DisplayError,
+    RenderEvent::DeviceError
+];
+
+/// FrameEvents aggregates the events reported through the frame callback
+///
+/// Every event is counted per kind, critical events are additionally escalated to the caller
+/// as RenderError::Critical so the core can stop using the server.
+#[derive(Debug, Default, Clone)]
+pub struct FrameEvents {
+    counts: [u64; EVENTS.len()],
+    critical: u64,
+    last_frame: Option<i32>
+}
+
+impl FrameEvents {
+    pub fn new() -> FrameEvents {
+        FrameEvents::default()
+    }
+
+    /// record counts the event reported for frame
+    ///
+    /// # Notes
+    /// Returns RenderError::Critical if the server flagged the event as critical
+    ///
+    /// # Safety
+    /// result must be the one passed to the frame callback and the callback must not have returned
+    pub unsafe fn record(&mut self, result: &RenderResult, frame: i32) -> Result<(), RenderError> {
+        self.count(result.event(), result.critical(), frame, result.message())
+    }
+
+    /// count counts an event that has already been copied out of a RenderResult
+    pub fn count(&mut self, event: RenderEvent, critical: bool, frame: i32, message: Option<&CStr>) -> Result<(), RenderError> {
+        self.counts[event as usize] += 1;
+        self.last_frame = Some(frame);
+        if !critical {
+            return Ok(());
+        }
+        self.critical += 1;
+        Err(RenderError::Critical {
+            event,
+            frame,
+            message: message.map(|m| m.to_string_lossy().into_owned())
+        })
+    }
+
+    /// count_of is the number of times event was reported
+    pub fn count_of(&self, event: RenderEvent) -> u64 {
+        self.counts[event as usize]
+    }
+
+    /// total is the number of events reported
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// critical is the number of critical events reported
+    pub fn critical(&self) -> u64 {
+        self.critical
+    }
+
+    /// last_frame is the frame index of the last reported event
+    pub fn last_frame(&self) -> Option<i32> {
+        self.last_frame
+    }
+
+    /// iter lists the count of every event kind
+    pub fn iter(&self) -> impl Iterator<Item = (RenderEvent, u64)> + '_ {
+        EVENTS.iter().copied().zip(self.counts.iter().copied())
+    }
+
+    /// reset clears every count
+    pub fn reset(&mut self) {
+        *self = FrameEvents::default();
+    }
+}
@@ -0,0 +1,616 @@
+use std::error::Error;
+use std::ffi::{c_void, CStr};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::sync::Mutex;
+use crate::{c_str, RenderState};
+use crate::error::{ErrorCode, ExprError};
+use crate::extension::{Extension, ExtensionDescriptor};
+use crate::negotiate::{check_server_version, string_array, ClientMetadata};
+use super::backend::{DylibBackend, RenderBackend};
+use super::diff::{Delta, Deltas, PushDeltaFn};
+use super::owned;
+#[cfg(feature = "serde")]
+use super::record::{self, Recorder};
+use super::state::{State, Violation};
+use super::{RenderEvent, RenderResult, UserEvent};
+
+pub type FrameCallback = unsafe extern "C" fn(RenderResult, i32, *mut State, *mut i8, *mut c_void);
+pub type UserCallback = unsafe extern "C" fn(UserEvent, *mut i8, *mut c_void);
+
+/// FrameClosure is a closure registered with RenderServer::on_frame
+type FrameClosure = Mutex<Box<dyn FnMut(RenderEvent, bool, i32, Option<&str>) + Send>>;
+
+/// UserClosure is a closure registered with RenderServer::on_user
+type UserClosure = Mutex<Box<dyn FnMut(UserEvent, &str) + Send>>;
+
+/// RenderError is any failure reported while loading or talking to a render server
+///
+/// # Variants
+/// * Load - the server library could not be loaded
+/// * MissingSymbol - the server does not expose a required symbol
+/// * Init - expr_init reported an unrecoverable error
+/// * UnsupportedVersion - the server chose a protocol version the client does not support
+/// * Server - one of the server's entry points failed with code, recoverable is whether the server can still be used
+/// * InvalidState - an owned state could not be lowered for the server
+/// * Violations - a state failed validation before being pushed (see RenderServer::validate_states)
+/// * NeedsReinit - expr_reconnect asked for expr_init to be rerun
+/// * Critical - the server reported a critical event while rendering a frame
+/// * Stopped - the render thread stopped, its server can no longer be reached (see thread::RenderThread)
+/// * Disconnected - the server was disconnected, it has to be reconnected before it is used again
+///
+/// # Notes
+/// Errors returned by the server with ErrorCode::NeedsReinit, ErrorCode::UnsupportedVersion and ErrorCode::InvalidState
+/// are reported as the variant of the same name, every other code as Server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenderError {
+    Load(String),
+    MissingSymbol(&'static str),
+    Init(String),
+    UnsupportedVersion(String),
+    Server { code: ErrorCode, recoverable: bool, message: Option<String> },
+    InvalidState(String),
+    Violations(Vec<Violation>),
+    NeedsReinit,
+    Critical { event: RenderEvent, frame: i32, message: Option<String> },
+    Stopped,
+    Disconnected
+}
+
+impl Display for RenderError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderError::Load(message) => write!(f, "failed to load render server: {}", message),
+            RenderError::MissingSymbol(symbol) => write!(f, "render server does not expose {}", symbol),
+            RenderError::Init(message) => write!(f, "render server failed to initialize: {}", message),
+            RenderError::UnsupportedVersion(message) => write!(f, "render server is incompatible: {}", message),
+            RenderError::Server { code, message: Some(message), .. } => write!(f, "render server error ({:?}): {}", code, message),
+            RenderError::Server { code, message: None, .. } => write!(f, "render server error ({:?})", code),
+            RenderError::InvalidState(message) => write!(f, "invalid game state: {}", message),
+            RenderError::Violations(violations) => {
+                write!(f, "invalid game state: ")?;
+                for (idx, violation) in violations.iter().enumerate() {
+                    write!(f, "{}{}", if idx == 0 { "" } else { "; " }, violation)?;
+                }
+                Ok(())
+            }
+            RenderError::NeedsReinit => write!(f, "render server requested reinitialization"),
+            RenderError::Stopped => write!(f, "render thread has stopped"),
+            RenderError::Disconnected => write!(f, "render server is disconnected"),
+            RenderError::Critical { event, frame, message: Some(message) } =>
+                write!(f, "critical {:?} on frame {}: {}", event, frame, message),
+            RenderError::Critical { event, frame, message: None } =>
+                write!(f, "critical {:?} on frame {}", event, frame)
+        }
+    }
+}
+
+impl Error for RenderError {}
+
+impl RenderError {
+    /// from_server is the error for an entry point that failed with code, see RenderError's notes
+    pub fn from_server(code: ErrorCode, recoverable: bool, message: Option<String>) -> RenderError {
+        match code {
+            ErrorCode::NeedsReinit => RenderError::NeedsReinit,
+            ErrorCode::UnsupportedVersion => RenderError::UnsupportedVersion(message.unwrap_or_default()),
+            ErrorCode::InvalidState => RenderError::InvalidState(message.unwrap_or_default()),
+            code => RenderError::Server { code, recoverable, message }
+        }
+    }
+
+    /// code is the ErrorCode closest to the error, so errors can be handled the same wherever they come from
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            RenderError::Load(_) | RenderError::MissingSymbol(_) | RenderError::Stopped | RenderError::Disconnected => ErrorCode::NotLoaded,
+            RenderError::Init(_) => ErrorCode::Unknown,
+            RenderError::UnsupportedVersion(_) => ErrorCode::UnsupportedVersion,
+            RenderError::Server { code, .. } => *code,
+            RenderError::InvalidState(_) | RenderError::Violations(_) => ErrorCode::InvalidState,
+            RenderError::NeedsReinit => ErrorCode::NeedsReinit,
+            RenderError::Critical { event: RenderEvent::DeviceError, .. } => ErrorCode::DeviceLost,
+            RenderError::Critical { event: RenderEvent::DisplayError, .. } => ErrorCode::DisplayError,
+            RenderError::Critical { .. } => ErrorCode::Unknown
+        }
+    }
+
+    /// recoverable is whether the server can still be used after the error without reinitializing it
+    pub fn recoverable(&self) -> bool {
+        match self {
+            RenderError::Server { recoverable, .. } => *recoverable,
+            RenderError::InvalidState(_) | RenderError::Violations(_) => true,
+            _ => false
+        }
+    }
+}
+
+/// owned_string copies a server owned C string into a rust string, null yields None
+///
+/// # Safety
+/// ptr must be null or point to a nul terminated string
+unsafe fn owned_string(ptr: *const i8) -> Option<String> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(ptr).to_string_lossy().into_owned())
+    }
+}
+
+/// take_string copies a string handed to the client by backend and releases the original, null yields None
+///
+/// # Safety
+/// ptr must be null or an error message returned by backend that has not been released yet
+unsafe fn take_string(backend: &dyn RenderBackend, ptr: *mut i8) -> Option<String> {
+    let string = owned_string(ptr);
+    if string.is_some() {
+        backend.free(ptr);
+    }
+    string
+}
+
+/// check converts the return value of an expr_* entry point of backend into a result
+///
+/// # Safety
+/// error must have been returned by backend and its message must not have been released yet
+unsafe fn check(backend: &dyn RenderBackend, error: ExprError) -> Result<(), RenderError> {
+    let message = take_string(backend, error.message_ptr());
+    match error.is_error() {
+        true => Err(RenderError::from_server(error.code(), error.recoverable(), message)),
+        false => Ok(())
+    }
+}
+
+/// Session is everything expr_init produced
+struct Session {
+    render_state: *mut RenderState,
+    server_version: String,
+    init_error: Option<String>,
+    extensions: Vec<ExtensionDescriptor>
+}
+
+impl Session {
+    /// init runs expr_init in backend and validates the result against metadata
+    unsafe fn init(backend: &dyn RenderBackend, metadata: &mut ClientMetadata) -> Result<Session, RenderError> {
+        let result = backend.init(metadata.as_incoming());
+        let init_error = take_string(backend, result.error.message_ptr()).filter(|_| result.error.is_error());
+        if result.server_version.is_null() || result.server_state.is_null() {
+            let message = init_error.unwrap_or_else(|| "no server state returned".to_string());
+            return Err(match result.error.code() {
+                ErrorCode::UnsupportedVersion => RenderError::UnsupportedVersion(message),
+                _ => RenderError::Init(message)
+            });
+        }
+
+        let names = |array, length| -> Vec<String> {
+            string_array(array, length).iter().map(|s| s.to_string_lossy().into_owned()).collect()
+        };
+        let server_extensions = names(result.server_extensions, result.server_extensions_length);
+        let accepted_extensions = names(result.accepted_extensions, result.accepted_extensions_length);
+        Ok(Session {
+            render_state: result.server_state,
+            server_version: owned_string(result.server_version).unwrap_or_default(),
+            init_error,
+            extensions: metadata.extensions().negotiate(&server_extensions, &accepted_extensions)
+                .into_iter()
+                .filter(|e| backend.exposes(e.symbols))
+                .collect()
+        })
+    }
+}
+
+/// RenderServer is a safe handle to a render server
+///
+/// The handle owns both the server backend (usually a loaded library) and the render state returned by expr_init.
+/// When the handle is dropped the server is disconnected (if connected) and then unloaded.
+///
+/// # Notes
+/// * Error strings returned by the server are copied into owned strings,
+///   the original is then released through expr_free if the server exposes it (see alloc)
+/// * Registered callbacks are remembered and registered again, with their user data, whenever the server is reloaded
+/// * In debug builds every state is validated before it is pushed, see validate_states
+/// * Once disconnected, pushing and registering callbacks fail with RenderError::Disconnected until the server
+///   is reconnected, the render state is never handed to a disconnected server except by reconnect
+pub struct RenderServer {
+    session: Session,
+    connected: bool,
+    frame_callback: Option<(FrameCallback, *mut c_void)>,
+    user_callback: Option<(UserCallback, *mut c_void)>,
+    validate: bool,
+    // NOTE: only tracked while the deltas extension is negotiated, None forces a full push
+    last_pushed: Option<owned::State>,
+    #[cfg(feature = "serde")]
+    recorder: Option<Recorder>,
+    backend: Box<dyn RenderBackend>,
+    // NOTE: the server may hold on to extension metadata, keep it alive as long as the server
+    metadata: ClientMetadata,
+    // NOTE: the server is handed the address of each of these as user data, they are dropped after it is unloaded
+    frame_closure: Option<Box<FrameClosure>>,
+    user_closure: Option<Box<UserClosure>>,
+    #[cfg(feature = "serde")]
+    intercept: Option<Box<record::Intercept>>
+}
+
+impl RenderServer {
+    /// new loads the render server selected by the environment (see crate::locate) and initializes it
+    pub fn new() -> Result<RenderServer, RenderError> {
+        RenderServer::open(crate::locate::select_server(None, None))
+    }
+
+    /// open loads the render server at path and initializes it with the default client metadata
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<RenderServer, RenderError> {
+        RenderServer::open_with(path, ClientMetadata::new(env!("CARGO_PKG_VERSION")))
+    }
+
+    /// open_with loads the render server at path and initializes it with metadata
+    ///
+    /// # Notes
+    /// * Loading a library runs its initialization routines,
+    ///   only servers conforming to the render API should be opened
+    /// * The server is rejected if the version it picks is not one of metadata's supported versions
+    pub fn open_with<P: AsRef<Path>>(path: P, metadata: ClientMetadata) -> Result<RenderServer, RenderError> {
+        RenderServer::with_backend(unsafe { DylibBackend::open(path)? }, metadata)
+    }
+
+    /// with_backend initializes the server reached through backend with metadata
+    ///
+    /// # Notes
+    /// The server is rejected if the version it picks is not one of metadata's supported versions
+    pub fn with_backend<B: RenderBackend + 'static>(backend: B, mut metadata: ClientMetadata) -> Result<RenderServer, RenderError> {
+        let session = unsafe { Session::init(&backend, &mut metadata)? };
+        let server = RenderServer {
+            session,
+            connected: true,
+            frame_callback: None,
+            user_callback: None,
+            validate: cfg!(debug_assertions),
+            last_pushed: None,
+            #[cfg(feature = "serde")]
+            recorder: None,
+            backend: Box::new(backend),
+            metadata,
+            frame_closure: None,
+            user_closure: None,
+            #[cfg(feature = "serde")]
+            intercept: None
+        };
+        // NOTE: the server is constructed first so a rejected server is still disconnected on drop
+        server.check_version()?;
+        Ok(server)
+    }
+
+    fn check_version(&self) -> Result<(), RenderError> {
+        check_server_version(&self.metadata.supported_versions(), &self.session.server_version)
+            .map(|_| ())
+            .map_err(RenderError::UnsupportedVersion)
+    }
+
+    /// path is the path the server library was loaded from, None for backends that are not loaded from a file
+    pub fn path(&self) -> Option<&Path> {
+        self.backend.path()
+    }
+
+    /// server_version is the version of the render API the server reported during init
+    pub fn server_version(&self) -> &str {
+        &self.session.server_version
+    }
+
+    /// init_error is the recoverable error the server reported during init, if any
+    pub fn init_error(&self) -> Option<&str> {
+        self.session.init_error.as_deref()
+    }
+
+    /// extensions lists the extensions negotiated with the server
+    ///
+    /// An extension is negotiated when the client enabled it, the server advertised it
+    /// and the server exposes every symbol it declares
+    pub fn extensions(&self) -> &[ExtensionDescriptor] {
+        &self.session.extensions
+    }
+
+    /// has_extension reports whether extension E was negotiated
+    pub fn has_extension<E: Extension>(&self) -> bool {
+        self.session.extensions.iter().any(|e| e.name == E::NAME)
+    }
+
+    /// extension_symbol resolves symbol of extension E
+    ///
+    /// None is returned if E was not negotiated or does not declare symbol
+    ///
+    /// # Safety
+    /// F must be the function pointer type E documents for symbol
+    pub unsafe fn extension_symbol<E: Extension, F: Copy>(&self, symbol: &str) -> Option<F> {
+        if !self.has_extension::<E>() || !E::SYMBOLS.contains(&symbol) {
+            return None;
+        }
+        self.backend.symbol(symbol).map(|f| std::mem::transmute_copy::<*const std::ffi::c_void, F>(&f))
+    }
+
+    /// render_state is the opaque server state, for use with extension symbols
+    ///
+    /// # Notes
+    /// The server may free its state on disconnect, it must not be used until the server is reconnected
+    pub fn render_state(&self) -> *mut RenderState {
+        self.session.render_state
+    }
+
+    /// connected_state is the render state, or RenderError::Disconnected if it must not be handed to the server
+    fn connected_state(&self) -> Result<*mut RenderState, RenderError> {
+        match self.connected {
+            true => Ok(self.session.render_state),
+            false => Err(RenderError::Disconnected)
+        }
+    }
+
+    /// validate_states sets whether every state is validated before it is pushed
+    ///
+    /// # Notes
+    /// Enabled by default in debug builds, states that fail validation are not pushed
+    pub fn validate_states(&mut self, enabled: bool) {
+        self.validate = enabled;
+    }
+
+    /// record starts recording every pushed state and user event to recorder
+    ///
+    /// # Notes
+    /// * Recording errors do not interrupt rendering, they are reported by Recorder::finish
+    /// * User events are only recorded while a user callback is registered (see record::Intercept)
+    #[cfg(feature = "serde")]
+    pub fn record(&mut self, recorder: Recorder) -> Result<(), RenderError> {
+        self.recorder = Some(recorder);
+        self.register_callbacks()
+    }
+
+    /// stop_recording stops recording, returning the recorder so it can be finished
+    #[cfg(feature = "serde")]
+    pub fn stop_recording(&mut self) -> Result<Option<Recorder>, RenderError> {
+        let recorder = self.recorder.take();
+        self.register_callbacks()?;
+        Ok(recorder)
+    }
+
+    /// push_state sends game_state to the server to be rendered
+    ///
+    /// # Notes
+    /// While recording, game_state is copied into an owned state to be recorded,
+    /// states that cannot be viewed safely (see state::StateView) are pushed but not recorded
+    pub fn push_state(&mut self, game_state: &mut State) -> Result<(), RenderError> {
+        #[cfg(feature = "serde")]
+        if let Some(recorder) = &self.recorder {
+            let owned = unsafe { super::state::StateView::new(game_state) }.ok().and_then(|v| owned::State::from_view(&v).ok());
+            if let Some(owned) = owned {
+                recorder.keep_state(&owned);
+            }
+        }
+        self.push_raw(game_state)
+    }
+
+    /// check_state validates game_state if validation is enabled, see validate_states
+    fn check_state(&self, game_state: &State) -> Result<(), RenderError> {
+        if !self.validate {
+            return Ok(());
+        }
+        let kinds = self.session.extensions.iter().flat_map(|e| e.kinds.iter().copied()).collect::<Vec<_>>();
+        unsafe { game_state.validate(&kinds) }.map_err(RenderError::Violations)
+    }
+
+    /// push_raw validates game_state if needed and sends it to the server
+    fn push_raw(&mut self, game_state: &mut State) -> Result<(), RenderError> {
+        let render_state = self.connected_state()?;
+        self.last_pushed = None;
+        self.check_state(game_state)?;
+        unsafe { check(self.backend.as_ref(), self.backend.push_state(render_state, game_state)) }
+    }
+
+    /// push sends an owned game_state to the server to be rendered
+    ///
+    /// If the deltas extension was negotiated only the changes since the last push are sent,
+    /// otherwise (and for the first push) the whole state is lowered and sent through push_state.
+    pub fn push(&mut self, game_state: &owned::State) -> Result<(), RenderError> {
+        let render_state = self.connected_state()?;
+        #[cfg(feature = "serde")]
+        if let Some(recorder) = &self.recorder {
+            recorder.keep_state(game_state);
+        }
+        let push_delta = unsafe { self.extension_symbol::<Deltas, PushDeltaFn>("expr_push_delta") };
+        let (push_delta, last) = match (push_delta, self.last_pushed.take()) {
+            (Some(push_delta), Some(last)) => (push_delta, last),
+            (push_delta, _) => {
+                let mut lowered = game_state.lower().map_err(|e| RenderError::InvalidState(e.to_string()))?;
+                self.push_raw(lowered.state_mut())?;
+                if push_delta.is_some() {
+                    self.last_pushed = Some(game_state.clone());
+                }
+                return Ok(());
+            }
+        };
+        // NOTE: the server still holds last when game_state is rejected, so the next push can be a delta against it
+        let valid = match self.validate {
+            true => game_state.lower()
+                .map_err(|e| RenderError::InvalidState(e.to_string()))
+                .and_then(|lowered| self.check_state(lowered.state())),
+            false => Ok(())
+        };
+        if let Err(e) = valid {
+            self.last_pushed = Some(last);
+            return Err(e);
+        }
+        let delta = Delta::between(&last, game_state);
+        let mut lowered = delta.lower().map_err(|e| RenderError::InvalidState(e.to_string()))?;
+        unsafe { check(self.backend.as_ref(), push_delta(render_state, lowered.as_mut_ptr()))? };
+        self.last_pushed = Some(game_state.clone());
+        Ok(())
+    }
+
+    /// frame_callback registers callback to be called when an event occurs while rendering a frame
+    ///
+    /// user_data is handed back to every call of callback, see on_frame for a safe alternative
+    ///
+    /// # Safety
+    /// user_data must stay valid for callback until another frame callback is registered or the server is dropped
+    pub unsafe fn frame_callback(&mut self, callback: FrameCallback, user_data: *mut c_void) -> Result<(), RenderError> {
+        let render_state = self.connected_state()?;
+        self.frame_callback = Some((callback, user_data));
+        check(self.backend.as_ref(), self.backend.frame_callback(render_state, callback, user_data))
+    }
+
+    /// user_callback registers callback to be called upon a user-triggered event
+    ///
+    /// user_data is handed back to every call of callback, see on_user for a safe alternative
+    ///
+    /// # Safety
+    /// user_data must stay valid for callback until another user callback is registered or the server is dropped
+    pub unsafe fn user_callback(&mut self, callback: UserCallback, user_data: *mut c_void) -> Result<(), RenderError> {
+        let render_state = self.connected_state()?;
+        self.user_callback = Some((callback, user_data));
+        #[cfg(feature = "serde")]
+        let intercept = self.recorder.as_ref().map(|recorder| record::Intercept::new(recorder.clone(), callback, user_data));
+        #[cfg(feature = "serde")]
+        let (callback, user_data) = intercept.as_ref().map_or((callback, user_data), |i| i.callback());
+        let result = check(self.backend.as_ref(), self.backend.user_callback(render_state, callback, user_data));
+        // NOTE: the previous intercept is only dropped once the server was handed its replacement
+        #[cfg(feature = "serde")]
+        {
+            self.intercept = intercept;
+        }
+        result
+    }
+
+    /// on_frame registers closure to be called when an event occurs while rendering a frame,
+    /// with the event, whether it is critical, the frame index and the server's message
+    ///
+    /// # Notes
+    /// closure replaces any frame callback, and may be called on any thread the server reports events from
+    pub fn on_frame<F>(&mut self, closure: F) -> Result<(), RenderError>
+        where F: FnMut(RenderEvent, bool, i32, Option<&str>) + Send + 'static
+    {
+        self.connected_state()?;
+        let closure: Box<FrameClosure> = Box::new(Mutex::new(Box::new(closure)));
+        // NOTE: the closure is kept until it is replaced or the server is dropped
+        let result = unsafe { self.frame_callback(frame_trampoline, &*closure as *const FrameClosure as *mut c_void) };
+        // NOTE: the previous closure is only dropped once the server was handed its replacement
+        self.frame_closure = Some(closure);
+        result
+    }
+
+    /// on_user registers closure to be called upon a user-triggered event, with the event and its text
+    ///
+    /// # Notes
+    /// closure replaces any user callback, and may be called on any thread the server reports events from
+    pub fn on_user<F>(&mut self, closure: F) -> Result<(), RenderError>
+        where F: FnMut(UserEvent, &str) + Send + 'static
+    {
+        self.connected_state()?;
+        let closure: Box<UserClosure> = Box::new(Mutex::new(Box::new(closure)));
+        // NOTE: the closure is kept until it is replaced or the server is dropped
+        let result = unsafe { self.user_callback(user_trampoline, &*closure as *const UserClosure as *mut c_void) };
+        self.user_closure = Some(closure);
+        result
+    }
+
+    /// disconnect notifies the server that it is about to be stopped
+    ///
+    /// # Notes
+    /// Disconnecting an already disconnected server does nothing
+    pub fn disconnect(&mut self) -> Result<(), RenderError> {
+        if !self.connected {
+            return Ok(());
+        }
+        self.connected = false;
+        match unsafe { self.backend.disconnect(self.session.render_state) } {
+            Some(error) => unsafe { check(self.backend.as_ref(), error) },
+            None => Ok(())
+        }
+    }
+
+    /// reconnect notifies the server that the client is reconnecting
+    ///
+    /// # Notes
+    /// If the server asks for expr_init to be rerun RenderError::NeedsReinit is returned
+    pub fn reconnect(&mut self) -> Result<(), RenderError> {
+        let error = unsafe { self.backend.reconnect(self.session.render_state) }.unwrap_or(ExprError::none());
+        unsafe { check(self.backend.as_ref(), error)? };
+        self.connected = true;
+        Ok(())
+    }
+
+    /// recover reconnects to the server, running expr_init again if the server asks for it
+    ///
+    /// # Notes
+    /// This is how a client restarts a server that crashed (see remote::RemoteBackend)
+    pub fn recover(&mut self) -> Result<(), RenderError> {
+        match self.reconnect() {
+            Err(RenderError::NeedsReinit) => self.reinit(),
+            result => result
+        }
+    }
+
+    /// reinit disconnects the server and runs expr_init again, replacing the render state
+    ///
+    /// # Notes
+    /// The previous render state is abandoned, it is up to the server whether it is reused
+    pub fn reinit(&mut self) -> Result<(), RenderError> {
+        self.last_pushed = None;
+        self.disconnect()?;
+        self.session = unsafe { Session::init(self.backend.as_ref(), &mut self.metadata)? };
+        self.connected = true;
+        self.check_version()?;
+        self.register_callbacks()
+    }
+
+    /// reload disconnects the server, loads the library at path again and reconnects to it
+    ///
+    /// The render state is preserved across the reload,
+    /// if the new server asks for expr_init to be rerun a full init is performed instead.
+    ///
+    /// # Notes
+    /// * The new library is loaded from a temporary copy of path before the old one is unloaded,
+    ///   if it cannot be loaded (or the backend cannot be reloaded) the current server is left untouched
+    /// * Negotiated extensions whose symbols the new library lacks are dropped
+    pub fn reload(&mut self) -> Result<(), RenderError> {
+        let backend = self.backend.reload()?;
+        self.last_pushed = None;
+        self.disconnect()?;
+        // NOTE: the old library is unloaded here
+        self.backend = backend;
+        let backend = &self.backend;
+        self.session.extensions.retain(|e| backend.exposes(e.symbols));
+        match self.reconnect() {
+            Ok(()) => self.register_callbacks(),
+            Err(RenderError::NeedsReinit) => self.reinit(),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// register_callbacks registers every remembered callback with the server
+    fn register_callbacks(&mut self) -> Result<(), RenderError> {
+        // NOTE: every remembered user data was promised valid when its callback was registered
+        if let Some((callback, user_data)) = self.frame_callback {
+            unsafe { self.frame_callback(callback, user_data)? };
+        }
+        if let Some((callback, user_data)) = self.user_callback {
+            unsafe { self.user_callback(callback, user_data)? };
+        }
+        Ok(())
+    }
+}
+
+impl Drop for RenderServer {
+    fn drop(&mut self) {
+        let _ = self.disconnect();
+    }
+}
+
+/// frame_trampoline calls the FrameClosure user_data points to
+unsafe extern "C" fn frame_trampoline(result: RenderResult, frame: i32, _game_state: *mut State, message: *mut i8, user_data: *mut c_void) {
+    let closure = &*(user_data as *const FrameClosure);
+    let message = result.message().or_else(|| c_str(message)).map(|m| m.to_string_lossy());
+    let mut closure = closure.lock().unwrap_or_else(|e| e.into_inner());
+    closure(result.event(), result.critical(), frame, message.as_deref());
+}
+
+/// user_trampoline calls the UserClosure user_data points to
+unsafe extern "C" fn user_trampoline(event: UserEvent, text: *mut i8, user_data: *mut c_void) {
+    let closure = &*(user_data as *const UserClosure);
+    let text = c_str(text).map(|t| t.to_string_lossy()).unwrap_or_default();
+    let mut closure = closure.lock().unwrap_or_else(|e| e.into_inner());
+    closure(event, &text);
+}
@@ -0,0 +1,257 @@
+//! menu models the section tree of a MenuState
+//!
+//! Section items are either plaintext ("t:<string>") or a reference to one of the section's
+//! subsections ("s:<index>"). Menu navigates an owned menu: the current section is the selected top level
+//! section, or the deepest subsection entered through descend, every entered subsection has the open attribute.
+
+use std::error::Error;
+use std::ffi::CStr;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use super::owned::{MenuState, Section};
+
+/// OPEN_ATTR is the attribute set on every subsection the user has descended into
+pub const OPEN_ATTR: &str = "open";
+
+/// MenuItem is a decoded Section item
+///
+/// # Variants
+/// * Text - t:\<string\>, a plaintext item
+/// * Section - s:\<index\>, the subsection at index in the section's subsections
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MenuItem {
+    Text(String),
+    Section(usize)
+}
+
+/// MenuError is why a menu item could not be decoded or resolved
+///
+/// # Variants
+/// * InvalidItem - the item is neither t:\<string\> nor s:\<index\>
+/// * SectionOutOfBounds - an s: item refers to a subsection that does not exist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MenuError {
+    InvalidItem(String),
+    SectionOutOfBounds { index: usize, subsections: usize }
+}
+
+impl Display for MenuError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MenuError::InvalidItem(item) => write!(f, "{:?} is not a valid menu item", item),
+            MenuError::SectionOutOfBounds { index, subsections } =>
+                write!(f, "subsection {} is out of bounds, section has {} subsections", index, subsections)
+        }
+    }
+}
+
+impl Error for MenuError {}
+
+impl FromStr for MenuItem {
+    type Err = MenuError;
+
+    fn from_str(item: &str) -> Result<MenuItem, MenuError> {
+        if let Some(text) = item.strip_prefix("t:") {
+            return Ok(MenuItem::Text(text.to_string()));
+        }
+        item.strip_prefix("s:")
+            .and_then(|index| index.parse().ok())
+            .map(MenuItem::Section)
+            .ok_or_else(|| MenuError::InvalidItem(item.to_string()))
+    }
+}
+
+impl Display for MenuItem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MenuItem::Text(text) => write!(f, "t:{}", text),
+            MenuItem::Section(index) => write!(f, "s:{}", index)
+        }
+    }
+}
+
+impl MenuItem {
+    /// from_c_str decodes an item read from a C string
+    pub fn from_c_str(item: &CStr) -> Result<MenuItem, MenuError> {
+        item.to_str().map_err(|_| MenuError::InvalidItem(item.to_string_lossy().into_owned()))?.parse()
+    }
+}
+
+/// selection is selected clamped into a list of length items, -1 if the list is empty
+pub(crate) fn selection(selected: isize, length: usize) -> isize {
+    if length == 0 {
+        -1
+    } else {
+        selected.clamp(0, length as isize - 1)
+    }
+}
+
+impl Section {
+    /// resolve is the subsection item refers to, None for text items
+    pub fn resolve(&self, item: &MenuItem) -> Result<Option<&Section>, MenuError> {
+        match item {
+            MenuItem::Text(_) => Ok(None),
+            MenuItem::Section(index) => self.subsections.get(*index).map(Some).ok_or(MenuError::SectionOutOfBounds {
+                index: *index,
+                subsections: self.subsections.len()
+            })
+        }
+    }
+
+    /// check checks every s: item in this section and its subsections is in bounds
+    pub fn check(&self) -> Result<(), MenuError> {
+        self.items.iter().try_for_each(|item| self.resolve(item).map(|_| ()))?;
+        self.subsections.iter().try_for_each(Section::check)
+    }
+
+    /// selected is the selected item, if any
+    pub fn selected(&self) -> Option<&MenuItem> {
+        usize::try_from(self.selected_item).ok().and_then(|idx| self.items.get(idx))
+    }
+
+    /// is_open is whether the user has descended into this section
+    pub fn is_open(&self) -> bool {
+        self.attrs.iter().any(|a| a == OPEN_ATTR)
+    }
+
+    fn set_open(&mut self, open: bool) {
+        self.attrs.retain(|a| a != OPEN_ATTR);
+        if open {
+            self.attrs.push(OPEN_ATTR.to_string());
+        }
+    }
+}
+
+/// Menu navigates an owned MenuState, keeping every selection in range
+///
+/// # Notes
+/// path is the subsection index of every entered section, starting from the selected top level section
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Menu {
+    state: MenuState,
+    path: Vec<usize>
+}
+
+impl Menu {
+    /// new clamps every selection in state into range and recovers the path from the open attributes
+    pub fn new(mut state: MenuState) -> Result<Menu, MenuError> {
+        state.sections.iter().try_for_each(Section::check)?;
+        state.selected_section = selection(state.selected_section, state.sections.len());
+        state.sections.iter_mut().for_each(clamp);
+        let mut menu = Menu { state, path: Vec::new() };
+        while let Some(index) = menu.selected_subsection().filter(|s| s.1.is_open()).map(|s| s.0) {
+            menu.path.push(index);
+        }
+        Ok(menu)
+    }
+
+    pub fn state(&self) -> &MenuState {
+        &self.state
+    }
+
+    pub fn into_state(self) -> MenuState {
+        self.state
+    }
+
+    pub fn path(&self) -> &[usize] {
+        &self.path
+    }
+
+    /// current is the section the user is in, None if the menu has no sections
+    pub fn current(&self) -> Option<&Section> {
+        let top = self.state.sections.get(usize::try_from(self.state.selected_section).ok()?)?;
+        Some(self.path.iter().fold(top, |section, idx| &section.subsections[*idx]))
+    }
+
+    fn current_mut(&mut self) -> Option<&mut Section> {
+        let top = self.state.sections.get_mut(usize::try_from(self.state.selected_section).ok()?)?;
+        Some(self.path.iter().fold(top, |section, idx| &mut section.subsections[*idx]))
+    }
+
+    /// selected_item is the selected item of the current section
+    pub fn selected_item(&self) -> Option<&MenuItem> {
+        self.current().and_then(Section::selected)
+    }
+
+    /// selected_subsection is the index and section the selected item refers to, if it is a subsection
+    fn selected_subsection(&self) -> Option<(usize, &Section)> {
+        let current = self.current()?;
+        match current.selected()? {
+            MenuItem::Section(idx) => current.subsections.get(*idx).map(|s| (*idx, s)),
+            MenuItem::Text(_) => None
+        }
+    }
+
+    /// select_next selects the next item of the current section, wrapping around
+    pub fn select_next(&mut self) {
+        self.step_item(1);
+    }
+
+    /// select_previous selects the previous item of the current section, wrapping around
+    pub fn select_previous(&mut self) {
+        self.step_item(-1);
+    }
+
+    fn step_item(&mut self, step: isize) {
+        if let Some(section) = self.current_mut() {
+            section.selected_item = wrap(section.selected_item, step, section.items.len());
+        }
+    }
+
+    /// next_section selects the next top level section, leaving any entered subsections
+    pub fn next_section(&mut self) {
+        self.step_section(1);
+    }
+
+    /// previous_section selects the previous top level section, leaving any entered subsections
+    pub fn previous_section(&mut self) {
+        self.step_section(-1);
+    }
+
+    fn step_section(&mut self, step: isize) {
+        while self.ascend() {}
+        self.state.selected_section = wrap(self.state.selected_section, step, self.state.sections.len());
+    }
+
+    /// descend enters the subsection the selected item refers to
+    ///
+    /// # Notes
+    /// Returns false if the selected item is not a subsection
+    pub fn descend(&mut self) -> bool {
+        let index = match self.selected_subsection() {
+            Some((index, _)) => index,
+            None => return false
+        };
+        self.path.push(index);
+        self.current_mut().unwrap().set_open(true);
+        true
+    }
+
+    /// ascend leaves the current subsection
+    ///
+    /// # Notes
+    /// Returns false if the current section is a top level section
+    pub fn ascend(&mut self) -> bool {
+        if self.path.is_empty() {
+            return false;
+        }
+        self.current_mut().unwrap().set_open(false);
+        self.path.pop();
+        true
+    }
+}
+
+/// clamp clamps the selected item of section and its subsections into range
+fn clamp(section: &mut Section) {
+    section.selected_item = selection(section.selected_item, section.items.len());
+    section.subsections.iter_mut().for_each(clamp);
+}
+
+/// wrap moves selected by step in a list of length items, wrapping around at either end
+fn wrap(selected: isize, step: isize, length: usize) -> isize {
+    if length == 0 {
+        return -1;
+    }
+    (selected.max(0) + step).rem_euclid(length as isize)
+}
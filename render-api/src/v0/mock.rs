@@ -0,0 +1,363 @@
+//! mock is an in process render server, so clients can be tested without building a server library
+//!
+//! MockBackend implements RenderBackend and is handed to RenderServer::with_backend. Clones of a MockBackend
+//! share the same server, so a test keeps one clone to inspect pushed states, send user and frame events
+//! through the registered callbacks and make any entry point fail. Extension symbols a test implements itself
+//! are exposed through with_symbol.
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::{c_void, CString};
+use std::path::{Path, PathBuf};
+use std::ptr::null_mut;
+use std::sync::{Arc, Mutex, MutexGuard};
+use crate::alloc::{self, Allocations};
+use crate::error::{ErrorCode, ExprError};
+use crate::negotiate::API_VERSIONS;
+use crate::{InitResult, IncomingMetadata, RenderState};
+use super::backend::RenderBackend;
+use super::handle::{FrameCallback, RenderError, UserCallback};
+use super::owned;
+use super::state::{State, StateView};
+use super::{RenderEvent, RenderResult, UserEvent};
+
+/// EntryPoint names an entry point of the render API
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EntryPoint {
+    Init,
+    PushState,
+    FrameCallback,
+    UserCallback,
+    Disconnect,
+    Reconnect
+}
+
+/// Mock is the state shared by every clone of a MockBackend
+///
+/// # Notes
+/// Every error handed to the client is freshly allocated and counted in allocations until the client frees it
+struct Mock {
+    version: CString,
+    errors: HashMap<EntryPoint, (ErrorCode, bool, String)>,
+    once: HashSet<EntryPoint>,
+    allocations: Allocations,
+    needs_reinit: bool,
+    inits: usize,
+    reloads: usize,
+    connected: bool,
+    pushed: Vec<owned::State>,
+    frame_callback: Option<(FrameCallback, *mut c_void)>,
+    user_callback: Option<(UserCallback, *mut c_void)>,
+    symbols: HashMap<String, *const c_void>,
+    extensions: Vec<CString>,
+    extension_ptrs: Vec<*mut i8>
+}
+
+// NOTE: user data is only handed back to the callback it was registered with, the mock never dereferences it
+unsafe impl Send for Mock {}
+
+/// MockBackend is a render server that records what it is sent instead of rendering it
+///
+/// # Notes
+/// * Pushed states are copied into owned states, states that cannot be viewed safely are rejected
+///   with an error like a conforming server would
+/// * Reloading yields a clone, so the server's state is carried across the reload
+/// * Errors are released through RenderBackend::free like expr_free, outstanding counts those the client still holds,
+///   strings the mock did not hand out (or already released) are ignored
+/// * A mock has no library unless one is given with with_path, the file is never read
+/// * A mock exposes no extension symbols unless they are given with with_symbol
+#[derive(Clone)]
+pub struct MockBackend {
+    mock: Arc<Mutex<Mock>>,
+    path: Option<PathBuf>
+}
+
+impl Default for MockBackend {
+    fn default() -> MockBackend {
+        MockBackend::new()
+    }
+}
+
+impl MockBackend {
+    /// new creates a server reporting the newest version in API_VERSIONS
+    pub fn new() -> MockBackend {
+        MockBackend::with_version(API_VERSIONS[API_VERSIONS.len() - 1])
+    }
+
+    /// with_version creates a server reporting version from expr_init
+    ///
+    /// # Panics
+    /// Panics if version contains a nul byte
+    pub fn with_version(version: &str) -> MockBackend {
+        let mock = Arc::new(Mutex::new(Mock {
+            version: CString::new(version).expect("version contains a nul byte"),
+            errors: HashMap::new(),
+            once: HashSet::new(),
+            allocations: Allocations::new(),
+            needs_reinit: false,
+            inits: 0,
+            reloads: 0,
+            connected: false,
+            pushed: Vec::new(),
+            frame_callback: None,
+            user_callback: None,
+            symbols: HashMap::new(),
+            extensions: Vec::new(),
+            extension_ptrs: Vec::new()
+        }));
+        MockBackend { mock, path: None }
+    }
+
+    /// with_path reports path as the library the server was loaded from, so it can be watched (see reload::HotReload)
+    pub fn with_path(self, path: impl AsRef<Path>) -> MockBackend {
+        MockBackend { path: Some(path.as_ref().to_path_buf()), ..self }
+    }
+
+    /// with_symbol exposes function as symbol, advertising extension in InitResult's server_extensions
+    ///
+    /// # Panics
+    /// Panics if extension contains a nul byte
+    pub fn with_symbol(self, extension: &str, symbol: &str, function: *const c_void) -> MockBackend {
+        {
+            let mut mock = self.lock();
+            mock.symbols.insert(symbol.to_string(), function);
+            if !mock.extensions.iter().any(|e| e.as_bytes() == extension.as_bytes()) {
+                mock.extensions.push(CString::new(extension).expect("extension contains a nul byte"));
+            }
+            mock.extension_ptrs = mock.extensions.iter().map(|e| e.as_ptr() as *mut i8).collect();
+        }
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Mock> {
+        self.mock.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// fail makes entry return a recoverable ErrorCode::Unknown with message until it is cleared with succeed
+    ///
+    /// # Notes
+    /// A failing Init is reported as unrecoverable
+    ///
+    /// # Panics
+    /// Panics if message contains a nul byte
+    pub fn fail(&self, entry: EntryPoint, message: &str) {
+        self.fail_with(entry, ErrorCode::Unknown, true, message);
+    }
+
+    /// fail_with makes entry return code with message until it is cleared with succeed
+    ///
+    /// # Panics
+    /// Panics if message contains a nul byte
+    pub fn fail_with(&self, entry: EntryPoint, code: ErrorCode, recoverable: bool, message: &str) {
+        assert!(!message.contains('\0'), "message contains a nul byte");
+        let mut mock = self.lock();
+        mock.errors.insert(entry, (code, recoverable, message.to_string()));
+        mock.once.remove(&entry);
+    }
+
+    /// fail_once_with makes the next call of entry return code with message, later calls succeed
+    ///
+    /// # Panics
+    /// Panics if message contains a nul byte
+    pub fn fail_once_with(&self, entry: EntryPoint, code: ErrorCode, recoverable: bool, message: &str) {
+        self.fail_with(entry, code, recoverable, message);
+        self.lock().once.insert(entry);
+    }
+
+    /// succeed stops entry from failing
+    pub fn succeed(&self, entry: EntryPoint) {
+        let mut mock = self.lock();
+        mock.errors.remove(&entry);
+        mock.once.remove(&entry);
+    }
+
+    /// request_reinit sets whether expr_reconnect asks for expr_init to be rerun
+    pub fn request_reinit(&self, needs_reinit: bool) {
+        self.lock().needs_reinit = needs_reinit;
+    }
+
+    /// inits is the number of times expr_init was called
+    pub fn inits(&self) -> usize {
+        self.lock().inits
+    }
+
+    /// reloads is the number of times the server was reloaded
+    pub fn reloads(&self) -> usize {
+        self.lock().reloads
+    }
+
+    /// is_connected is whether the client is connected, from expr_init or expr_reconnect until expr_disconnect
+    pub fn is_connected(&self) -> bool {
+        self.lock().connected
+    }
+
+    /// pushed is every state pushed so far, oldest first
+    pub fn pushed(&self) -> Vec<owned::State> {
+        self.lock().pushed.clone()
+    }
+
+    /// last_pushed is the most recently pushed state
+    pub fn last_pushed(&self) -> Option<owned::State> {
+        self.lock().pushed.last().cloned()
+    }
+
+    /// clear_pushed forgets every pushed state
+    pub fn clear_pushed(&self) {
+        self.lock().pushed.clear();
+    }
+
+    /// send_user_event reports a user event to the registered user callback
+    ///
+    /// # Notes
+    /// Returns false if no user callback is registered
+    ///
+    /// # Panics
+    /// Panics if text contains a nul byte
+    pub fn send_user_event(&self, event: UserEvent, text: &str) -> bool {
+        let callback = self.lock().user_callback;
+        let text = CString::new(text).expect("text contains a nul byte");
+        match callback {
+            Some((callback, user_data)) => {
+                unsafe { callback(event, text.as_ptr() as *mut i8, user_data) };
+                true
+            }
+            None => false
+        }
+    }
+
+    /// send_frame_event reports event on frame to the registered frame callback
+    ///
+    /// The callback is handed the last pushed state, or null if nothing has been pushed
+    ///
+    /// # Notes
+    /// Returns false if no frame callback is registered
+    ///
+    /// # Panics
+    /// Panics if message contains a nul byte
+    pub fn send_frame_event(&self, event: RenderEvent, critical: bool, frame: i32, message: Option<&str>) -> bool {
+        let (callback, last) = {
+            let mock = self.lock();
+            (mock.frame_callback, mock.pushed.last().cloned())
+        };
+        let (callback, user_data) = match callback {
+            Some(callback) => callback,
+            None => return false
+        };
+        let message = message.map(|m| CString::new(m).expect("message contains a nul byte"));
+        let message = message.as_ref().map_or(null_mut(), |m| m.as_ptr() as *mut i8);
+        // NOTE: pushed states were lowered once already, so lowering them again cannot fail
+        let mut lowered = last.as_ref().map(|state| state.lower().expect("pushed state lowers"));
+        let game_state = lowered.as_mut().map_or(null_mut(), |l| l.as_mut_ptr());
+        unsafe { callback(RenderResult::new(event, critical, message), frame, game_state, message, user_data) };
+        true
+    }
+
+    /// outstanding is the number of errors handed to the client and not released through expr_free yet
+    pub fn outstanding(&self) -> usize {
+        self.lock().allocations.outstanding()
+    }
+
+    /// error allocates the error entry is configured to return, ExprError::none if it succeeds
+    fn error(mock: &mut Mock, entry: EntryPoint) -> ExprError {
+        let error = match mock.once.remove(&entry) {
+            true => mock.errors.remove(&entry),
+            false => mock.errors.get(&entry).cloned()
+        };
+        match error {
+            Some((code, recoverable, message)) => {
+                let message = mock.allocations.string(message);
+                match recoverable {
+                    true => ExprError::new(code, message),
+                    false => ExprError::fatal(code, message)
+                }
+            }
+            None => ExprError::none()
+        }
+    }
+
+}
+
+impl RenderBackend for MockBackend {
+    unsafe fn init(&self, _metadata: IncomingMetadata) -> InitResult {
+        let mut mock = self.lock();
+        mock.inits += 1;
+        if mock.errors.contains_key(&EntryPoint::Init) {
+            let error = MockBackend::error(&mut mock, EntryPoint::Init);
+            return InitResult::failed(ExprError::fatal(error.code(), error.message_ptr()));
+        }
+        mock.connected = true;
+        let extensions = mock.extension_ptrs.as_mut_ptr();
+        InitResult::new(mock.version.as_ptr() as *mut i8, Arc::as_ptr(&self.mock) as *mut RenderState)
+            .with_extensions(extensions, mock.extension_ptrs.len() as isize, null_mut(), 0)
+    }
+
+    unsafe fn push_state(&self, _render_state: *mut RenderState, game_state: *mut State) -> ExprError {
+        let mut mock = self.lock();
+        if mock.errors.contains_key(&EntryPoint::PushState) {
+            return MockBackend::error(&mut mock, EntryPoint::PushState);
+        }
+        let state = StateView::from_ptr(game_state)
+            .map_err(|e| e.to_string())
+            .and_then(|view| owned::State::from_view(&view).map_err(|e| e.to_string()));
+        match state {
+            Ok(state) => {
+                mock.pushed.push(state);
+                ExprError::none()
+            }
+            Err(e) => {
+                let error = mock.allocations.string(format!("invalid game state: {}", e));
+                ExprError::new(ErrorCode::InvalidState, error)
+            }
+        }
+    }
+
+    unsafe fn frame_callback(&self, _render_state: *mut RenderState, callback: FrameCallback, user_data: *mut c_void) -> ExprError {
+        let mut mock = self.lock();
+        if !mock.errors.contains_key(&EntryPoint::FrameCallback) {
+            mock.frame_callback = Some((callback, user_data));
+        }
+        MockBackend::error(&mut mock, EntryPoint::FrameCallback)
+    }
+
+    unsafe fn user_callback(&self, _render_state: *mut RenderState, callback: UserCallback, user_data: *mut c_void) -> ExprError {
+        let mut mock = self.lock();
+        if !mock.errors.contains_key(&EntryPoint::UserCallback) {
+            mock.user_callback = Some((callback, user_data));
+        }
+        MockBackend::error(&mut mock, EntryPoint::UserCallback)
+    }
+
+    unsafe fn disconnect(&self, _render_state: *mut RenderState) -> Option<ExprError> {
+        let mut mock = self.lock();
+        mock.connected = false;
+        Some(MockBackend::error(&mut mock, EntryPoint::Disconnect))
+    }
+
+    unsafe fn reconnect(&self, _render_state: *mut RenderState) -> Option<ExprError> {
+        let mut mock = self.lock();
+        if mock.needs_reinit {
+            return Some(ExprError::new(ErrorCode::NeedsReinit, null_mut()));
+        }
+        let error = MockBackend::error(&mut mock, EntryPoint::Reconnect);
+        mock.connected = !error.is_error();
+        Some(error)
+    }
+
+    unsafe fn free(&self, string: *mut i8) {
+        if self.lock().allocations.owns(string) {
+            alloc::free(string);
+        }
+    }
+
+    fn symbol(&self, symbol: &str) -> Option<*const c_void> {
+        self.lock().symbols.get(symbol).copied()
+    }
+
+    fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    fn reload(&self) -> Result<Box<dyn RenderBackend>, RenderError> {
+        self.lock().reloads += 1;
+        Ok(Box::new(self.clone()))
+    }
+}
@@ -3,13 +3,54 @@
 //! In this document "client" refers to the game engine proper
 //! and "server" refers to the rendering engine the client loads.
 //!
-//! This version provides two submodules: client and state.
-//! The client submodule defines what functions a client should expect to be callable.
-//! The state submodule is the type used to communicate state from the client to the server.
+//! This version provides the following submodules:
+//! * attribute parses and serializes the "name:value" attributes attached to state.
+//! * backend abstracts how a handle reaches a server, loaded from a library or implemented in process.
+//! * client defines what functions a client should expect to be callable.
+//! * conformance checks a render server against the rules of the render API.
+//! * diff computes deltas between states and sends them to servers that accept them.
+//! * events aggregates the events a server reports through the frame callback.
+//! * handle provides a safe, owned handle around a loaded server.
+//! * menu decodes section items and navigates the section tree of a menu.
+//! * mock is an in process render server for testing clients without a server library.
+//! * owned provides owned builders for state that lower into the repr(C) types.
+//! * record records the states and user events of a session to a file and reads them back (serde feature).
+//! * remote runs a server in a child process and talks to it over a socket (serde feature, unix only).
+//! * reload watches a loaded server and reloads it when its library changes.
+//! * serial saves owned states as JSON or a compact binary format (serde feature).
+//! * state is the type used to communicate state from the client to the server.
+//! * thread runs a server on a dedicated render thread, reporting its events over a channel.
 
+use std::ffi::CStr;
+use crate::c_str;
+
+pub mod attribute;
+#[cfg(feature = "client")]
+pub mod backend;
 #[cfg(feature = "client")]
 pub mod client;
+#[cfg(feature = "client")]
+pub mod conformance;
+pub mod diff;
+#[cfg(feature = "client")]
+pub mod events;
+#[cfg(feature = "client")]
+pub mod handle;
+pub mod menu;
+#[cfg(feature = "client")]
+pub mod mock;
+pub mod owned;
+#[cfg(feature = "serde")]
+pub mod record;
+#[cfg(feature = "client")]
+pub mod reload;
+#[cfg(all(feature = "client", feature = "serde", unix))]
+pub mod remote;
+#[cfg(feature = "serde")]
+pub mod serial;
 pub mod state;
+#[cfg(feature = "client")]
+pub mod thread;
 
 /// RenderEvent indicates what event, if any, happened when rendering a frame
 ///
@@ -19,6 +60,8 @@ pub mod state;
 /// * DisplayError - the frame was rendered but could not be displayed
 /// * DeviceError - an error occured in the driver doing the actual rendering
 #[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RenderEvent {
     FrameSkipped,
     RenderError,
@@ -39,12 +82,35 @@ pub struct RenderResult {
     message: *mut i8
 }
 
+impl RenderResult {
+    /// new creates a result for event, message is owned by the server and may be null
+    pub fn new(event: RenderEvent, critical: bool, message: *mut i8) -> RenderResult {
+        RenderResult { event, critical, message }
+    }
+
+    pub fn event(&self) -> RenderEvent {
+        self.event
+    }
+
+    pub fn critical(&self) -> bool {
+        self.critical
+    }
+
+    /// # Safety
+    /// The result must be the one passed to the frame callback and the callback must not have returned
+    pub unsafe fn message(&self) -> Option<&CStr> {
+        c_str(self.message)
+    }
+}
+
 /// UserEvent indicates what kind of user input was received
 ///
 /// # Variants
 /// * Input - literal button input, keyboard, joystick, controller button, etc
 /// * Command - text command entered in a field of some kind
 #[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum UserEvent {
     Input,
     Command
@@ -0,0 +1,629 @@
+//! owned is a Vec and String based counterpart of the state module
+//!
+//! Every type here mirrors the type of the same name in state, see state for what each field means.
+//! Owned states are built with the with_* methods and lowered into a state::State through
+//! State::lower, which keeps every pointer valid for as long as the LoweredState is alive.
+
+use std::any::Any;
+use std::ffi::{CStr, CString, NulError};
+use std::marker::PhantomData;
+use std::ptr::null_mut;
+use crate::c_array;
+use super::menu::{selection, MenuError, MenuItem};
+use super::state;
+use super::state::{Attribute, DrawableView, RenderContext, SectionView, StateView, Strings};
+
+/// Drawable represents a tile to be drawn on the world
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Drawable {
+    pub kind: String,
+    pub position: (i64, i64, i64),
+    pub span: (i64, i64, i64),
+    pub attrs: Vec<String>
+}
+
+impl Drawable {
+    /// new is a drawable of kind at the origin spanning a single tile
+    pub fn new(kind: impl Into<String>) -> Drawable {
+        Drawable { kind: kind.into(), position: (0, 0, 0), span: (1, 1, 1), attrs: Vec::new() }
+    }
+
+    pub fn with_position(self, x: i64, y: i64, z: i64) -> Drawable {
+        Drawable { position: (x, y, z), ..self }
+    }
+
+    pub fn with_span(self, x: i64, y: i64, z: i64) -> Drawable {
+        Drawable { span: (x, y, z), ..self }
+    }
+
+    pub fn with_attr(mut self, attr: impl Into<String>) -> Drawable {
+        self.attrs.push(attr.into());
+        self
+    }
+}
+
+/// Actor represents an in-world character, either a player or an AI
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Actor {
+    pub name: String,
+    pub description: Option<String>,
+    pub draw: Drawable,
+    pub attrs: Vec<String>
+}
+
+impl Actor {
+    /// new is an actor named name drawn as kind (player or computer)
+    pub fn new(name: impl Into<String>, kind: impl Into<String>) -> Actor {
+        Actor { name: name.into(), description: None, draw: Drawable::new(kind), attrs: Vec::new() }
+    }
+
+    pub fn with_description(self, description: impl Into<String>) -> Actor {
+        Actor { description: Some(description.into()), ..self }
+    }
+
+    pub fn with_position(self, x: i64, y: i64, z: i64) -> Actor {
+        Actor { draw: self.draw.clone().with_position(x, y, z), ..self }
+    }
+
+    pub fn with_draw(self, draw: Drawable) -> Actor {
+        Actor { draw, ..self }
+    }
+
+    pub fn with_attr(mut self, attr: impl Into<String>) -> Actor {
+        self.attrs.push(attr.into());
+        self
+    }
+}
+
+/// Terrain represents a world tile that may or may not be traversable
+///
+/// # Notes
+/// The default terrain is impassable
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Terrain {
+    pub draw: Drawable,
+    pub attrs: Vec<String>
+}
+
+impl Default for Terrain {
+    fn default() -> Terrain {
+        Terrain::new("impassable")
+    }
+}
+
+impl Terrain {
+    /// new is terrain drawn as kind (terminal, entrance, impassable, ...)
+    pub fn new(kind: impl Into<String>) -> Terrain {
+        Terrain { draw: Drawable::new(kind), attrs: Vec::new() }
+    }
+
+    pub fn with_draw(self, draw: Drawable) -> Terrain {
+        Terrain { draw, ..self }
+    }
+
+    pub fn with_attr(mut self, attr: impl Into<String>) -> Terrain {
+        self.attrs.push(attr.into());
+        self
+    }
+}
+
+/// WorldState is the collective game state of all terrain in the current world
+///
+/// Terrain is stored flattened in the order documented on state::WorldState
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(try_from = "RawWorldState"))]
+pub struct WorldState {
+    dimensions: (usize, usize, usize),
+    terrain: Vec<Terrain>,
+    pub attrs: Vec<String>
+}
+
+impl WorldState {
+    /// new is a world of the given dimensions filled with the default (impassable) terrain
+    ///
+    /// # Panics
+    /// Panics if the world has more tiles than fit in a usize
+    pub fn new(len_x: usize, len_y: usize, len_z: usize) -> WorldState {
+        let length = len_x.checked_mul(len_y).and_then(|l| l.checked_mul(len_z)).expect("world dimensions overflow");
+        let mut world = WorldState {
+            dimensions: (len_x, len_y, len_z),
+            terrain: vec![Terrain::default(); length],
+            attrs: Vec::new()
+        };
+        for z in 0..len_z {
+            for y in 0..len_y {
+                for x in 0..len_x {
+                    let idx = world.index(x, y, z).unwrap();
+                    world.terrain[idx].draw.position = (x as i64, y as i64, z as i64);
+                }
+            }
+        }
+        world
+    }
+
+    /// fill is new with every tile set to terrain
+    pub fn fill(len_x: usize, len_y: usize, len_z: usize, terrain: &Terrain) -> WorldState {
+        let mut world = WorldState::new(len_x, len_y, len_z);
+        for tile in world.terrain.iter_mut() {
+            let position = tile.draw.position;
+            *tile = terrain.clone();
+            tile.draw.position = position;
+        }
+        world
+    }
+
+    /// dimensions is the (x, y, z) length of the world
+    pub fn dimensions(&self) -> (usize, usize, usize) {
+        self.dimensions
+    }
+
+    /// index is the index of (x, y, z) in the flattened terrain, None if it is out of bounds
+    pub fn index(&self, x: usize, y: usize, z: usize) -> Option<usize> {
+        let (len_x, len_y, len_z) = self.dimensions;
+        if x < len_x && y < len_y && z < len_z {
+            Some(x + y * len_x + z * len_x * len_y)
+        } else {
+            None
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> Option<&Terrain> {
+        self.index(x, y, z).map(|idx| &self.terrain[idx])
+    }
+
+    pub fn get_mut(&mut self, x: usize, y: usize, z: usize) -> Option<&mut Terrain> {
+        self.index(x, y, z).map(|idx| &mut self.terrain[idx])
+    }
+
+    /// set replaces the terrain at (x, y, z), its position is set to match
+    ///
+    /// # Panics
+    /// Panics if (x, y, z) is out of bounds
+    pub fn set(&mut self, x: usize, y: usize, z: usize, mut terrain: Terrain) {
+        let idx = self.index(x, y, z).expect("terrain position out of bounds");
+        terrain.draw.position = (x as i64, y as i64, z as i64);
+        self.terrain[idx] = terrain;
+    }
+
+    /// with_terrain is set in builder form
+    pub fn with_terrain(mut self, x: usize, y: usize, z: usize, terrain: Terrain) -> WorldState {
+        self.set(x, y, z, terrain);
+        self
+    }
+
+    pub fn with_attr(mut self, attr: impl Into<String>) -> WorldState {
+        self.attrs.push(attr.into());
+        self
+    }
+
+    /// terrain is the flattened terrain
+    pub fn terrain(&self) -> &[Terrain] {
+        &self.terrain
+    }
+
+    /// terrain_mut is the flattened terrain
+    pub fn terrain_mut(&mut self) -> &mut [Terrain] {
+        &mut self.terrain
+    }
+}
+
+/// RawWorldState is a deserialized WorldState whose terrain has not been checked against its dimensions
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct RawWorldState {
+    dimensions: (usize, usize, usize),
+    terrain: Vec<Terrain>,
+    attrs: Vec<String>
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<RawWorldState> for WorldState {
+    type Error = String;
+
+    fn try_from(raw: RawWorldState) -> Result<WorldState, String> {
+        let (len_x, len_y, len_z) = raw.dimensions;
+        let length = len_x.checked_mul(len_y).and_then(|l| l.checked_mul(len_z));
+        if length != Some(raw.terrain.len()) {
+            return Err(format!("world of dimensions {:?} has {} terrain", raw.dimensions, raw.terrain.len()));
+        }
+        Ok(WorldState { dimensions: raw.dimensions, terrain: raw.terrain, attrs: raw.attrs })
+    }
+}
+
+/// ActorState is the collective game state of all actors in the current world
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ActorState {
+    pub actors: Vec<Actor>,
+    pub attrs: Vec<String>
+}
+
+impl ActorState {
+    pub fn new() -> ActorState {
+        ActorState::default()
+    }
+
+    pub fn with_actor(mut self, actor: Actor) -> ActorState {
+        self.actors.push(actor);
+        self
+    }
+
+    pub fn with_attr(mut self, attr: impl Into<String>) -> ActorState {
+        self.attrs.push(attr.into());
+        self
+    }
+}
+
+/// Section represents an individual section of a menu
+///
+/// # Notes
+/// selected_item is -1 while the section has no items
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Section {
+    pub title: String,
+    pub description: Option<String>,
+    pub subsections: Vec<Section>,
+    pub items: Vec<MenuItem>,
+    pub selected_item: isize,
+    pub attrs: Vec<String>
+}
+
+impl Default for Section {
+    fn default() -> Section {
+        Section::new("")
+    }
+}
+
+impl Section {
+    pub fn new(title: impl Into<String>) -> Section {
+        Section {
+            title: title.into(),
+            description: None,
+            subsections: Vec::new(),
+            items: Vec::new(),
+            selected_item: -1,
+            attrs: Vec::new()
+        }
+    }
+
+    pub fn with_description(self, description: impl Into<String>) -> Section {
+        Section { description: Some(description.into()), ..self }
+    }
+
+    /// with_item adds item, selecting it if it is the first item
+    pub fn with_item(mut self, item: MenuItem) -> Section {
+        self.items.push(item);
+        self.selected_item = selection(self.selected_item, self.items.len());
+        self
+    }
+
+    /// with_text adds a plaintext item
+    pub fn with_text(self, text: impl Into<String>) -> Section {
+        self.with_item(MenuItem::Text(text.into()))
+    }
+
+    /// with_subsection adds subsection along with an item referring to it
+    pub fn with_subsection(mut self, subsection: Section) -> Section {
+        self.subsections.push(subsection);
+        let index = self.subsections.len() - 1;
+        self.with_item(MenuItem::Section(index))
+    }
+
+    pub fn with_selected_item(self, selected_item: isize) -> Section {
+        Section { selected_item, ..self }
+    }
+
+    pub fn with_attr(mut self, attr: impl Into<String>) -> Section {
+        self.attrs.push(attr.into());
+        self
+    }
+}
+
+/// MenuState is the current state of the game menu
+///
+/// # Notes
+/// selected_section is -1 while the menu has no sections
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MenuState {
+    pub kind: String,
+    pub sections: Vec<Section>,
+    pub selected_section: isize,
+    pub attrs: Vec<String>
+}
+
+impl Default for MenuState {
+    fn default() -> MenuState {
+        MenuState::new("invisible")
+    }
+}
+
+impl MenuState {
+    pub fn new(kind: impl Into<String>) -> MenuState {
+        MenuState { kind: kind.into(), sections: Vec::new(), selected_section: -1, attrs: Vec::new() }
+    }
+
+    /// with_section adds a top level section, selecting it if it is the first section
+    pub fn with_section(mut self, section: Section) -> MenuState {
+        self.sections.push(section);
+        self.selected_section = selection(self.selected_section, self.sections.len());
+        self
+    }
+
+    pub fn with_selected_section(self, selected_section: isize) -> MenuState {
+        MenuState { selected_section, ..self }
+    }
+
+    pub fn with_attr(mut self, attr: impl Into<String>) -> MenuState {
+        self.attrs.push(attr.into());
+        self
+    }
+}
+
+/// State is the aggregated state of the current world
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct State {
+    pub render_context: RenderContext,
+    pub world_state: WorldState,
+    pub actor_state: ActorState,
+    pub menu_state: MenuState,
+    pub attrs: Vec<String>
+}
+
+impl State {
+    pub fn new(render_context: RenderContext) -> State {
+        State { render_context, ..State::default() }
+    }
+
+    pub fn with_world(self, world_state: WorldState) -> State {
+        State { world_state, ..self }
+    }
+
+    pub fn with_actors(self, actor_state: ActorState) -> State {
+        State { actor_state, ..self }
+    }
+
+    pub fn with_menu(self, menu_state: MenuState) -> State {
+        State { menu_state, ..self }
+    }
+
+    pub fn with_attr(mut self, attr: impl Into<String>) -> State {
+        self.attrs.push(attr.into());
+        self
+    }
+
+    /// from_view copies a checked state into an owned one
+    ///
+    /// # Notes
+    /// Fails if a menu item can not be decoded, strings that are not utf-8 are copied lossily
+    pub fn from_view(view: &StateView) -> Result<State, MenuError> {
+        let world = view.world();
+        let (len_x, len_y, len_z) = world.dimensions();
+        let menu = view.menu();
+        Ok(State {
+            render_context: view.render_context(),
+            world_state: WorldState {
+                dimensions: (len_x, len_y, len_z),
+                terrain: world.terrain()
+                    .map(|t| Terrain { draw: drawable(&t.draw()), attrs: strings(t.attrs()) })
+                    .collect(),
+                attrs: strings(world.attrs())
+            },
+            actor_state: ActorState {
+                actors: view.actors()
+                    .map(|a| Actor {
+                        name: string(a.name()),
+                        description: a.description().map(string),
+                        draw: drawable(&a.draw()),
+                        attrs: strings(a.attrs())
+                    })
+                    .collect(),
+                attrs: strings(view.actor_attrs())
+            },
+            menu_state: MenuState {
+                kind: string(menu.kind()),
+                sections: menu.sections().map(|s| section(&s)).collect::<Result<_, _>>()?,
+                selected_section: menu.selected_section(),
+                attrs: strings(menu.attrs())
+            },
+            attrs: strings(view.attrs())
+        })
+    }
+
+    /// lower builds the #[repr(C)] form of this state
+    ///
+    /// # Notes
+    /// Fails if any string contains a nul byte
+    pub fn lower(&self) -> Result<LoweredState<'_>, NulError> {
+        let mut arena = Arena::default();
+        let state = arena.state(self)?;
+        Ok(LoweredState { state, _arena: arena, _owner: PhantomData })
+    }
+}
+
+fn string(string: &CStr) -> String {
+    string.to_string_lossy().into_owned()
+}
+
+fn strings(strings: Strings) -> Vec<String> {
+    strings.map(string).collect()
+}
+
+fn drawable(view: &DrawableView) -> Drawable {
+    Drawable { kind: string(view.kind()), position: view.position(), span: view.span(), attrs: strings(view.attrs()) }
+}
+
+fn section(view: &SectionView) -> Result<Section, MenuError> {
+    Ok(Section {
+        title: string(view.title()),
+        description: view.description().map(string),
+        subsections: view.subsections().map(|s| section(&s)).collect::<Result<_, _>>()?,
+        items: view.menu_items().collect::<Result<_, _>>()?,
+        selected_item: view.selected_item(),
+        attrs: strings(view.attrs())
+    })
+}
+
+/// LoweredState is a state::State along with every allocation its pointers refer to
+///
+/// The pointers stay valid for as long as the LoweredState is alive,
+/// the owned State it was lowered from can not be modified in that time.
+pub struct LoweredState<'a> {
+    state: state::State,
+    _arena: Arena,
+    _owner: PhantomData<&'a State>
+}
+
+impl LoweredState<'_> {
+    pub fn state(&self) -> &state::State {
+        &self.state
+    }
+
+    pub fn state_mut(&mut self) -> &mut state::State {
+        &mut self.state
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut state::State {
+        &mut self.state
+    }
+}
+
+/// Arena owns the buffers of a LoweredState
+///
+/// Only the heap buffers are pointed to, so moving the arena (or pushing to it) does not invalidate anything
+#[derive(Default)]
+pub(crate) struct Arena {
+    strings: Vec<CString>,
+    buffers: Vec<Box<dyn Any>>
+}
+
+/// array is a pointer and length pair for items, null if items is empty
+fn array<T>(items: &mut [T]) -> (c_array<T>, isize) {
+    if items.is_empty() {
+        (null_mut(), 0)
+    } else {
+        (items.as_mut_ptr(), items.len() as isize)
+    }
+}
+
+impl Arena {
+    pub(crate) fn string(&mut self, string: &str) -> Result<*mut i8, NulError> {
+        self.strings.push(CString::new(string)?);
+        Ok(self.strings.last().unwrap().as_ptr() as *mut i8)
+    }
+
+    pub(crate) fn optional_string(&mut self, string: Option<&String>) -> Result<*mut i8, NulError> {
+        string.map_or(Ok(null_mut()), |s| self.string(s))
+    }
+
+    pub(crate) fn strings(&mut self, strings: &[String]) -> Result<(c_array<Attribute>, isize), NulError> {
+        let pointers = strings.iter().map(|s| self.string(s)).collect::<Result<Vec<_>, _>>()?;
+        Ok(self.keep(pointers))
+    }
+
+    pub(crate) fn drawable(&mut self, drawable: &Drawable) -> Result<state::Drawable, NulError> {
+        let (attrs, attrs_length) = self.strings(&drawable.attrs)?;
+        Ok(state::Drawable {
+            kind: self.string(&drawable.kind)?,
+            pos_x: drawable.position.0,
+            pos_y: drawable.position.1,
+            pos_z: drawable.position.2,
+            span_x: drawable.span.0,
+            span_y: drawable.span.1,
+            span_z: drawable.span.2,
+            attrs,
+            attrs_length
+        })
+    }
+
+    pub(crate) fn section(&mut self, section: &Section) -> Result<state::Section, NulError> {
+        let subsections = section.subsections.iter().map(|s| self.section(s)).collect::<Result<Vec<_>, _>>()?;
+        let (subsections, subsections_length) = self.keep(subsections);
+        let items = section.items.iter().map(MenuItem::to_string).collect::<Vec<_>>();
+        let (items, items_length) = self.strings(&items)?;
+        let (attrs, attrs_length) = self.strings(&section.attrs)?;
+        Ok(state::Section {
+            title: self.string(&section.title)?,
+            description: self.optional_string(section.description.as_ref())?,
+            subsections,
+            subsections_length,
+            items,
+            items_length,
+            selected_item: section.selected_item,
+            attrs,
+            attrs_length
+        })
+    }
+
+    pub(crate) fn terrain(&mut self, terrain: &Terrain) -> Result<state::Terrain, NulError> {
+        let (attrs, attrs_length) = self.strings(&terrain.attrs)?;
+        Ok(state::Terrain { draw: self.drawable(&terrain.draw)?, attrs, attrs_length })
+    }
+
+    pub(crate) fn actor(&mut self, actor: &Actor) -> Result<state::Actor, NulError> {
+        let (attrs, attrs_length) = self.strings(&actor.attrs)?;
+        Ok(state::Actor {
+            name: self.string(&actor.name)?,
+            description: self.optional_string(actor.description.as_ref())?,
+            draw: self.drawable(&actor.draw)?,
+            attrs,
+            attrs_length
+        })
+    }
+
+    /// keep moves items into the arena, returning them as a c_array
+    pub(crate) fn keep<T: 'static>(&mut self, items: Vec<T>) -> (c_array<T>, isize) {
+        self.buffers.push(Box::new(items));
+        array(self.buffers.last_mut().unwrap().downcast_mut::<Vec<T>>().unwrap())
+    }
+
+    pub(crate) fn state(&mut self, state: &State) -> Result<state::State, NulError> {
+        let world = &state.world_state;
+        let terrain = world.terrain.iter().map(|t| self.terrain(t)).collect::<Result<Vec<_>, _>>()?;
+        let (terrain, _) = self.keep(terrain);
+        let (world_attrs, world_attrs_length) = self.strings(&world.attrs)?;
+
+        let actors = state.actor_state.actors.iter().map(|a| self.actor(a)).collect::<Result<Vec<_>, _>>()?;
+        let (actors, actors_length) = self.keep(actors);
+        let (actor_attrs, actor_attrs_length) = self.strings(&state.actor_state.attrs)?;
+
+        let menu = &state.menu_state;
+        let sections = menu.sections.iter().map(|s| self.section(s)).collect::<Result<Vec<_>, _>>()?;
+        let (sections, sections_length) = self.keep(sections);
+        let (menu_attrs, menu_attrs_length) = self.strings(&menu.attrs)?;
+        let (attrs, attrs_length) = self.strings(&state.attrs)?;
+
+        Ok(state::State {
+            render_context: state.render_context,
+            world_state: state::WorldState {
+                terrain,
+                terrain_len_x: world.dimensions.0 as i64,
+                terrain_len_y: world.dimensions.1 as i64,
+                terrain_len_z: world.dimensions.2 as i64,
+                attrs: world_attrs,
+                attrs_length: world_attrs_length
+            },
+            actor_state: state::ActorState {
+                actors,
+                actors_length,
+                attrs: actor_attrs,
+                attrs_length: actor_attrs_length
+            },
+            menu_state: state::MenuState {
+                kind: self.string(&menu.kind)?,
+                sections,
+                sections_length,
+                selected_section: menu.selected_section,
+                attrs: menu_attrs,
+                attrs_length: menu_attrs_length
+            },
+            attrs,
+            attrs_length
+        })
+    }
+}
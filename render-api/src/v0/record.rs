@@ -0,0 +1,212 @@
+//! record logs what a client sends to a render server, so a session can be replayed later
+//!
+//! A session file is a sequence of entries, each a pushed state or a user event along with the time
+//! since recording started. Files ending in .json hold one JSON entry per line, any other file holds
+//! entries in the binary format of serial, each prefixed with its length as a little endian u32.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use super::owned;
+use super::serial::{read_framed, write_framed, Format, SerialError};
+use super::UserEvent;
+
+/// Event is something that happened during a session
+///
+/// # Variants
+/// * State - the client pushed a state
+/// * User - the server reported a user event
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub enum Event {
+    State(Box<owned::State>),
+    User { event: UserEvent, text: String }
+}
+
+/// EventRef is Event by reference, it serializes exactly like Event
+#[derive(Serialize)]
+#[serde(rename = "Event")]
+enum EventRef<'a> {
+    State(&'a owned::State),
+    User { event: UserEvent, text: &'a str }
+}
+
+/// Entry is an Event and when it happened, relative to the start of the session
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Entry {
+    pub at: Duration,
+    pub event: Event
+}
+
+#[derive(Serialize)]
+#[serde(rename = "Entry")]
+struct EntryRef<'a> {
+    at: Duration,
+    event: EventRef<'a>
+}
+
+/// Writer is the open session file of a Recorder
+struct Writer {
+    out: BufWriter<File>,
+    format: Format,
+    start: Instant,
+    // NOTE: recording happens inside callbacks that cannot report errors, the first one is kept for finish
+    error: Option<SerialError>
+}
+
+impl Writer {
+    fn write(&mut self, event: EventRef) -> Result<(), SerialError> {
+        let entry = EntryRef { at: self.start.elapsed(), event };
+        match self.format {
+            Format::Json => {
+                serde_json::to_writer(&mut self.out, &entry).map_err(SerialError::Json)?;
+                self.out.write_all(b"\n")?;
+            }
+            Format::Binary => write_framed(&mut self.out, &entry)?
+        }
+        Ok(())
+    }
+}
+
+/// Recorder appends entries to a session file, clones share the same file
+#[derive(Clone)]
+pub struct Recorder(Arc<Mutex<Writer>>);
+
+impl Recorder {
+    /// create creates (or truncates) the session file at path, the session starts now
+    pub fn create(path: impl AsRef<Path>) -> Result<Recorder, SerialError> {
+        let writer = Writer {
+            out: BufWriter::new(File::create(&path)?),
+            format: Format::of(&path),
+            start: Instant::now(),
+            error: None
+        };
+        Ok(Recorder(Arc::new(Mutex::new(writer))))
+    }
+
+    fn record(&self, event: EventRef) -> Result<(), SerialError> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).write(event)
+    }
+
+    #[cfg(feature = "client")]
+    /// keep records event, keeping the first error for finish instead of returning it
+    fn keep(&self, event: EventRef) {
+        let mut writer = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = writer.write(event) {
+            writer.error.get_or_insert(e);
+        }
+    }
+
+    pub fn record_state(&self, state: &owned::State) -> Result<(), SerialError> {
+        self.record(EventRef::State(state))
+    }
+
+    pub fn record_user(&self, event: UserEvent, text: &str) -> Result<(), SerialError> {
+        self.record(EventRef::User { event, text })
+    }
+
+    #[cfg(feature = "client")]
+    /// keep_state is record_state for callers that cannot handle errors, see finish
+    pub(crate) fn keep_state(&self, state: &owned::State) {
+        self.keep(EventRef::State(state))
+    }
+
+    #[cfg(feature = "client")]
+    /// keep_user is record_user for callers that cannot handle errors, see finish
+    pub(crate) fn keep_user(&self, event: UserEvent, text: &str) {
+        self.keep(EventRef::User { event, text })
+    }
+
+    /// finish flushes the session file
+    ///
+    /// # Notes
+    /// Returns the first error that occurred while recording on behalf of a RenderServer, if any
+    pub fn finish(&self) -> Result<(), SerialError> {
+        let mut writer = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        match writer.error.take() {
+            Some(e) => Err(e),
+            None => Ok(writer.out.flush()?)
+        }
+    }
+}
+
+/// Session reads the entries of a session file in order
+pub struct Session {
+    input: BufReader<File>,
+    format: Format
+}
+
+impl Session {
+    pub fn open(path: impl AsRef<Path>) -> Result<Session, SerialError> {
+        Ok(Session { input: BufReader::new(File::open(&path)?), format: Format::of(&path) })
+    }
+
+    fn read(&mut self) -> Result<Option<Entry>, SerialError> {
+        match self.format {
+            Format::Json => {
+                let mut line = String::new();
+                loop {
+                    line.clear();
+                    if self.input.read_line(&mut line)? == 0 {
+                        return Ok(None);
+                    }
+                    if !line.trim().is_empty() {
+                        return serde_json::from_str(&line).map(Some).map_err(SerialError::Json);
+                    }
+                }
+            }
+            Format::Binary => read_framed(&mut self.input)
+        }
+    }
+}
+
+impl Iterator for Session {
+    type Item = Result<Entry, SerialError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read().transpose()
+    }
+}
+
+#[cfg(feature = "client")]
+pub(crate) use intercept::Intercept;
+
+#[cfg(feature = "client")]
+mod intercept {
+    use std::ffi::c_void;
+    use crate::c_str;
+    use super::super::handle::UserCallback;
+    use super::super::UserEvent;
+    use super::Recorder;
+
+    /// Intercept records user events before forwarding them to the client's callback
+    ///
+    /// # Notes
+    /// The server is handed the address of the Intercept as user data, it must not move while registered
+    pub(crate) struct Intercept {
+        recorder: Recorder,
+        callback: UserCallback,
+        user_data: *mut c_void
+    }
+
+    impl Intercept {
+        /// new intercepts the user events sent to callback, recording them to recorder
+        pub(crate) fn new(recorder: Recorder, callback: UserCallback, user_data: *mut c_void) -> Box<Intercept> {
+            Box::new(Intercept { recorder, callback, user_data })
+        }
+
+        /// callback is the callback and user data to register with the server in place of the client's
+        pub(crate) fn callback(&self) -> (UserCallback, *mut c_void) {
+            (record_user_event, self as *const Intercept as *mut c_void)
+        }
+    }
+
+    unsafe extern "C" fn record_user_event(event: UserEvent, text: *mut i8, user_data: *mut c_void) {
+        let intercept = &*(user_data as *const Intercept);
+        let message = c_str(text).map(|t| t.to_string_lossy().into_owned()).unwrap_or_default();
+        intercept.recorder.keep_user(event, &message);
+        (intercept.callback)(event, text, intercept.user_data);
+    }
+}
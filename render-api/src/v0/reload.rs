@@ -0,0 +1,85 @@
+//! reload watches the library a render server was loaded from, and reloads the server when it is rebuilt
+//!
+//! HotReload wraps a RenderServer and is polled by the client, typically once per frame.
+//! A change is acted on through RenderServer::reload, which disconnects the server,
+//! loads the new library and reconnects with the preserved render state.
+
+use std::ops::{Deref, DerefMut};
+use std::time::SystemTime;
+use super::handle::{RenderError, RenderServer};
+
+/// Stamp identifies a version of the server library on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    modified: SystemTime,
+    length: u64
+}
+
+/// HotReload watches the library a RenderServer was loaded from and reloads the server when it changes
+///
+/// HotReload dereferences to the RenderServer it wraps, so it can be used in its place.
+/// See RenderServer::reload for how the render state is carried across a reload.
+///
+/// # Notes
+/// * Changes are detected by polling the modification time and size of the library,
+///   a change is only acted on once two consecutive polls agree, so half written libraries are skipped
+/// * A reload that fails leaves the previous server running, the next change to the library is retried
+pub struct HotReload {
+    server: RenderServer,
+    loaded: Option<Stamp>,
+    pending: Option<Stamp>
+}
+
+impl HotReload {
+    /// new starts watching the library server was loaded from
+    pub fn new(server: RenderServer) -> HotReload {
+        let loaded = stamp(&server);
+        HotReload { server, loaded, pending: None }
+    }
+
+    /// poll checks the library for changes and reloads the server if it changed
+    ///
+    /// Returns true if the server was reloaded
+    pub fn poll(&mut self) -> Result<bool, RenderError> {
+        let current = match stamp(&self.server) {
+            Some(current) => current,
+            // NOTE: the library is missing while it is being rebuilt, wait for it to come back
+            None => return Ok(false)
+        };
+        if Some(current) == self.loaded {
+            self.pending = None;
+            return Ok(false);
+        }
+        if self.pending != Some(current) {
+            self.pending = Some(current);
+            return Ok(false);
+        }
+        self.pending = None;
+        self.loaded = Some(current);
+        self.server.reload().map(|_| true)
+    }
+
+    /// into_inner stops watching and returns the server
+    pub fn into_inner(self) -> RenderServer {
+        self.server
+    }
+}
+
+impl Deref for HotReload {
+    type Target = RenderServer;
+
+    fn deref(&self) -> &RenderServer {
+        &self.server
+    }
+}
+
+impl DerefMut for HotReload {
+    fn deref_mut(&mut self) -> &mut RenderServer {
+        &mut self.server
+    }
+}
+
+fn stamp(server: &RenderServer) -> Option<Stamp> {
+    let metadata = std::fs::metadata(server.path()?).ok()?;
+    Some(Stamp { modified: metadata.modified().ok()?, length: metadata.len() })
+}
@@ -0,0 +1,465 @@
+//! remote runs a render server in a child process, so a crashing server cannot take the client down
+//!
+//! RemoteBackend spawns a host process (any executable calling serve, such as the core's render-host)
+//! and talks to it over a Unix domain socket. The host loads the server library and forwards every request,
+//! frame and user events are sent back and handed to the callbacks registered with the RemoteBackend.
+//! Every message is in the binary format of serial, prefixed with its length.
+//!
+//! # Crashes
+//! When the host exits, or does not reply within REPLY_TIMEOUT, it is stopped and every request
+//! fails with ErrorCode::DeviceLost until the client reconnects.
+//! Reconnecting starts a new host and asks for expr_init to be rerun, so RenderServer::recover
+//! (or reconnect followed by reinit) restores the server. Registered callbacks are registered again by reinit.
+//!
+//! # Notes
+//! * Extensions are not forwarded, the host negotiates none with the server
+//! * Callbacks are called on a thread owned by the RemoteBackend
+
+use std::ffi::{c_void, CString};
+use std::io::{BufReader, ErrorKind};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use crate::error::{ErrorCode, ExprError};
+use crate::negotiate::ClientMetadata;
+use crate::{c_str, IncomingMetadata, InitResult, RenderState};
+use super::backend::RenderBackend;
+use super::handle::{FrameCallback, RenderError, RenderServer, UserCallback};
+use super::owned;
+use super::serial::{read_framed, write_framed, SerialError};
+use super::state::{State, StateView};
+use super::{RenderEvent, RenderResult, UserEvent};
+
+/// CONNECT_TIMEOUT is how long a host is given to connect after it is spawned
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// REPLY_TIMEOUT is how long a host is given to reply to a request before it is considered hung
+pub const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Request is sent by the client, the host answers every request with a Reply
+#[derive(Debug, Serialize, Deserialize)]
+enum Request {
+    Init { client_version: String, supported_versions: Vec<String> },
+    Push(Box<owned::State>),
+    FrameCallback,
+    UserCallback,
+    Disconnect,
+    Reconnect
+}
+
+/// Reply is the outcome of a Request
+#[derive(Debug, Serialize, Deserialize)]
+enum Reply {
+    Ok,
+    Error { code: ErrorCode, recoverable: bool, message: Option<String> },
+    Initialized { version: String, error: Option<String> }
+}
+
+/// Message is sent by the host
+#[derive(Debug, Serialize, Deserialize)]
+enum Message {
+    Reply(Reply),
+    Frame { event: RenderEvent, critical: bool, frame: i32, message: Option<String>, state: Option<Box<owned::State>> },
+    User { event: UserEvent, text: String }
+}
+
+/// Callbacks are the callbacks registered with a RemoteBackend and their user data
+#[derive(Default)]
+struct Callbacks {
+    frame: Option<(FrameCallback, *mut c_void)>,
+    user: Option<(UserCallback, *mut c_void)>
+}
+
+// NOTE: user data is only handed back to the callback it was registered with, it is never dereferenced here
+unsafe impl Send for Callbacks {}
+
+/// Connection is a running host
+struct Connection {
+    child: Child,
+    stream: UnixStream,
+    replies: Receiver<Reply>
+}
+
+impl Connection {
+    /// close waits for the host to exit, killing it if it does not exit on its own
+    fn close(mut self) -> String {
+        let _ = self.stream.shutdown(std::net::Shutdown::Both);
+        let deadline = Instant::now() + Duration::from_millis(500);
+        while Instant::now() < deadline {
+            if let Ok(Some(status)) = self.child.try_wait() {
+                return status.to_string();
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        self.child.wait().map_or_else(|e| e.to_string(), |status| status.to_string())
+    }
+}
+
+/// Strings are the strings handed to the client, each stays valid until it is replaced
+#[derive(Default)]
+struct Strings {
+    version: CString,
+    init_error: Option<CString>,
+    error: Option<CString>
+}
+
+/// RemoteBackend is a render server running in a host process
+///
+/// # Notes
+/// Dropping the backend closes the connection, which stops the host
+pub struct RemoteBackend {
+    host: PathBuf,
+    library: PathBuf,
+    connection: Mutex<Option<Connection>>,
+    callbacks: Arc<Mutex<Callbacks>>,
+    strings: Mutex<Strings>
+}
+
+impl RemoteBackend {
+    /// spawn starts host to serve the server library at library
+    ///
+    /// host is run with the library and the path of the socket to connect to as its arguments
+    pub fn spawn(host: impl AsRef<Path>, library: impl AsRef<Path>) -> Result<RemoteBackend, RenderError> {
+        let backend = RemoteBackend {
+            host: host.as_ref().to_path_buf(),
+            library: library.as_ref().to_path_buf(),
+            connection: Mutex::new(None),
+            callbacks: Arc::new(Mutex::new(Callbacks::default())),
+            strings: Mutex::new(Strings::default())
+        };
+        *backend.connection() = Some(backend.start().map_err(RenderError::Load)?);
+        Ok(backend)
+    }
+
+    /// process_id is the id of the host process, None if it has exited
+    pub fn process_id(&self) -> Option<u32> {
+        self.connection().as_ref().map(|c| c.child.id())
+    }
+
+    fn connection(&self) -> MutexGuard<'_, Option<Connection>> {
+        self.connection.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn callbacks(&self) -> MutexGuard<'_, Callbacks> {
+        self.callbacks.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// start spawns a host and waits for it to connect
+    fn start(&self) -> Result<Connection, String> {
+        static SOCKETS: AtomicUsize = AtomicUsize::new(0);
+        let socket = std::env::temp_dir()
+            .join(format!("exploritron-render-{}-{}.sock", std::process::id(), SOCKETS.fetch_add(1, Ordering::Relaxed)));
+        let listener = UnixListener::bind(&socket).map_err(|e| format!("{}: {}", socket.display(), e))?;
+        let result = self.accept(&listener, &socket);
+        let _ = std::fs::remove_file(&socket);
+        let (child, stream) = result?;
+
+        let (replies_sender, replies) = mpsc::channel();
+        let input = stream.try_clone().map_err(|e| e.to_string())?;
+        let callbacks = self.callbacks.clone();
+        std::thread::spawn(move || receive(input, replies_sender, callbacks));
+        Ok(Connection { child, stream, replies })
+    }
+
+    fn accept(&self, listener: &UnixListener, socket: &Path) -> Result<(Child, UnixStream), String> {
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        let mut child = Command::new(&self.host).arg(&self.library).arg(socket).spawn()
+            .map_err(|e| format!("{}: {}", self.host.display(), e))?;
+        let deadline = Instant::now() + CONNECT_TIMEOUT;
+        let error = loop {
+            match listener.accept() {
+                Ok((stream, _)) => match stream.set_nonblocking(false) {
+                    Ok(()) => return Ok((child, stream)),
+                    Err(e) => break e.to_string()
+                },
+                Err(e) if e.kind() == ErrorKind::WouldBlock => match child.try_wait() {
+                    Ok(Some(status)) => return Err(format!("render host exited before connecting ({})", status)),
+                    Ok(None) if Instant::now() > deadline => break "render host did not connect in time".to_string(),
+                    Ok(None) => std::thread::sleep(Duration::from_millis(10)),
+                    Err(e) => break e.to_string()
+                },
+                Err(e) => break e.to_string()
+            }
+        };
+        let _ = child.kill();
+        let _ = child.wait();
+        Err(error)
+    }
+
+    /// request sends request to the host and waits for its reply
+    ///
+    /// If the host cannot be reached or does not reply within REPLY_TIMEOUT it is stopped,
+    /// and requests fail until the client reconnects
+    fn request(&self, request: Request) -> Result<Reply, String> {
+        let mut connection = self.connection();
+        let live = match connection.as_mut() {
+            Some(live) => live,
+            None => return Err("render host is not running, reconnect to restart it".to_string())
+        };
+        let reply = match write_framed(&mut live.stream, &request) {
+            Ok(()) => live.replies.recv_timeout(REPLY_TIMEOUT).map_err(|e| match e {
+                RecvTimeoutError::Timeout => format!("did not reply within {:?}", REPLY_TIMEOUT),
+                RecvTimeoutError::Disconnected => "exited".to_string()
+            }),
+            Err(_) => Err("exited".to_string())
+        };
+        reply.map_err(|what| {
+            let status = connection.take().map(Connection::close).unwrap_or_default();
+            format!("render host {} ({})", what, status)
+        })
+    }
+
+    /// error keeps message so it can be handed to the client along with code
+    fn error(&self, code: ErrorCode, recoverable: bool, message: Option<String>) -> ExprError {
+        let mut strings = self.strings.lock().unwrap_or_else(|e| e.into_inner());
+        strings.error = message.map(|m| CString::new(m.replace('\0', " ")).unwrap_or_default());
+        let message = strings.error.as_ref().map_or(null_mut(), |m| m.as_ptr() as *mut i8);
+        match recoverable {
+            true => ExprError::new(code, message),
+            false => ExprError::fatal(code, message)
+        }
+    }
+
+    /// reply converts the reply to a request into the return value of an entry point
+    ///
+    /// # Notes
+    /// A host that cannot be reached is reported as ErrorCode::DeviceLost, the client has to reconnect
+    fn reply(&self, reply: Result<Reply, String>) -> ExprError {
+        match reply {
+            Ok(Reply::Ok) => ExprError::none(),
+            Ok(Reply::Error { code, recoverable, message }) => self.error(code, recoverable, message),
+            Ok(reply) => self.error(ErrorCode::Unknown, true, Some(format!("unexpected reply {:?}", reply))),
+            Err(message) => self.error(ErrorCode::DeviceLost, false, Some(message))
+        }
+    }
+
+    /// render_state is the render state handed to the client, the host keeps the real one
+    fn render_state(&self) -> *mut RenderState {
+        std::ptr::NonNull::dangling().as_ptr()
+    }
+}
+
+impl RenderBackend for RemoteBackend {
+    unsafe fn init(&self, metadata: IncomingMetadata) -> InitResult {
+        if self.connection().is_none() {
+            match self.start() {
+                Ok(connection) => *self.connection() = Some(connection),
+                Err(e) => return InitResult::failed(self.error(ErrorCode::NotLoaded, false, Some(e)))
+            }
+        }
+        let strings = |list: Vec<&std::ffi::CStr>| list.iter().map(|s| s.to_string_lossy().into_owned()).collect();
+        let request = Request::Init {
+            client_version: metadata.client_version().map(|v| v.to_string_lossy().into_owned()).unwrap_or_default(),
+            supported_versions: strings(metadata.supported_versions())
+        };
+        match self.request(request) {
+            Ok(Reply::Initialized { version, error }) => {
+                let mut strings = self.strings.lock().unwrap_or_else(|e| e.into_inner());
+                strings.version = CString::new(version).unwrap_or_default();
+                strings.init_error = error.and_then(|e| CString::new(e).ok());
+                let result = InitResult::new(strings.version.as_ptr() as *mut i8, self.render_state());
+                match strings.init_error.as_ref() {
+                    Some(e) => result.with_error(ExprError::new(ErrorCode::Unknown, e.as_ptr() as *mut i8)),
+                    None => result
+                }
+            }
+            reply => {
+                let error = self.reply(reply);
+                InitResult::failed(ExprError::fatal(error.code(), error.message_ptr()))
+            }
+        }
+    }
+
+    unsafe fn push_state(&self, _render_state: *mut RenderState, game_state: *mut State) -> ExprError {
+        let state = StateView::from_ptr(game_state)
+            .map_err(|e| e.to_string())
+            .and_then(|view| owned::State::from_view(&view).map_err(|e| e.to_string()));
+        match state {
+            Ok(state) => self.reply(self.request(Request::Push(Box::new(state)))),
+            Err(e) => self.error(ErrorCode::InvalidState, true, Some(format!("invalid game state: {}", e)))
+        }
+    }
+
+    unsafe fn frame_callback(&self, _render_state: *mut RenderState, callback: FrameCallback, user_data: *mut c_void) -> ExprError {
+        self.callbacks().frame = Some((callback, user_data));
+        self.reply(self.request(Request::FrameCallback))
+    }
+
+    unsafe fn user_callback(&self, _render_state: *mut RenderState, callback: UserCallback, user_data: *mut c_void) -> ExprError {
+        self.callbacks().user = Some((callback, user_data));
+        self.reply(self.request(Request::UserCallback))
+    }
+
+    /// disconnect does nothing if the host is not running
+    unsafe fn disconnect(&self, _render_state: *mut RenderState) -> Option<ExprError> {
+        if self.connection().is_none() {
+            return Some(ExprError::none());
+        }
+        Some(self.reply(self.request(Request::Disconnect)))
+    }
+
+    /// reconnect starts a new host if the previous one exited, and then asks for expr_init to be rerun
+    unsafe fn reconnect(&self, _render_state: *mut RenderState) -> Option<ExprError> {
+        if self.connection().is_some() {
+            return Some(self.reply(self.request(Request::Reconnect)));
+        }
+        match self.start() {
+            Ok(connection) => {
+                *self.connection() = Some(connection);
+                Some(ExprError::new(ErrorCode::NeedsReinit, null_mut()))
+            }
+            Err(e) => Some(self.error(ErrorCode::NotLoaded, false, Some(e)))
+        }
+    }
+
+    fn symbol(&self, _symbol: &str) -> Option<*const c_void> {
+        None
+    }
+
+    fn path(&self) -> Option<&Path> {
+        Some(&self.library)
+    }
+
+    /// reload starts a new host, the library is loaded again from scratch
+    fn reload(&self) -> Result<Box<dyn RenderBackend>, RenderError> {
+        Ok(Box::new(RemoteBackend::spawn(&self.host, &self.library)?))
+    }
+}
+
+impl Drop for RemoteBackend {
+    fn drop(&mut self) {
+        if let Some(connection) = self.connection().take() {
+            connection.close();
+        }
+    }
+}
+
+/// receive reads messages from the host until it disconnects, replies are sent to replies
+///
+/// # Notes
+/// Callbacks are called with callbacks locked, so a callback is never called once it has been replaced
+fn receive(input: UnixStream, replies: Sender<Reply>, callbacks: Arc<Mutex<Callbacks>>) {
+    let mut input = BufReader::new(input);
+    while let Ok(Some(message)) = read_framed::<Message>(&mut input) {
+        match message {
+            Message::Reply(reply) => {
+                if replies.send(reply).is_err() {
+                    return;
+                }
+            }
+            Message::Frame { event, critical, frame, message, state } => {
+                let message = message.and_then(|m| CString::new(m).ok());
+                let message = message.as_ref().map_or(null_mut(), |m| m.as_ptr() as *mut i8);
+                let mut lowered = state.as_ref().and_then(|s| s.lower().ok());
+                let game_state = lowered.as_mut().map_or(null_mut(), |l| l.as_mut_ptr());
+                let callbacks = callbacks.lock().unwrap_or_else(|e| e.into_inner());
+                if let Some((callback, user_data)) = callbacks.frame {
+                    unsafe { callback(RenderResult::new(event, critical, message), frame, game_state, message, user_data) };
+                }
+            }
+            Message::User { event, text } => {
+                let text = CString::new(text).unwrap_or_default();
+                let callbacks = callbacks.lock().unwrap_or_else(|e| e.into_inner());
+                if let Some((callback, user_data)) = callbacks.user {
+                    unsafe { callback(event, text.as_ptr() as *mut i8, user_data) };
+                }
+            }
+        }
+    }
+}
+
+/// Outgoing is the connection of serve to the client, handed to the server's callbacks as user data
+type Outgoing = Mutex<UnixStream>;
+
+fn send(outgoing: &Outgoing, message: &Message) -> Result<(), SerialError> {
+    write_framed(&mut *outgoing.lock().unwrap_or_else(|e| e.into_inner()), message)
+}
+
+/// serve runs the host side of a RemoteBackend: it connects to socket and serves the server library at library
+///
+/// Returns once the client disconnects
+///
+/// # Notes
+/// States are validated by the client before they are sent, the host does not validate them again
+pub fn serve(library: impl AsRef<Path>, socket: impl AsRef<Path>) -> Result<(), SerialError> {
+    let stream = UnixStream::connect(socket)?;
+    let outgoing: Outgoing = Mutex::new(stream.try_clone()?);
+    let mut input = BufReader::new(stream);
+    // NOTE: declared after outgoing so the server is dropped before the connection its callbacks write to
+    let mut server = None;
+    while let Some(request) = read_framed::<Request>(&mut input)? {
+        let reply = handle(library.as_ref(), &outgoing, &mut server, request);
+        send(&outgoing, &Message::Reply(reply))?;
+    }
+    Ok(())
+}
+
+fn handle(library: &Path, outgoing: &Outgoing, server: &mut Option<RenderServer>, request: Request) -> Reply {
+    let (request, server) = match (request, server.as_mut()) {
+        (Request::Init { client_version, supported_versions }, _) => {
+            // NOTE: the previous server is disconnected before the library is initialized again
+            *server = None;
+            let metadata = ClientMetadata::with_versions(&client_version, &supported_versions);
+            return match RenderServer::open_with(library, metadata) {
+                Ok(opened) => {
+                    let reply = Reply::Initialized {
+                        version: opened.server_version().to_string(),
+                        error: opened.init_error().map(str::to_string)
+                    };
+                    let opened = server.insert(opened);
+                    opened.validate_states(false);
+                    reply
+                }
+                Err(e) => Reply::Error { code: e.code(), recoverable: false, message: Some(e.to_string()) }
+            };
+        }
+        (Request::Disconnect, None) => return Reply::Ok,
+        (Request::Reconnect, None) => return Reply::Error { code: ErrorCode::NeedsReinit, recoverable: true, message: None },
+        (_, None) => {
+            let message = Some("render server is not initialized".to_string());
+            return Reply::Error { code: ErrorCode::InvalidRenderState, recoverable: false, message };
+        }
+        (request, Some(server)) => (request, server)
+    };
+    let result = match request {
+        Request::Push(state) => server.push(&state),
+        // NOTE: outgoing outlives every server (see serve)
+        Request::FrameCallback => unsafe { server.frame_callback(forward_frame, outgoing as *const Outgoing as *mut c_void) },
+        Request::UserCallback => unsafe { server.user_callback(forward_user, outgoing as *const Outgoing as *mut c_void) },
+        Request::Disconnect => server.disconnect(),
+        Request::Reconnect => server.reconnect(),
+        Request::Init { .. } => unreachable!("init is handled above")
+    };
+    match result {
+        Ok(()) => Reply::Ok,
+        Err(RenderError::Server { code, recoverable, message }) => Reply::Error { code, recoverable, message },
+        Err(RenderError::InvalidState(message)) => Reply::Error { code: ErrorCode::InvalidState, recoverable: true, message: Some(message) },
+        Err(RenderError::NeedsReinit) => Reply::Error { code: ErrorCode::NeedsReinit, recoverable: true, message: None },
+        Err(e) => Reply::Error { code: e.code(), recoverable: e.recoverable(), message: Some(e.to_string()) }
+    }
+}
+
+unsafe extern "C" fn forward_frame(result: RenderResult, frame: i32, game_state: *mut State, message: *mut i8, outgoing: *mut c_void) {
+    let state = StateView::from_ptr(game_state).ok()
+        .and_then(|view| owned::State::from_view(&view).ok())
+        .map(Box::new);
+    let _ = send(&*(outgoing as *const Outgoing), &Message::Frame {
+        event: result.event(),
+        critical: result.critical(),
+        frame,
+        message: c_str(message).map(|m| m.to_string_lossy().into_owned()),
+        state
+    });
+}
+
+unsafe extern "C" fn forward_user(event: UserEvent, text: *mut i8, outgoing: *mut c_void) {
+    let text = c_str(text).map(|t| t.to_string_lossy().into_owned()).unwrap_or_default();
+    let _ = send(&*(outgoing as *const Outgoing), &Message::User { event, text });
+}
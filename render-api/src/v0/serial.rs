@@ -0,0 +1,168 @@
+//! serial converts owned states (and the other serializable types of v0) to JSON or a compact binary format
+//!
+//! JSON is meant for people: frames dumped as JSON can be read and diffed in review.
+//! The binary format is meant for volume: it is postcard prefixed with MAGIC and BINARY_VERSION,
+//! so files from an incompatible version are rejected instead of being misread.
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::{ErrorKind, Read, Write};
+use std::path::Path;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// MAGIC starts every value in the binary format
+pub const MAGIC: &[u8; 4] = b"EXPR";
+
+/// BINARY_VERSION is the version of the binary format written after MAGIC
+pub const BINARY_VERSION: u8 = 0;
+
+/// MAX_FRAME is the length of the largest value written or read with a length prefix, in bytes
+///
+/// # Notes
+/// The length prefix is read before the value, so it is bounded before anything is allocated for it
+pub const MAX_FRAME: usize = 256 << 20;
+
+/// Format is a serialization format
+///
+/// # Variants
+/// * Json - pretty printed JSON
+/// * Binary - the compact binary format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    Json,
+    Binary
+}
+
+impl Format {
+    /// of is the format of path, files ending in .json are JSON and anything else is binary
+    pub fn of(path: impl AsRef<Path>) -> Format {
+        match path.as_ref().extension() {
+            Some(extension) if extension == "json" => Format::Json,
+            _ => Format::Binary
+        }
+    }
+}
+
+/// SerialError is why a value could not be serialized or deserialized
+///
+/// # Variants
+/// * Io - the file could not be read or written
+/// * Json - the JSON is invalid or does not describe the value
+/// * Binary - the binary data is invalid or does not describe the value
+/// * BadMagic - the binary data does not start with MAGIC
+/// * UnsupportedVersion - the binary data was written by an unsupported format version
+/// * FrameTooLarge - a length prefixed value is longer than MAX_FRAME
+#[derive(Debug)]
+pub enum SerialError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Binary(postcard::Error),
+    BadMagic,
+    UnsupportedVersion(u8),
+    FrameTooLarge(usize)
+}
+
+impl Display for SerialError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SerialError::Io(e) => write!(f, "{}", e),
+            SerialError::Json(e) => write!(f, "invalid json: {}", e),
+            SerialError::Binary(e) => write!(f, "invalid binary data: {}", e),
+            SerialError::BadMagic => write!(f, "binary data does not start with {:?}", MAGIC),
+            SerialError::UnsupportedVersion(version) => write!(f, "unsupported binary format version {}", version),
+            SerialError::FrameTooLarge(length) => write!(f, "frame of {} bytes exceeds the limit of {} bytes", length, MAX_FRAME)
+        }
+    }
+}
+
+impl Error for SerialError {}
+
+impl From<std::io::Error> for SerialError {
+    fn from(e: std::io::Error) -> SerialError {
+        SerialError::Io(e)
+    }
+}
+
+pub fn to_json<T: Serialize>(value: &T) -> Result<String, SerialError> {
+    serde_json::to_string_pretty(value).map_err(SerialError::Json)
+}
+
+pub fn from_json<T: DeserializeOwned>(json: &str) -> Result<T, SerialError> {
+    serde_json::from_str(json).map_err(SerialError::Json)
+}
+
+pub fn to_binary<T: Serialize>(value: &T) -> Result<Vec<u8>, SerialError> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.push(BINARY_VERSION);
+    postcard::to_extend(value, bytes).map_err(SerialError::Binary)
+}
+
+pub fn from_binary<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, SerialError> {
+    let body = bytes.strip_prefix(MAGIC.as_slice()).ok_or(SerialError::BadMagic)?;
+    match body.split_first() {
+        Some((&BINARY_VERSION, body)) => postcard::from_bytes(body).map_err(SerialError::Binary),
+        Some((version, _)) => Err(SerialError::UnsupportedVersion(*version)),
+        None => Err(SerialError::BadMagic)
+    }
+}
+
+/// to_bytes serializes value in format
+pub fn to_bytes<T: Serialize>(value: &T, format: Format) -> Result<Vec<u8>, SerialError> {
+    match format {
+        Format::Json => to_json(value).map(String::into_bytes),
+        Format::Binary => to_binary(value)
+    }
+}
+
+/// from_bytes deserializes a value in format
+pub fn from_bytes<T: DeserializeOwned>(bytes: &[u8], format: Format) -> Result<T, SerialError> {
+    match format {
+        Format::Json => serde_json::from_slice(bytes).map_err(SerialError::Json),
+        Format::Binary => from_binary(bytes)
+    }
+}
+
+/// save writes value to path in the format of path (see Format::of)
+pub fn save<T: Serialize>(path: impl AsRef<Path>, value: &T) -> Result<(), SerialError> {
+    let bytes = to_bytes(value, Format::of(&path))?;
+    Ok(std::fs::write(path, bytes)?)
+}
+
+/// load reads a value from path in the format of path (see Format::of)
+pub fn load<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, SerialError> {
+    let bytes = std::fs::read(&path)?;
+    from_bytes(&bytes, Format::of(&path))
+}
+
+/// write_framed writes value to out in the binary format, prefixed with its length as a little endian u32
+///
+/// # Notes
+/// Fails without writing anything if value is longer than MAX_FRAME
+pub(crate) fn write_framed<T: Serialize>(out: &mut impl Write, value: &T) -> Result<(), SerialError> {
+    let bytes = to_binary(value)?;
+    if bytes.len() > MAX_FRAME {
+        return Err(SerialError::FrameTooLarge(bytes.len()));
+    }
+    out.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    Ok(out.write_all(&bytes)?)
+}
+
+/// read_framed reads a value written by write_framed, None if input ends before the next value
+///
+/// # Notes
+/// A value that is cut short fails with an io error of kind UnexpectedEof
+pub(crate) fn read_framed<T: DeserializeOwned>(input: &mut impl Read) -> Result<Option<T>, SerialError> {
+    let mut length = [0u8; 4];
+    match input.read_exact(&mut length) {
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        result => result?
+    }
+    let length = u32::from_le_bytes(length) as usize;
+    if length > MAX_FRAME {
+        return Err(SerialError::FrameTooLarge(length));
+    }
+    let mut bytes = vec![0u8; length];
+    input.read_exact(&mut bytes)?;
+    from_binary(&bytes).map(Some)
+}
@@ -1,10 +1,27 @@
-use crate::c_array;
+//! state is the type used to communicate state from the client to the server
+//!
+//! Every pointer in State and its children is owned by the client and is only valid
+//! for the duration of the expr_push_state call it was passed to.
+//! The unsafe accessors in this module require exactly that: the state must be one received through
+//! expr_push_state (or built by the client) and still be in scope.
+//!
+//! StateView wraps a State in safe, borrowed views: the state is checked for null pointers
+//! and negative lengths once when the view is created, after which it can be read without unsafe.
+
+use std::error::Error;
+use std::ffi::CStr;
+use std::fmt::{Display, Formatter};
+use crate::{c_array, c_slice, c_str};
+use super::menu::{MenuError, MenuItem, OPEN_ATTR};
 
 /// Attribute is a string of the format: "name:value"
 /// The first section identifies what attribute is being read, and the value denotes the value.
 /// If an attriubte has more than one argument, they will be separated by further :.
+/// A : inside an argument is escaped as \:, and a \ as \\.
 /// Examples of attributes: "status:burn", "items:5", "movement:5:5"
 ///
+/// See the attribute module for a typed form of attributes.
+///
 /// # Notes
 /// * Extensions can add new attributes, therefore the list of attributes for any given type
 ///   may not be complete.
@@ -20,7 +37,10 @@ pub type Attribute = *mut i8;
 /// * BuildingTraversal - The scene takes place inside a building with no map context
 /// * Battle - The scene takes place inside a battle map
 #[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RenderContext {
+    #[default]
     WorldTraversal,
     BuildingTraversal,
     Battle
@@ -40,7 +60,10 @@ pub enum RenderContext {
 /// * Summary - actor/world summary screen is open; map is loaded, pauses game
 /// * Loadout - user loadout is open; map is loaded, does not pause game but should capture input
 #[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MenuContext {
+    #[default]
     Invisible,
     Main,
     Pause,
@@ -61,19 +84,60 @@ pub enum MenuContext {
 /// * items is a list of items in this section,
 ///   item may be of the form "t:<string>" in which case it is a plaintext string,
 ///   or it may be of the form "s:<index>" in which case it is a subsection at a given index
-/// * selected item indicates which item is selected
+///   (see the menu module for a typed form of items)
+/// * selected item indicates which item is selected, -1 if there are no items
 /// * attrs is an attribute set
+///
+/// # Standard Client Attributes
+/// * open - the user has descended into this subsection, the section the user is in is the deepest open
+///   subsection reached by following selected items from the selected top level section
 #[repr(C)]
 pub struct Section {
-    title: *mut i8,
-    description: *mut i8,
-    subsections: c_array<Section>,
-    subsections_length: isize,
-    items: c_array<Attribute>,
-    items_length: isize,
-    selected_item: isize,
-    attrs: c_array<Attribute>,
-    attrs_length: isize
+    pub(crate) title: *mut i8,
+    pub(crate) description: *mut i8,
+    pub(crate) subsections: c_array<Section>,
+    pub(crate) subsections_length: isize,
+    pub(crate) items: c_array<Attribute>,
+    pub(crate) items_length: isize,
+    pub(crate) selected_item: isize,
+    pub(crate) attrs: c_array<Attribute>,
+    pub(crate) attrs_length: isize
+}
+
+impl Section {
+    /// # Safety
+    /// See the module documentation
+    pub unsafe fn title(&self) -> Option<&CStr> {
+        c_str(self.title)
+    }
+
+    /// # Safety
+    /// See the module documentation
+    pub unsafe fn description(&self) -> Option<&CStr> {
+        c_str(self.description)
+    }
+
+    /// # Safety
+    /// See the module documentation
+    pub unsafe fn subsections(&self) -> &[Section] {
+        c_slice(self.subsections, self.subsections_length)
+    }
+
+    /// # Safety
+    /// See the module documentation
+    pub unsafe fn items(&self) -> &[Attribute] {
+        c_slice(self.items, self.items_length)
+    }
+
+    pub fn selected_item(&self) -> isize {
+        self.selected_item
+    }
+
+    /// # Safety
+    /// See the module documentation
+    pub unsafe fn attrs(&self) -> &[Attribute] {
+        c_slice(self.attrs, self.attrs_length)
+    }
 }
 
 /// Drawable represents a tile to be drawn on the world
@@ -85,15 +149,39 @@ pub struct Section {
 /// * attrs is an attribute set
 #[repr(C)]
 pub struct Drawable {
-    kind: *mut i8,
-    pos_x: i64,
-    pos_y: i64,
-    pos_z: i64,
-    span_x: i64,
-    span_y: i64,
-    span_z: i64,
-    attrs: c_array<Attribute>,
-    attrs_length: isize
+    pub(crate) kind: *mut i8,
+    pub(crate) pos_x: i64,
+    pub(crate) pos_y: i64,
+    pub(crate) pos_z: i64,
+    pub(crate) span_x: i64,
+    pub(crate) span_y: i64,
+    pub(crate) span_z: i64,
+    pub(crate) attrs: c_array<Attribute>,
+    pub(crate) attrs_length: isize
+}
+
+impl Drawable {
+    /// # Safety
+    /// See the module documentation
+    pub unsafe fn kind(&self) -> Option<&CStr> {
+        c_str(self.kind)
+    }
+
+    /// position is the (x, y, z) position of this tile
+    pub fn position(&self) -> (i64, i64, i64) {
+        (self.pos_x, self.pos_y, self.pos_z)
+    }
+
+    /// span is the (x, y, z) size of this tile
+    pub fn span(&self) -> (i64, i64, i64) {
+        (self.span_x, self.span_y, self.span_z)
+    }
+
+    /// # Safety
+    /// See the module documentation
+    pub unsafe fn attrs(&self) -> &[Attribute] {
+        c_slice(self.attrs, self.attrs_length)
+    }
 }
 
 /// Actor represents an in-world character, either a player or an AI
@@ -101,7 +189,7 @@ pub struct Drawable {
 /// # Fields
 /// * name is the actors name, how this is displayed is up to the server
 /// * description is the actors description text, how this is displayed is up to the server
-/// * draw is the tile information for this actor (valid kind details in the Valid Kinds section)
+/// * draw is the tile information for this actor (valid kind details in the Valid Kinds section, NOTE: extensions can add new kinds)
 /// * attrs is an attribute set
 ///
 /// # Valid Kinds
@@ -120,17 +208,41 @@ pub struct Drawable {
 ///   positive delta means stat increase and negative delta means stat decrease
 #[repr(C)]
 pub struct Actor {
-    name: *mut i8,
-    description: *mut i8,
-    draw: Drawable,
-    attrs: c_array<Attribute>,
-    attrs_length: isize
+    pub(crate) name: *mut i8,
+    pub(crate) description: *mut i8,
+    pub(crate) draw: Drawable,
+    pub(crate) attrs: c_array<Attribute>,
+    pub(crate) attrs_length: isize
+}
+
+impl Actor {
+    /// # Safety
+    /// See the module documentation
+    pub unsafe fn name(&self) -> Option<&CStr> {
+        c_str(self.name)
+    }
+
+    /// # Safety
+    /// See the module documentation
+    pub unsafe fn description(&self) -> Option<&CStr> {
+        c_str(self.description)
+    }
+
+    pub fn draw(&self) -> &Drawable {
+        &self.draw
+    }
+
+    /// # Safety
+    /// See the module documentation
+    pub unsafe fn attrs(&self) -> &[Attribute] {
+        c_slice(self.attrs, self.attrs_length)
+    }
 }
 
 /// Terrain represents a world tile that may or may not be traversable
 ///
 /// # Fields
-/// * draw is the tile information for this terrain (valid kinds listed in Valid Kinds section)
+/// * draw is the tile information for this terrain (valid kinds listed in Valid Kinds section, NOTE: extensions can add new kinds)
 /// * attrs is an attribute set
 ///
 /// # Valid Kinds
@@ -148,25 +260,69 @@ pub struct Actor {
 /// * resource:\<name\>:\<value\> - The quantity of a given resource available on this terrain (may occur more than once)
 #[repr(C)]
 pub struct Terrain {
-    draw: Drawable,
-    attrs: c_array<Attribute>,
-    attrs_length: isize
+    pub(crate) draw: Drawable,
+    pub(crate) attrs: c_array<Attribute>,
+    pub(crate) attrs_length: isize
+}
+
+impl Terrain {
+    pub fn draw(&self) -> &Drawable {
+        &self.draw
+    }
+
+    /// # Safety
+    /// See the module documentation
+    pub unsafe fn attrs(&self) -> &[Attribute] {
+        c_slice(self.attrs, self.attrs_length)
+    }
 }
 
 /// WorldState is the collective game state of all terrain in the current world
 ///
 /// # Fields
-/// * terrain is a flattened array of length terrain_len_x * terrain_len_y * terrain_len_z
+/// * terrain is a flattened array of length terrain_len_x * terrain_len_y * terrain_len_z,
+///   the terrain at (x, y, z) is at index x + y * terrain_len_x + z * terrain_len_x * terrain_len_y
 /// * terrain_len_* is the length of the terrain along a given axis
 /// * attrs is an attribute set
 #[repr(C)]
 pub struct WorldState {
-    terrain: c_array<Terrain>, // NOTE: this is an array of length terrain_length
-    terrain_len_x: i64,
-    terrain_len_y: i64,
-    terrain_len_z: i64,
-    attrs: c_array<Attribute>,
-    attrs_length: isize
+    pub(crate) terrain: c_array<Terrain>, // NOTE: this is an array of length terrain_length
+    pub(crate) terrain_len_x: i64,
+    pub(crate) terrain_len_y: i64,
+    pub(crate) terrain_len_z: i64,
+    pub(crate) attrs: c_array<Attribute>,
+    pub(crate) attrs_length: isize
+}
+
+impl WorldState {
+    /// dimensions is the (x, y, z) length of the terrain
+    pub fn dimensions(&self) -> (i64, i64, i64) {
+        (self.terrain_len_x, self.terrain_len_y, self.terrain_len_z)
+    }
+
+    /// terrain_length is the length of the flattened terrain, None if any dimension is negative or it overflows
+    fn terrain_length(&self) -> Option<isize> {
+        if self.terrain_len_x < 0 || self.terrain_len_y < 0 || self.terrain_len_z < 0 {
+            return None;
+        }
+        self.terrain_len_x.checked_mul(self.terrain_len_y)
+            .and_then(|l| l.checked_mul(self.terrain_len_z))
+            .and_then(|l| isize::try_from(l).ok())
+    }
+
+    /// terrain is the flattened terrain, empty if any dimension is not positive
+    ///
+    /// # Safety
+    /// See the module documentation
+    pub unsafe fn terrain(&self) -> &[Terrain] {
+        c_slice(self.terrain, self.terrain_length().unwrap_or(0))
+    }
+
+    /// # Safety
+    /// See the module documentation
+    pub unsafe fn attrs(&self) -> &[Attribute] {
+        c_slice(self.attrs, self.attrs_length)
+    }
 }
 
 /// ActorState is the collective game state of all actors in the current world
@@ -176,27 +332,66 @@ pub struct WorldState {
 /// * attrs is an attribute set
 #[repr(C)]
 pub struct ActorState {
-    actors: c_array<Actor>, // NOTE: this is an array of length actors_length
-    actors_length: isize,
-    attrs: c_array<Attribute>,
-    attrs_length: isize
+    pub(crate) actors: c_array<Actor>, // NOTE: this is an array of length actors_length
+    pub(crate) actors_length: isize,
+    pub(crate) attrs: c_array<Attribute>,
+    pub(crate) attrs_length: isize
+}
+
+impl ActorState {
+    /// # Safety
+    /// See the module documentation
+    pub unsafe fn actors(&self) -> &[Actor] {
+        c_slice(self.actors, self.actors_length)
+    }
+
+    /// # Safety
+    /// See the module documentation
+    pub unsafe fn attrs(&self) -> &[Attribute] {
+        c_slice(self.attrs, self.attrs_length)
+    }
 }
 
 /// MenuState is the current state of the game menu
 ///
 /// # Fields
-/// * kind is the kind of menu this is (see Valid Kinds section, NOTE: extensions can add new kinds)
+/// * kind is the kind of menu this is, one of the MenuContext variants in lowercase (e.g. "pause"),
+///   NOTE: extensions can add new kinds
 /// * sections is a list of top level sections in this menu
-/// * selected_section is the currently active section
+/// * selected_section is the currently active top level section, -1 if there are no sections
 /// * attrs is an attribute set
 #[repr(C)]
 pub struct MenuState {
-    kind: *mut i8,
-    sections: c_array<*mut i8>,
-    sections_length: isize,
-    selected_section: isize,
-    attrs: c_array<Attribute>,
-    attrs_length: isize
+    pub(crate) kind: *mut i8,
+    pub(crate) sections: c_array<Section>,
+    pub(crate) sections_length: isize,
+    pub(crate) selected_section: isize,
+    pub(crate) attrs: c_array<Attribute>,
+    pub(crate) attrs_length: isize
+}
+
+impl MenuState {
+    /// # Safety
+    /// See the module documentation
+    pub unsafe fn kind(&self) -> Option<&CStr> {
+        c_str(self.kind)
+    }
+
+    /// # Safety
+    /// See the module documentation
+    pub unsafe fn sections(&self) -> &[Section] {
+        c_slice(self.sections, self.sections_length)
+    }
+
+    pub fn selected_section(&self) -> isize {
+        self.selected_section
+    }
+
+    /// # Safety
+    /// See the module documentation
+    pub unsafe fn attrs(&self) -> &[Attribute] {
+        c_slice(self.attrs, self.attrs_length)
+    }
 }
 
 /// State is the aggregated state of the current world
@@ -204,10 +399,686 @@ pub struct MenuState {
 /// See the documentation for each type for a description as to their purpose
 #[repr(C)]
 pub struct State {
-    render_context: RenderContext,
-    world_state: WorldState,
-    actor_state: ActorState,
-    menu_state: MenuState,
-    attrs: c_array<Attribute>,
-    attrs_length: isize
-}
\ No newline at end of file
+    pub(crate) render_context: RenderContext,
+    pub(crate) world_state: WorldState,
+    pub(crate) actor_state: ActorState,
+    pub(crate) menu_state: MenuState,
+    pub(crate) attrs: c_array<Attribute>,
+    pub(crate) attrs_length: isize
+}
+
+impl State {
+    pub fn render_context(&self) -> &RenderContext {
+        &self.render_context
+    }
+
+    pub fn world_state(&self) -> &WorldState {
+        &self.world_state
+    }
+
+    pub fn actor_state(&self) -> &ActorState {
+        &self.actor_state
+    }
+
+    pub fn menu_state(&self) -> &MenuState {
+        &self.menu_state
+    }
+
+    /// # Safety
+    /// See the module documentation
+    pub unsafe fn attrs(&self) -> &[Attribute] {
+        c_slice(self.attrs, self.attrs_length)
+    }
+}
+
+/// ViewError describes the first part of a State that can not be viewed safely
+///
+/// # Fields
+/// * path is where the problem is, e.g. "world_state.terrain[3].draw.kind"
+/// * kind is what the problem is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ViewError {
+    path: String,
+    kind: ViewErrorKind
+}
+
+/// ViewErrorKind is the kind of problem a ViewError describes
+///
+/// # Variants
+/// * Null - a required pointer, or an array with a positive length, is null
+/// * NegativeLength - a length or dimension is negative
+/// * TooLarge - the terrain dimensions overflow when multiplied
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ViewErrorKind {
+    Null,
+    NegativeLength(i64),
+    TooLarge
+}
+
+impl ViewError {
+    fn new(field: &str, kind: ViewErrorKind) -> ViewError {
+        ViewError { path: field.to_string(), kind }
+    }
+
+    /// within prefixes the path with the field or index containing it
+    fn within(mut self, parent: impl Display) -> ViewError {
+        self.path = if self.path.starts_with('[') {
+            format!("{}{}", parent, self.path)
+        } else {
+            format!("{}.{}", parent, self.path)
+        };
+        self
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn kind(&self) -> ViewErrorKind {
+        self.kind
+    }
+}
+
+impl Display for ViewError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            ViewErrorKind::Null => write!(f, "{} is null", self.path),
+            ViewErrorKind::NegativeLength(length) => write!(f, "{} has negative length {}", self.path, length),
+            ViewErrorKind::TooLarge => write!(f, "{} is too large", self.path)
+        }
+    }
+}
+
+impl Error for ViewError {}
+
+/// check_str fails if a required string is null
+fn check_str(field: &str, string: *mut i8) -> Result<(), ViewError> {
+    if string.is_null() {
+        return Err(ViewError::new(field, ViewErrorKind::Null));
+    }
+    Ok(())
+}
+
+/// check_array reads a c_array, failing on a negative length or a null array with a positive length
+///
+/// # Safety
+/// See c_slice
+unsafe fn check_array<'a, T>(field: &str, array: c_array<T>, length: isize) -> Result<&'a [T], ViewError> {
+    if length < 0 {
+        return Err(ViewError::new(field, ViewErrorKind::NegativeLength(length as i64)));
+    }
+    if array.is_null() && length > 0 {
+        return Err(ViewError::new(field, ViewErrorKind::Null));
+    }
+    Ok(c_slice(array, length))
+}
+
+/// check_strings checks a c_array of strings, every string must be non-null
+///
+/// # Safety
+/// See c_slice
+unsafe fn check_strings(field: &str, array: c_array<*mut i8>, length: isize) -> Result<(), ViewError> {
+    for (idx, string) in check_array(field, array, length)?.iter().enumerate() {
+        check_str("", *string).map_err(|_| ViewError::new(&format!("[{}]", idx), ViewErrorKind::Null).within(field))?;
+    }
+    Ok(())
+}
+
+/// check_items checks every item of a c_array with check
+///
+/// # Safety
+/// See c_slice
+unsafe fn check_items<T>(field: &str, array: c_array<T>, length: isize,
+                         check: impl Fn(&T) -> Result<(), ViewError>) -> Result<(), ViewError> {
+    for (idx, item) in check_array(field, array, length)?.iter().enumerate() {
+        check(item).map_err(|e| e.within(format_args!("{}[{}]", field, idx)))?;
+    }
+    Ok(())
+}
+
+impl Drawable {
+    unsafe fn check(&self) -> Result<(), ViewError> {
+        check_str("kind", self.kind)?;
+        check_strings("attrs", self.attrs, self.attrs_length)
+    }
+}
+
+impl Terrain {
+    unsafe fn check(&self) -> Result<(), ViewError> {
+        self.draw.check().map_err(|e| e.within("draw"))?;
+        check_strings("attrs", self.attrs, self.attrs_length)
+    }
+}
+
+impl Actor {
+    unsafe fn check(&self) -> Result<(), ViewError> {
+        check_str("name", self.name)?;
+        self.draw.check().map_err(|e| e.within("draw"))?;
+        check_strings("attrs", self.attrs, self.attrs_length)
+    }
+}
+
+impl Section {
+    unsafe fn check(&self) -> Result<(), ViewError> {
+        check_str("title", self.title)?;
+        check_items("subsections", self.subsections, self.subsections_length, |s| s.check())?;
+        check_strings("items", self.items, self.items_length)?;
+        check_strings("attrs", self.attrs, self.attrs_length)
+    }
+}
+
+impl State {
+    unsafe fn check(&self) -> Result<(), ViewError> {
+        let world = &self.world_state;
+        for (axis, length) in [("terrain_len_x", world.terrain_len_x), ("terrain_len_y", world.terrain_len_y),
+                               ("terrain_len_z", world.terrain_len_z)] {
+            if length < 0 {
+                return Err(ViewError::new(axis, ViewErrorKind::NegativeLength(length)).within("world_state"));
+            }
+        }
+        let length = world.terrain_length()
+            .ok_or_else(|| ViewError::new("terrain", ViewErrorKind::TooLarge).within("world_state"))?;
+        check_items("terrain", world.terrain, length, |t| t.check())
+            .and_then(|_| check_strings("attrs", world.attrs, world.attrs_length))
+            .map_err(|e| e.within("world_state"))?;
+
+        let actors = &self.actor_state;
+        check_items("actors", actors.actors, actors.actors_length, |a| a.check())
+            .and_then(|_| check_strings("attrs", actors.attrs, actors.attrs_length))
+            .map_err(|e| e.within("actor_state"))?;
+
+        let menu = &self.menu_state;
+        check_str("kind", menu.kind)
+            .and_then(|_| check_items("sections", menu.sections, menu.sections_length, |s| s.check()))
+            .and_then(|_| check_strings("attrs", menu.attrs, menu.attrs_length))
+            .map_err(|e| e.within("menu_state"))?;
+
+        check_strings("attrs", self.attrs, self.attrs_length)
+    }
+}
+
+/// Strings iterates over a checked array of strings
+#[derive(Debug, Clone)]
+pub struct Strings<'a>(std::slice::Iter<'a, *mut i8>);
+
+impl<'a> Strings<'a> {
+    /// # Safety
+    /// strings must have been checked with check_strings and outlive 'a
+    unsafe fn new(strings: &'a [*mut i8]) -> Strings<'a> {
+        Strings(strings.iter())
+    }
+}
+
+impl<'a> Iterator for Strings<'a> {
+    type Item = &'a CStr;
+
+    fn next(&mut self) -> Option<&'a CStr> {
+        // NOTE: every string was checked to be non-null when the view was created
+        self.0.next().map(|s| unsafe { CStr::from_ptr(*s) })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Strings<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|s| unsafe { CStr::from_ptr(*s) })
+    }
+}
+
+impl ExactSizeIterator for Strings<'_> {}
+
+// NOTE: every view below wraps a part of a State that was checked by StateView::new,
+//       so reading its pointers is sound for as long as 'a
+
+/// StateView is a safe, borrowed view of a State
+#[derive(Clone, Copy)]
+pub struct StateView<'a>(&'a State);
+
+impl<'a> StateView<'a> {
+    /// new checks state for null pointers and negative lengths and wraps it in a view
+    ///
+    /// # Safety
+    /// See the module documentation, state must stay valid for 'a
+    pub unsafe fn new(state: &'a State) -> Result<StateView<'a>, ViewError> {
+        state.check()?;
+        Ok(StateView(state))
+    }
+
+    /// from_ptr is new for a state passed to expr_push_state
+    ///
+    /// # Safety
+    /// state must be null or point to a State that stays valid for 'a
+    pub unsafe fn from_ptr(state: *const State) -> Result<StateView<'a>, ViewError> {
+        match state.as_ref() {
+            Some(state) => StateView::new(state),
+            None => Err(ViewError::new("state", ViewErrorKind::Null))
+        }
+    }
+
+    /// state is the viewed State
+    pub fn state(&self) -> &'a State {
+        self.0
+    }
+
+    pub fn render_context(&self) -> RenderContext {
+        self.0.render_context
+    }
+
+    pub fn world(&self) -> WorldView<'a> {
+        WorldView(&self.0.world_state)
+    }
+
+    pub fn actors(&self) -> impl ExactSizeIterator<Item = ActorView<'a>> + 'a {
+        unsafe { self.0.actor_state.actors() }.iter().map(ActorView)
+    }
+
+    /// actor_attrs is the attribute set of the actor state
+    pub fn actor_attrs(&self) -> Strings<'a> {
+        unsafe { Strings::new(self.0.actor_state.attrs()) }
+    }
+
+    pub fn menu(&self) -> MenuView<'a> {
+        MenuView(&self.0.menu_state)
+    }
+
+    pub fn attrs(&self) -> Strings<'a> {
+        unsafe { Strings::new(self.0.attrs()) }
+    }
+}
+
+/// WorldView is a safe, borrowed view of a WorldState
+#[derive(Clone, Copy)]
+pub struct WorldView<'a>(&'a WorldState);
+
+impl<'a> WorldView<'a> {
+    /// dimensions is the (x, y, z) length of the terrain
+    pub fn dimensions(&self) -> (usize, usize, usize) {
+        let (x, y, z) = self.0.dimensions();
+        (x as usize, y as usize, z as usize)
+    }
+
+    /// terrain iterates over the flattened terrain, see WorldState for the order
+    pub fn terrain(&self) -> impl ExactSizeIterator<Item = TerrainView<'a>> + 'a {
+        unsafe { self.0.terrain() }.iter().map(TerrainView)
+    }
+
+    /// get is the terrain at (x, y, z), None if it is out of bounds
+    pub fn get(&self, x: usize, y: usize, z: usize) -> Option<TerrainView<'a>> {
+        let (len_x, len_y, len_z) = self.dimensions();
+        if x >= len_x || y >= len_y || z >= len_z {
+            return None;
+        }
+        unsafe { self.0.terrain() }.get(x + y * len_x + z * len_x * len_y).map(TerrainView)
+    }
+
+    pub fn attrs(&self) -> Strings<'a> {
+        unsafe { Strings::new(self.0.attrs()) }
+    }
+}
+
+/// DrawableView is a safe, borrowed view of a Drawable
+#[derive(Clone, Copy)]
+pub struct DrawableView<'a>(&'a Drawable);
+
+impl<'a> DrawableView<'a> {
+    pub fn kind(&self) -> &'a CStr {
+        unsafe { CStr::from_ptr(self.0.kind) }
+    }
+
+    pub fn position(&self) -> (i64, i64, i64) {
+        self.0.position()
+    }
+
+    pub fn span(&self) -> (i64, i64, i64) {
+        self.0.span()
+    }
+
+    pub fn attrs(&self) -> Strings<'a> {
+        unsafe { Strings::new(self.0.attrs()) }
+    }
+}
+
+/// TerrainView is a safe, borrowed view of a Terrain
+#[derive(Clone, Copy)]
+pub struct TerrainView<'a>(&'a Terrain);
+
+impl<'a> TerrainView<'a> {
+    pub fn draw(&self) -> DrawableView<'a> {
+        DrawableView(&self.0.draw)
+    }
+
+    pub fn attrs(&self) -> Strings<'a> {
+        unsafe { Strings::new(self.0.attrs()) }
+    }
+}
+
+/// ActorView is a safe, borrowed view of an Actor
+#[derive(Clone, Copy)]
+pub struct ActorView<'a>(&'a Actor);
+
+impl<'a> ActorView<'a> {
+    pub fn name(&self) -> &'a CStr {
+        unsafe { CStr::from_ptr(self.0.name) }
+    }
+
+    pub fn description(&self) -> Option<&'a CStr> {
+        unsafe { self.0.description() }
+    }
+
+    pub fn draw(&self) -> DrawableView<'a> {
+        DrawableView(&self.0.draw)
+    }
+
+    pub fn attrs(&self) -> Strings<'a> {
+        unsafe { Strings::new(self.0.attrs()) }
+    }
+}
+
+/// MenuView is a safe, borrowed view of a MenuState
+#[derive(Clone, Copy)]
+pub struct MenuView<'a>(&'a MenuState);
+
+impl<'a> MenuView<'a> {
+    pub fn kind(&self) -> &'a CStr {
+        unsafe { CStr::from_ptr(self.0.kind) }
+    }
+
+    pub fn sections(&self) -> impl ExactSizeIterator<Item = SectionView<'a>> + 'a {
+        unsafe { self.0.sections() }.iter().map(SectionView)
+    }
+
+    pub fn selected_section(&self) -> isize {
+        self.0.selected_section
+    }
+
+    /// selected is the selected top level section, None if selected_section is out of range
+    pub fn selected(&self) -> Option<SectionView<'a>> {
+        let idx = usize::try_from(self.0.selected_section).ok()?;
+        unsafe { self.0.sections() }.get(idx).map(SectionView)
+    }
+
+    /// current is the section the user is in, see Section for how it is found
+    pub fn current(&self) -> Option<SectionView<'a>> {
+        let mut current = self.selected()?;
+        while let Some(subsection) = current.selected_subsection().filter(SectionView::is_open) {
+            current = subsection;
+        }
+        Some(current)
+    }
+
+    pub fn attrs(&self) -> Strings<'a> {
+        unsafe { Strings::new(self.0.attrs()) }
+    }
+}
+
+/// SectionView is a safe, borrowed view of a Section
+#[derive(Clone, Copy)]
+pub struct SectionView<'a>(&'a Section);
+
+impl<'a> SectionView<'a> {
+    pub fn title(&self) -> &'a CStr {
+        unsafe { CStr::from_ptr(self.0.title) }
+    }
+
+    pub fn description(&self) -> Option<&'a CStr> {
+        unsafe { self.0.description() }
+    }
+
+    pub fn subsections(&self) -> impl ExactSizeIterator<Item = SectionView<'a>> + 'a {
+        unsafe { self.0.subsections() }.iter().map(SectionView)
+    }
+
+    pub fn items(&self) -> Strings<'a> {
+        unsafe { Strings::new(self.0.items()) }
+    }
+
+    /// menu_items decodes every item
+    pub fn menu_items(&self) -> impl ExactSizeIterator<Item = Result<MenuItem, MenuError>> + 'a {
+        self.items().map(MenuItem::from_c_str)
+    }
+
+    /// resolve is the subsection item refers to, None for text items
+    pub fn resolve(&self, item: &MenuItem) -> Result<Option<SectionView<'a>>, MenuError> {
+        let subsections = unsafe { self.0.subsections() };
+        match item {
+            MenuItem::Text(_) => Ok(None),
+            MenuItem::Section(index) => subsections.get(*index).map(|s| Some(SectionView(s)))
+                .ok_or(MenuError::SectionOutOfBounds { index: *index, subsections: subsections.len() })
+        }
+    }
+
+    pub fn selected_item(&self) -> isize {
+        self.0.selected_item
+    }
+
+    /// selected_subsection is the subsection the selected item refers to, if any
+    pub fn selected_subsection(&self) -> Option<SectionView<'a>> {
+        let item = self.items().nth(usize::try_from(self.0.selected_item).ok()?)?;
+        self.resolve(&MenuItem::from_c_str(item).ok()?).ok().flatten()
+    }
+
+    /// is_open is whether the user has descended into this section
+    pub fn is_open(&self) -> bool {
+        self.attrs().any(|a| a.to_bytes() == OPEN_ATTR.as_bytes())
+    }
+
+    pub fn attrs(&self) -> Strings<'a> {
+        unsafe { Strings::new(self.0.attrs()) }
+    }
+}
+
+/// TERRAIN_KINDS is every standard Terrain draw kind (see Terrain)
+pub const TERRAIN_KINDS: &[&str] = &["terminal", "entrance", "no_entrance", "passable", "impassable"];
+
+/// ACTOR_KINDS is every standard Actor draw kind (see Actor)
+pub const ACTOR_KINDS: &[&str] = &["player", "computer"];
+
+/// Violation is a rule documented in this module that a State breaks
+///
+/// # Fields
+/// * path is where the violation is, e.g. "world_state.terrain[12].draw.kind"
+/// * kind is what rule is broken
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    path: String,
+    kind: ViolationKind
+}
+
+/// ViolationKind is the rule a Violation breaks
+///
+/// # Variants
+/// * View - the state can not be viewed safely (see ViewErrorKind)
+/// * SelectionOutOfRange - a selected item or section is not in the list it selects from
+/// * InvalidKind - a drawable kind is neither a standard kind for its container nor one added by an extension
+/// * InvalidItem - a section item is not a valid menu item, or refers to a missing subsection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ViolationKind {
+    View(ViewErrorKind),
+    SelectionOutOfRange { selected: isize, length: usize },
+    InvalidKind(String),
+    InvalidItem(MenuError)
+}
+
+impl Violation {
+    fn new(path: impl ToString, kind: ViolationKind) -> Violation {
+        Violation { path: path.to_string(), kind }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn kind(&self) -> &ViolationKind {
+        &self.kind
+    }
+}
+
+impl From<ViewError> for Violation {
+    fn from(e: ViewError) -> Violation {
+        Violation { path: e.path, kind: ViolationKind::View(e.kind) }
+    }
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ViolationKind::View(kind) => write!(f, "{}", ViewError::new(&self.path, *kind)),
+            ViolationKind::SelectionOutOfRange { selected, length } =>
+                write!(f, "{} selects {} out of {} entries", self.path, selected, length),
+            ViolationKind::InvalidKind(kind) => write!(f, "{} is not a valid kind: {:?}", self.path, kind),
+            ViolationKind::InvalidItem(e) => write!(f, "{}: {}", self.path, e)
+        }
+    }
+}
+
+impl Error for Violation {}
+
+impl State {
+    /// validate checks every rule documented in this module, listing every violation
+    ///
+    /// kinds lists the draw kinds added by extensions (see extension::Extension::KINDS),
+    /// they are valid in addition to TERRAIN_KINDS and ACTOR_KINDS
+    ///
+    /// # Notes
+    /// Every part of the state is checked on its own, so a part that can not be viewed (see StateView::new)
+    /// is reported with ViolationKind::View and only what it contains is skipped
+    ///
+    /// # Safety
+    /// See the module documentation
+    pub unsafe fn validate(&self, kinds: &[&str]) -> Result<(), Vec<Violation>> {
+        let mut validator = Validator { kinds, violations: Vec::new() };
+        validator.state(self);
+        if validator.violations.is_empty() {
+            Ok(())
+        } else {
+            Err(validator.violations)
+        }
+    }
+}
+
+/// Validator walks a State, collecting every violation instead of stopping at the first
+///
+/// # Notes
+/// The checks StateView::new makes are repeated here part by part, a part is only read once it passed them
+struct Validator<'k> {
+    kinds: &'k [&'k str],
+    violations: Vec<Violation>
+}
+
+impl Validator<'_> {
+    /// view records the error of result if there is one, returning its value otherwise
+    fn view<T>(&mut self, result: Result<T, ViewError>) -> Option<T> {
+        result.map_err(|e| self.violations.push(Violation::from(e))).ok()
+    }
+
+    /// array checks a c_array, returning its items if it can be read
+    unsafe fn array<'a, T>(&mut self, path: &str, array: c_array<T>, length: isize) -> Option<&'a [T]> {
+        self.view(check_array(path, array, length))
+    }
+
+    /// strings checks a c_array of strings, returning them if every one can be read
+    unsafe fn strings<'a>(&mut self, path: &str, array: c_array<*mut i8>, length: isize) -> Option<&'a [*mut i8]> {
+        let strings = self.array(path, array, length)?;
+        let before = self.violations.len();
+        for (idx, string) in strings.iter().enumerate() {
+            self.view(check_str(&format!("{}[{}]", path, idx), *string));
+        }
+        (self.violations.len() == before).then_some(strings)
+    }
+
+    fn selection(&mut self, path: &str, selected: isize, length: usize) {
+        let valid = if length == 0 { selected == -1 } else { (0..length as isize).contains(&selected) };
+        if !valid {
+            self.violations.push(Violation::new(path, ViolationKind::SelectionOutOfRange { selected, length }));
+        }
+    }
+
+    unsafe fn drawable(&mut self, path: &str, drawable: &Drawable, standard: &[&str]) {
+        if let Some(()) = self.view(check_str(&format!("{}.kind", path), drawable.kind)) {
+            let kind = CStr::from_ptr(drawable.kind).to_bytes();
+            if !standard.iter().chain(self.kinds).any(|k| k.as_bytes() == kind) {
+                let kind = String::from_utf8_lossy(kind).into_owned();
+                self.violations.push(Violation::new(format!("{}.kind", path), ViolationKind::InvalidKind(kind)));
+            }
+        }
+        self.strings(&format!("{}.attrs", path), drawable.attrs, drawable.attrs_length);
+    }
+
+    unsafe fn section(&mut self, path: &str, section: &Section) {
+        self.view(check_str(&format!("{}.title", path), section.title));
+        let subsections = self.array(&format!("{}.subsections", path), section.subsections, section.subsections_length);
+        for (idx, subsection) in subsections.unwrap_or_default().iter().enumerate() {
+            self.section(&format!("{}.subsections[{}]", path, idx), subsection);
+        }
+        if let Some(items) = self.strings(&format!("{}.items", path), section.items, section.items_length) {
+            for (idx, item) in items.iter().enumerate() {
+                let item = MenuItem::from_c_str(CStr::from_ptr(*item));
+                let resolved = match (item, subsections) {
+                    (Ok(MenuItem::Section(index)), Some(subsections)) if index >= subsections.len() =>
+                        Err(MenuError::SectionOutOfBounds { index, subsections: subsections.len() }),
+                    (item, _) => item.map(|_| ())
+                };
+                if let Err(e) = resolved {
+                    self.violations.push(Violation::new(format!("{}.items[{}]", path, idx), ViolationKind::InvalidItem(e)));
+                }
+            }
+            self.selection(&format!("{}.selected_item", path), section.selected_item, items.len());
+        }
+        self.strings(&format!("{}.attrs", path), section.attrs, section.attrs_length);
+    }
+
+    unsafe fn world(&mut self, world: &WorldState) {
+        let mut dimensions = true;
+        for (axis, length) in [("terrain_len_x", world.terrain_len_x), ("terrain_len_y", world.terrain_len_y),
+                               ("terrain_len_z", world.terrain_len_z)] {
+            if length < 0 {
+                let e = ViewError::new(&format!("world_state.{}", axis), ViewErrorKind::NegativeLength(length));
+                self.violations.push(Violation::from(e));
+                dimensions = false;
+            }
+        }
+        let length = match (dimensions, world.terrain_length()) {
+            (true, Some(length)) => Some(length),
+            (true, None) => self.view(Err(ViewError::new("world_state.terrain", ViewErrorKind::TooLarge))),
+            (false, _) => None
+        };
+        let terrain = length.and_then(|length| self.array("world_state.terrain", world.terrain, length));
+        for (idx, terrain) in terrain.unwrap_or_default().iter().enumerate() {
+            let path = format!("world_state.terrain[{}]", idx);
+            self.drawable(&format!("{}.draw", path), &terrain.draw, TERRAIN_KINDS);
+            self.strings(&format!("{}.attrs", path), terrain.attrs, terrain.attrs_length);
+        }
+        self.strings("world_state.attrs", world.attrs, world.attrs_length);
+    }
+
+    unsafe fn actors(&mut self, actors: &ActorState) {
+        for (idx, actor) in self.array("actor_state.actors", actors.actors, actors.actors_length).unwrap_or_default().iter().enumerate() {
+            let path = format!("actor_state.actors[{}]", idx);
+            self.view(check_str(&format!("{}.name", path), actor.name));
+            self.drawable(&format!("{}.draw", path), &actor.draw, ACTOR_KINDS);
+            self.strings(&format!("{}.attrs", path), actor.attrs, actor.attrs_length);
+        }
+        self.strings("actor_state.attrs", actors.attrs, actors.attrs_length);
+    }
+
+    unsafe fn menu(&mut self, menu: &MenuState) {
+        self.view(check_str("menu_state.kind", menu.kind));
+        if let Some(sections) = self.array("menu_state.sections", menu.sections, menu.sections_length) {
+            for (idx, section) in sections.iter().enumerate() {
+                self.section(&format!("menu_state.sections[{}]", idx), section);
+            }
+            self.selection("menu_state.selected_section", menu.selected_section, sections.len());
+        }
+        self.strings("menu_state.attrs", menu.attrs, menu.attrs_length);
+    }
+
+    unsafe fn state(&mut self, state: &State) {
+        self.world(&state.world_state);
+        self.actors(&state.actor_state);
+        self.menu(&state.menu_state);
+        self.strings("attrs", state.attrs, state.attrs_length);
+    }
+}
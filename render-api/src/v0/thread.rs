@@ -0,0 +1,233 @@
+//! thread runs a render server on a dedicated thread, the core talks to it through channels
+//!
+//! RenderThread owns the RenderServer, every entry point is called on the render thread.
+//! States are enqueued with push, which never blocks on the server,
+//! and everything the server reports comes back to the core as a RenderMessage.
+//! A server that reports ErrorCode::DeviceLost is recovered on the render thread (see RenderServer::recover).
+
+use std::sync::mpsc::{channel, sync_channel, Receiver, RecvTimeoutError, Sender, TryIter};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use crate::error::ErrorCode;
+use super::handle::{RenderError, RenderServer};
+use super::owned;
+use super::{RenderEvent, UserEvent};
+
+/// RenderMessage is anything the render thread reports to the core
+///
+/// # Variants
+/// * Frame - the server reported an event through the frame callback
+/// * User - the server reported a user event through the user callback
+/// * Failed - an entry point called on the render thread failed
+/// * Recovered - the server was lost and has been recovered, it follows the Failed message reporting the loss
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenderMessage {
+    Frame { event: RenderEvent, critical: bool, frame: i32, message: Option<String> },
+    User { event: UserEvent, text: String },
+    Failed(RenderError),
+    Recovered
+}
+
+/// Command is what the core asks of the render thread
+enum Command {
+    Push(Box<owned::State>),
+    Reload,
+    Recover,
+    Stop
+}
+
+/// RenderThread is a render server running on a dedicated thread
+///
+/// # Examples
+/// ```ignore
+/// let render = RenderThread::spawn(|| RenderServer::open(path))?;
+/// render.push(state)?;
+/// for message in render.messages() {
+///     // handle frame and user events
+/// }
+/// ```
+///
+/// # Notes
+/// * Pushes are coalesced, if several states are waiting when the server is ready only the newest is pushed
+/// * When an entry point fails with ErrorCode::DeviceLost the server is recovered right away,
+///   a state whose push was lost is pushed again once the server is back
+/// * Dropping the thread disconnects the server and waits for the thread to finish
+pub struct RenderThread {
+    commands: Sender<Command>,
+    messages: Receiver<RenderMessage>,
+    server_version: String,
+    thread: Option<JoinHandle<()>>
+}
+
+impl RenderThread {
+    /// spawn starts a render thread and opens the server on it with open
+    ///
+    /// Returns once the server is initialized, errors opening the server are returned here rather than as a message.
+    /// Errors registering the callbacks are reported as RenderMessage::Failed
+    pub fn spawn<F>(open: F) -> Result<RenderThread, RenderError>
+        where F: FnOnce() -> Result<RenderServer, RenderError> + Send + 'static
+    {
+        let (commands, incoming) = channel();
+        let (outgoing, messages) = channel();
+        let (opened, started) = sync_channel(1);
+        let thread = std::thread::Builder::new()
+            .name("render".to_string())
+            .spawn(move || {
+                match open() {
+                    Ok(mut server) => {
+                        // NOTE: a server may render without reporting events, so failing to register is not fatal
+                        let registered = register(&mut server, &outgoing);
+                        if let Err(e) = registered {
+                            let _ = outgoing.send(RenderMessage::Failed(e));
+                        }
+                        let _ = opened.send(Ok(server.server_version().to_string()));
+                        run(server, incoming, outgoing);
+                    }
+                    Err(e) => {
+                        let _ = opened.send(Err(e));
+                    }
+                }
+            })
+            .map_err(|e| RenderError::Load(e.to_string()))?;
+        match started.recv() {
+            Ok(Ok(server_version)) => Ok(RenderThread { commands, messages, server_version, thread: Some(thread) }),
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                Err(e)
+            }
+            Err(_) => {
+                let _ = thread.join();
+                Err(RenderError::Stopped)
+            }
+        }
+    }
+
+    /// server_version is the version of the render API the server reported during init
+    pub fn server_version(&self) -> &str {
+        &self.server_version
+    }
+
+    /// push enqueues game_state to be pushed on the render thread, without waiting for the server
+    ///
+    /// # Notes
+    /// Errors pushing the state are reported as RenderMessage::Failed
+    pub fn push(&self, game_state: owned::State) -> Result<(), RenderError> {
+        self.send(Command::Push(Box::new(game_state)))
+    }
+
+    /// reload enqueues a reload of the server (see RenderServer::reload)
+    pub fn reload(&self) -> Result<(), RenderError> {
+        self.send(Command::Reload)
+    }
+
+    /// recover enqueues a reconnect to the server, reinitializing it if asked to (see RenderServer::recover)
+    pub fn recover(&self) -> Result<(), RenderError> {
+        self.send(Command::Recover)
+    }
+
+    /// try_message is the oldest message not yet received, if any
+    pub fn try_message(&self) -> Option<RenderMessage> {
+        self.messages.try_recv().ok()
+    }
+
+    /// wait_message waits up to timeout for a message
+    ///
+    /// # Notes
+    /// Returns RenderError::Stopped once the thread has stopped and every message was received
+    pub fn wait_message(&self, timeout: Duration) -> Result<Option<RenderMessage>, RenderError> {
+        match self.messages.recv_timeout(timeout) {
+            Ok(message) => Ok(Some(message)),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(RenderError::Stopped)
+        }
+    }
+
+    /// messages iterates every message received so far, without waiting
+    pub fn messages(&self) -> TryIter<'_, RenderMessage> {
+        self.messages.try_iter()
+    }
+
+    /// stop disconnects the server and waits for the render thread to finish
+    ///
+    /// Returns every message that was not received yet
+    pub fn stop(mut self) -> Vec<RenderMessage> {
+        self.join();
+        self.messages.try_iter().collect()
+    }
+
+    fn send(&self, command: Command) -> Result<(), RenderError> {
+        self.commands.send(command).map_err(|_| RenderError::Stopped)
+    }
+
+    fn join(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            let _ = self.commands.send(Command::Stop);
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for RenderThread {
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+/// run handles commands until the core stops the thread or drops its end of the channel
+fn run(mut server: RenderServer, incoming: Receiver<Command>, outgoing: Sender<RenderMessage>) {
+    let mut pending = None;
+    while let Some(mut command) = pending.take().or_else(|| incoming.recv().ok()) {
+        // NOTE: only the newest of the states waiting is worth pushing
+        if let Command::Push(_) = command {
+            while let Ok(next) = incoming.try_recv() {
+                match next {
+                    Command::Push(_) => command = next,
+                    next => {
+                        pending = Some(next);
+                        break;
+                    }
+                }
+            }
+        }
+        let (result, game_state) = match command {
+            Command::Push(game_state) => (server.push(&game_state), Some(game_state)),
+            Command::Reload => (server.reload(), None),
+            Command::Recover => (server.recover(), None),
+            Command::Stop => break
+        };
+        let lost = matches!(&result, Err(e) if e.code() == ErrorCode::DeviceLost);
+        if let Err(e) = result {
+            let _ = outgoing.send(RenderMessage::Failed(e));
+        }
+        if lost {
+            recover(&mut server, game_state, &outgoing);
+        }
+    }
+    if let Err(e) = server.disconnect() {
+        let _ = outgoing.send(RenderMessage::Failed(e));
+    }
+}
+
+/// recover recovers a lost server and pushes game_state again, reporting the outcome to outgoing
+fn recover(server: &mut RenderServer, game_state: Option<Box<owned::State>>, outgoing: &Sender<RenderMessage>) {
+    if let Err(e) = server.recover() {
+        let _ = outgoing.send(RenderMessage::Failed(e));
+        return;
+    }
+    let _ = outgoing.send(RenderMessage::Recovered);
+    if let Some(Err(e)) = game_state.map(|game_state| server.push(&game_state)) {
+        let _ = outgoing.send(RenderMessage::Failed(e));
+    }
+}
+
+/// register registers closures sending every frame and user event of server to outgoing
+fn register(server: &mut RenderServer, outgoing: &Sender<RenderMessage>) -> Result<(), RenderError> {
+    let frames = outgoing.clone();
+    server.on_frame(move |event, critical, frame, message| {
+        let _ = frames.send(RenderMessage::Frame { event, critical, frame, message: message.map(str::to_string) });
+    })?;
+    let users = outgoing.clone();
+    server.on_user(move |event, text| {
+        let _ = users.send(RenderMessage::User { event, text: text.to_string() });
+    })
+}
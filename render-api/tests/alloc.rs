@@ -0,0 +1,47 @@
+use render_api::alloc::{self, Allocations};
+
+#[test]
+fn counts_strings_per_owner() {
+    let first = Allocations::new();
+    let second = Allocations::new();
+    let a = first.string("a");
+    let b = first.string("b");
+    let c = second.string("c");
+    assert_eq!((first.outstanding(), second.outstanding()), (2, 1));
+
+    unsafe { alloc::free(b) };
+    unsafe { alloc::free(c) };
+    assert_eq!((first.outstanding(), second.outstanding()), (1, 0));
+    unsafe { alloc::free(a) };
+    assert_eq!(first.outstanding(), 0);
+}
+
+#[test]
+fn clones_share_their_count() {
+    let owner = Allocations::new();
+    let message = owner.clone().string("shared");
+    assert_eq!(owner.outstanding(), 1);
+    unsafe { alloc::free(message) };
+    assert_eq!(owner.outstanding(), 0);
+}
+
+#[test]
+fn frees_strings_without_an_owner() {
+    let owner = Allocations::new();
+    let message = alloc::string("nul\0byte");
+    assert_eq!(unsafe { std::ffi::CStr::from_ptr(message) }.to_str(), Ok("nul byte"));
+    unsafe { alloc::free(message) };
+    unsafe { alloc::free(std::ptr::null_mut()) };
+    assert_eq!(owner.outstanding(), 0);
+}
+
+#[test]
+fn owns_only_its_outstanding_strings() {
+    let owner = Allocations::new();
+    let other = Allocations::new();
+    let message = owner.string("owned");
+    assert!(owner.owns(message) && owner.clone().owns(message));
+    assert!(!other.owns(message));
+    unsafe { alloc::free(message) };
+    assert!(!owner.owns(message));
+}
@@ -0,0 +1,94 @@
+use render_api::v0::attribute::{Attr, AttributeError, AttributeSet, Control};
+
+fn every_attr() -> Vec<Attr> {
+    vec![
+        Attr::Control(Control::Current),
+        Attr::Control(Control::Standby),
+        Attr::Control(Control::Distant),
+        Attr::Status("poisoned".to_string()),
+        Attr::Affinity("fire".to_string()),
+        Attr::AffinityInteraction { name: "water".to_string(), value: "weak".to_string() },
+        Attr::Stat { name: "hp".to_string(), value: 10, delta: None },
+        Attr::Stat { name: "hp".to_string(), value: 7, delta: Some(-3) },
+        Attr::Note("a note".to_string()),
+        Attr::KindNote("a kind note".to_string()),
+        Attr::Type("goblin".to_string()),
+        Attr::Resource { name: "gold".to_string(), value: 250 },
+        Attr::Other { name: "weather".to_string(), args: vec!["rain".to_string(), "heavy".to_string()] },
+        Attr::Other { name: "flag".to_string(), args: Vec::new() }
+    ]
+}
+
+#[test]
+fn every_attr_round_trips() {
+    for attr in every_attr() {
+        assert_eq!(attr.to_string().parse::<Attr>(), Ok(attr.clone()), "{}", attr);
+        assert_eq!(Attr::from_c_str(&attr.to_c_string().unwrap()), Ok(attr));
+    }
+}
+
+#[test]
+fn parses_standard_attributes() {
+    assert_eq!("control:standby".parse(), Ok(Attr::Control(Control::Standby)));
+    assert_eq!("stat:hp:7:-3".parse(), Ok(Attr::Stat { name: "hp".to_string(), value: 7, delta: Some(-3) }));
+    assert_eq!("resource:gold:250".parse(), Ok(Attr::Resource { name: "gold".to_string(), value: 250 }));
+    assert_eq!("weather:rain".parse(), Ok(Attr::Other { name: "weather".to_string(), args: vec!["rain".to_string()] }));
+}
+
+#[test]
+fn escapes_separators_and_backslashes() {
+    let attr = Attr::Status("ratio 1:2 in C:\\".to_string());
+    assert_eq!(attr.to_string(), "status:ratio 1\\:2 in C\\:\\\\");
+    assert_eq!(attr.to_string().parse(), Ok(attr));
+
+    let attr = Attr::Other { name: "a:b".to_string(), args: vec!["\\".to_string(), ":".to_string(), String::new()] };
+    assert_eq!(attr.to_string(), "a\\:b:\\\\:\\::");
+    assert_eq!(attr.to_string().parse(), Ok(attr));
+}
+
+#[test]
+fn rejects_unknown_escapes() {
+    for attr in ["note:a\\x", "note:a\\", "\\n", "stat:h\\p:1"] {
+        assert_eq!(attr.parse::<Attr>(), Err(AttributeError::InvalidEscape(attr.to_string())), "{}", attr);
+    }
+}
+
+#[test]
+fn rejects_wrong_arities() {
+    let arity = |name: &str, found| Err(AttributeError::Arity { name: name.to_string(), found });
+    assert_eq!("control".parse::<Attr>(), arity("control", 0));
+    assert_eq!("control:current:now".parse::<Attr>(), arity("control", 2));
+    assert_eq!("status:a:b".parse::<Attr>(), arity("status", 2));
+    assert_eq!("note".parse::<Attr>(), arity("note", 0));
+    assert_eq!("affinity_interaction:water".parse::<Attr>(), arity("affinity_interaction", 1));
+    assert_eq!("stat:hp".parse::<Attr>(), arity("stat", 1));
+    assert_eq!("stat:hp:1:2:3".parse::<Attr>(), arity("stat", 4));
+    assert_eq!("resource:gold:1:2".parse::<Attr>(), arity("resource", 3));
+}
+
+#[test]
+fn rejects_invalid_values() {
+    assert_eq!("".parse::<Attr>(), Err(AttributeError::Empty));
+    assert_eq!(":value".parse::<Attr>(), Err(AttributeError::Empty));
+    assert_eq!("control:asleep".parse::<Attr>(), Err(AttributeError::InvalidControl("asleep".to_string())));
+    assert_eq!("stat:hp:ten".parse::<Attr>(),
+               Err(AttributeError::InvalidNumber { name: "stat".to_string(), value: "ten".to_string() }));
+    assert_eq!("resource:gold:1.5".parse::<Attr>(),
+               Err(AttributeError::InvalidNumber { name: "resource".to_string(), value: "1.5".to_string() }));
+    assert_eq!(Attr::Note("nul\0".to_string()).to_c_string(), Err(AttributeError::Nul("note:nul\0".to_string())));
+}
+
+#[test]
+fn attribute_sets_round_trip() {
+    let set: AttributeSet = every_attr().into_iter().collect();
+    let strings = set.to_strings();
+    assert_eq!(AttributeSet::parse(strings.iter().map(String::as_str)), Ok(set.clone()));
+    let c_strings = set.to_c_strings().unwrap();
+    assert_eq!(AttributeSet::from_c_strs(c_strings.iter().map(|s| s.as_c_str())), Ok(set.clone()));
+
+    assert_eq!(set.control(), Some(Control::Current));
+    assert_eq!(set.stat("hp"), Some((10, None)));
+    assert_eq!(set.resource("gold"), Some(250));
+    assert_eq!(set.statuses().collect::<Vec<_>>(), vec!["poisoned"]);
+    assert_eq!(set.named("stat").count(), 2);
+}
@@ -0,0 +1,117 @@
+#![cfg(feature = "client")]
+
+use std::sync::{Arc, Mutex};
+use render_api::error::ErrorCode;
+use render_api::negotiate::ClientMetadata;
+use render_api::v0::conformance::{check, check_library, Outcome, Rule};
+use render_api::v0::handle::RenderServer;
+use render_api::v0::mock::{EntryPoint, MockBackend};
+use render_api::v0::owned::{State, Terrain, WorldState};
+use render_api::v0::RenderEvent;
+
+#[test]
+fn mock_conforms() {
+    let mock = MockBackend::new();
+    let report = check(&mock);
+
+    assert!(report.conforms(), "{}", report);
+    assert_eq!(report.results().len(), Rule::ALL.len());
+    assert!(!mock.is_connected());
+}
+
+#[test]
+fn reports_violated_rules() {
+    let mock = MockBackend::new();
+    mock.fail_with(EntryPoint::PushState, ErrorCode::InvalidState, true, "rejects everything");
+    mock.request_reinit(true);
+    let report = check(&mock);
+
+    let violated: Vec<Rule> = report.violations().map(|(rule, _)| rule).collect();
+    assert_eq!(violated, vec![Rule::AcceptsValidStates, Rule::Reconnect]);
+    assert_eq!(report.outcome(Rule::RejectsInvalidStates), Some(&Outcome::Passed));
+    assert_eq!(mock.inits(), 2);
+}
+
+#[test]
+fn expects_invalid_states_to_be_reported_as_such() {
+    let mock = MockBackend::new();
+    mock.fail(EntryPoint::PushState, "out of memory");
+    let report = check(&mock);
+
+    assert!(matches!(report.outcome(Rule::RejectsInvalidStates), Some(Outcome::Failed(_))));
+}
+
+#[test]
+fn skips_everything_after_a_failed_init() {
+    let mock = MockBackend::with_version("999");
+    mock.fail(EntryPoint::Init, "no display");
+    let report = check(&mock);
+
+    assert!(report.conforms());
+    assert_eq!(report.outcome(Rule::InitResult), Some(&Outcome::Passed));
+    assert_eq!(report.outcome(Rule::Reconnect), Some(&Outcome::Skipped("initialization failed: Unknown: no display".to_string())));
+}
+
+#[test]
+fn rejects_unsupported_versions() {
+    let report = check(&MockBackend::with_version("999"));
+    assert!(matches!(report.outcome(Rule::SupportedVersion), Some(Outcome::Failed(_))));
+}
+
+#[test]
+fn reports_missing_libraries() {
+    let report = check_library("/nonexistent/render.so");
+    assert!(matches!(report.outcome(Rule::RequiredSymbols), Some(Outcome::Failed(_))));
+    assert!(matches!(report.outcome(Rule::InitResult), Some(Outcome::Skipped(_))));
+}
+
+/// build_c_server compiles the example C server against include/render_api.h, with $CC if set
+///
+/// # Notes
+/// The server is compiled once, every test loads the same library
+#[cfg(unix)]
+fn build_c_server() -> &'static std::path::Path {
+    static LIBRARY: std::sync::OnceLock<std::path::PathBuf> = std::sync::OnceLock::new();
+    LIBRARY.get_or_init(compile_c_server)
+}
+
+#[cfg(unix)]
+fn compile_c_server() -> std::path::PathBuf {
+    let crate_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+    let library = std::path::Path::new(env!("CARGO_TARGET_TMPDIR"))
+        .join(format!("{}render_c{}", std::env::consts::DLL_PREFIX, std::env::consts::DLL_SUFFIX));
+    let compiler = std::env::var_os("CC").unwrap_or_else(|| "cc".into());
+    let status = std::process::Command::new(&compiler)
+        .args(["-std=c99", "-Wall", "-Wextra", "-pedantic", "-Werror", "-shared", "-fPIC"])
+        .arg("-I").arg(crate_dir.join("include"))
+        .arg(crate_dir.join("examples").join("c").join("render_c.c"))
+        .arg("-o").arg(&library)
+        .status()
+        .unwrap_or_else(|e| panic!("{} could not be run: {}", compiler.to_string_lossy(), e));
+    assert!(status.success(), "render_c.c failed to compile");
+    library
+}
+
+#[test]
+#[cfg(unix)]
+fn c_server_conforms() {
+    let report = check_library(build_c_server());
+    assert!(report.conforms(), "{}", report);
+    assert!(report.results().iter().all(|(_, outcome)| *outcome == Outcome::Passed), "{}", report);
+}
+
+#[test]
+#[cfg(unix)]
+fn c_server_reports_frame_events() {
+    let mut server = RenderServer::open_with(build_c_server(), ClientMetadata::new("test")).unwrap();
+    assert_eq!(server.server_version(), "0");
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let received = events.clone();
+    server.on_frame(move |event, critical, frame, message| {
+        received.lock().unwrap().push((event, critical, frame, message.map(str::to_string)));
+    }).unwrap();
+
+    server.push(&State::default().with_world(WorldState::fill(2, 2, 1, &Terrain::new("passable")))).unwrap();
+    server.push(&State::default()).unwrap();
+    assert_eq!(*events.lock().unwrap(), vec![(RenderEvent::FrameSkipped, false, 2, Some("nothing to draw".to_string()))]);
+}
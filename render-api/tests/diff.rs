@@ -0,0 +1,119 @@
+use render_api::v0::diff::{ActorChange, Delta, DELTA_ACTOR_ATTRS, DELTA_MENU, DELTA_WORLD};
+use render_api::v0::owned::{Actor, ActorState, MenuState, Section, State, Terrain, WorldState};
+use render_api::v0::state::RenderContext;
+
+fn actors(names: &[&str]) -> State {
+    let actors = names.iter().fold(ActorState::new(), |actors, name| actors.with_actor(Actor::new(*name, "computer")));
+    State::default()
+        .with_world(WorldState::fill(3, 3, 1, &Terrain::new("passable")))
+        .with_actors(actors)
+}
+
+fn names(state: &State) -> Vec<&str> {
+    state.actor_state.actors.iter().map(|a| a.name.as_str()).collect()
+}
+
+/// round_trip asserts that the delta between old and new turns old into new, returning the delta
+fn round_trip(old: &State, new: &State) -> Delta {
+    let delta = Delta::between(old, new);
+    let mut applied = old.clone();
+    delta.apply(&mut applied);
+    assert_eq!(&applied, new, "delta {:?}", delta);
+    delta
+}
+
+fn changes(delta: &Delta) -> Vec<(ActorChange, Option<usize>, &str)> {
+    delta.actors.iter().map(|u| (u.change, u.index, u.actor.name.as_str())).collect()
+}
+
+#[test]
+fn equal_states_have_empty_deltas() {
+    let state = actors(&["a", "b"]);
+    assert!(round_trip(&state, &state).is_empty());
+}
+
+#[test]
+fn adds_actors() {
+    let delta = round_trip(&actors(&["a"]), &actors(&["a", "b", "c"]));
+    assert_eq!(changes(&delta), [(ActorChange::Added, None, "b"), (ActorChange::Added, None, "c")]);
+}
+
+#[test]
+fn removes_actors() {
+    let delta = round_trip(&actors(&["a", "b", "c"]), &actors(&["a", "c"]));
+    assert_eq!(changes(&delta), [(ActorChange::Removed, Some(1), "b")]);
+    round_trip(&actors(&["a", "b"]), &actors(&[]));
+}
+
+#[test]
+fn reorders_actors() {
+    let old = actors(&["a", "b", "c"]);
+    let new = actors(&["c", "a", "b"]);
+    let delta = round_trip(&old, &new);
+    assert_eq!(changes(&delta), [
+        (ActorChange::Added, None, "a"),
+        (ActorChange::Added, None, "b"),
+        (ActorChange::Removed, Some(0), "a"),
+        (ActorChange::Removed, Some(1), "b")
+    ]);
+    round_trip(&old, &actors(&["b", "a", "c"]));
+    round_trip(&old, &actors(&["a", "c", "b"]));
+}
+
+#[test]
+fn matches_actors_of_the_same_name_in_order() {
+    round_trip(&actors(&["x", "y", "x"]), &actors(&["x", "x", "y"]));
+    round_trip(&actors(&["x", "x"]), &actors(&["x"]));
+    round_trip(&actors(&["x"]), &actors(&["y", "x", "x"]));
+}
+
+#[test]
+fn moves_and_updates_actors() {
+    let old = actors(&["a", "b"]);
+    let mut new = old.clone();
+    new.actor_state.actors[0].draw.position = (2, 1, 0);
+    new.actor_state.actors[1].attrs.push("status:burn".to_string());
+    let delta = round_trip(&old, &new);
+    assert_eq!(changes(&delta), [(ActorChange::Moved, Some(0), "a"), (ActorChange::Updated, Some(1), "b")]);
+}
+
+#[test]
+fn mixes_every_actor_change() {
+    let old = actors(&["a", "b", "c", "d"]);
+    let mut new = actors(&["a", "c", "e", "b"]);
+    new.actor_state.actors[1].draw.position = (1, 1, 0);
+    assert_eq!(names(&new), ["a", "c", "e", "b"]);
+    round_trip(&old, &new);
+}
+
+#[test]
+fn replaces_changed_terrain() {
+    let old = actors(&[]);
+    let mut new = old.clone();
+    new.world_state.set(1, 2, 0, Terrain::new("impassable"));
+    let delta = round_trip(&old, &new);
+    assert_eq!(delta.terrain.len(), 1);
+    assert_eq!(delta.terrain[0].0, new.world_state.index(1, 2, 0).unwrap());
+    assert!(delta.world.is_none());
+}
+
+#[test]
+fn replaces_resized_worlds() {
+    let old = actors(&[]);
+    let new = old.clone().with_world(WorldState::fill(2, 2, 2, &Terrain::new("terminal")));
+    let delta = round_trip(&old, &new);
+    assert!(delta.terrain.is_empty());
+    assert_eq!(delta.lower().unwrap().delta().changed(), DELTA_WORLD);
+}
+
+#[test]
+fn replaces_every_other_part() {
+    let old = actors(&["a"]);
+    let mut new = old.clone().with_menu(MenuState::new("pause").with_section(Section::new("options"))).with_attr("tick:2");
+    new.render_context = RenderContext::Battle;
+    new.world_state.attrs.push("weather:rain".to_string());
+    new.actor_state.attrs.push("turn:1".to_string());
+    let delta = round_trip(&old, &new);
+    let changed = delta.lower().unwrap().delta().changed();
+    assert_eq!(changed & (DELTA_MENU | DELTA_ACTOR_ATTRS | DELTA_WORLD), DELTA_MENU | DELTA_ACTOR_ATTRS);
+}
@@ -0,0 +1,33 @@
+#![cfg(feature = "client")]
+
+use render_api::v0::events::FrameEvents;
+use render_api::v0::handle::RenderError;
+use render_api::v0::RenderEvent;
+
+#[test]
+fn counts_events_per_kind() {
+    let mut events = FrameEvents::new();
+    events.count(RenderEvent::FrameSkipped, false, 1, None).unwrap();
+    events.count(RenderEvent::FrameSkipped, false, 2, None).unwrap();
+    events.count(RenderEvent::DisplayError, false, 3, None).unwrap();
+
+    assert_eq!(events.count_of(RenderEvent::FrameSkipped), 2);
+    assert_eq!(events.count_of(RenderEvent::DisplayError), 1);
+    assert_eq!(events.count_of(RenderEvent::DeviceError), 0);
+    assert_eq!(events.total(), 3);
+    assert_eq!(events.last_frame(), Some(3));
+}
+
+#[test]
+fn escalates_critical_events() {
+    let mut events = FrameEvents::new();
+    let error = events.count(RenderEvent::DeviceError, true, 7, Some(c"device lost")).unwrap_err();
+
+    assert_eq!(error, RenderError::Critical {
+        event: RenderEvent::DeviceError,
+        frame: 7,
+        message: Some("device lost".to_string())
+    });
+    assert_eq!(events.critical(), 1);
+    assert_eq!(events.count_of(RenderEvent::DeviceError), 1);
+}
@@ -0,0 +1,96 @@
+use render_api::extension::*;
+use render_api::negotiate::ClientMetadata;
+
+struct Deltas;
+
+impl Extension for Deltas {
+    const NAME: &'static str = "deltas";
+    const SYMBOLS: &'static [&'static str] = &["expr_push_delta"];
+    const ATTRIBUTES: &'static [&'static str] = &[];
+    type Metadata = ();
+}
+
+struct Weather;
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+struct WeatherMetadata {
+    max_particles: u32
+}
+
+impl Extension for Weather {
+    const NAME: &'static str = "weather";
+    const SYMBOLS: &'static [&'static str] = &[];
+    const ATTRIBUTES: &'static [&'static str] = &["weather", "wind"];
+    type Metadata = WeatherMetadata;
+}
+
+struct Lava;
+
+impl Extension for Lava {
+    const NAME: &'static str = "lava";
+    const SYMBOLS: &'static [&'static str] = &[];
+    const ATTRIBUTES: &'static [&'static str] = &[];
+    const KINDS: &'static [&'static str] = &["lava"];
+    type Metadata = ();
+}
+
+struct Unused;
+
+impl Extension for Unused {
+    const NAME: &'static str = "unused";
+    const SYMBOLS: &'static [&'static str] = &[];
+    const ATTRIBUTES: &'static [&'static str] = &[];
+    type Metadata = ();
+}
+
+fn registry() -> ExtensionRegistry {
+    let mut registry = ExtensionRegistry::new();
+    registry.register::<Deltas>(())
+        .register::<Weather>(WeatherMetadata { max_particles: 64 })
+        .support::<Unused>();
+    registry
+}
+
+fn names(extensions: &[ExtensionDescriptor]) -> Vec<&str> {
+    extensions.iter().map(|e| e.name).collect()
+}
+
+#[test]
+fn negotiates_intersection() {
+    let registry = registry();
+    let negotiated = registry.negotiate(&["deltas", "unused"], &["weather"]);
+    assert_eq!(names(&negotiated), ["deltas", "weather"]);
+}
+
+#[test]
+fn protocol_and_state_lists_are_not_interchangeable() {
+    let registry = registry();
+    // deltas adds symbols, so it must be a server extension; weather adds attributes
+    assert!(registry.negotiate(&["weather"], &["deltas"]).is_empty());
+}
+
+#[test]
+fn kinds_make_state_extensions() {
+    let lava = ExtensionDescriptor::of::<Lava>();
+    assert_eq!(lava.kinds, ["lava"]);
+    assert!(lava.is_state() && !lava.is_protocol());
+    assert!(!lava.accepted_by(&["lava"], &[]));
+    assert!(lava.accepted_by(&[], &["lava"]));
+}
+
+#[test]
+fn metadata_reaches_the_server() {
+    let mut metadata = ClientMetadata::new("test").with_extensions(registry());
+    assert_eq!(metadata.extensions().metadata::<Weather>(), Some(&WeatherMetadata { max_particles: 64 }));
+    let incoming = metadata.as_incoming();
+    unsafe {
+        let supported: Vec<&str> = incoming.supported_extensions().iter().map(|e| e.to_str().unwrap()).collect();
+        let enabled: Vec<&str> = incoming.enabled_extensions().iter().map(|e| e.to_str().unwrap()).collect();
+        assert_eq!(supported, ["deltas", "weather", "unused"]);
+        assert_eq!(enabled, ["deltas", "weather"]);
+        let weather = incoming.extension_metadata("weather").unwrap() as *const WeatherMetadata;
+        assert_eq!(*weather, WeatherMetadata { max_particles: 64 });
+        assert!(incoming.extension_metadata("unused").is_none());
+    }
+}
@@ -0,0 +1,27 @@
+//! header checks include/render_api.h matches the repr(C) types of the crate
+//!
+//! Run with UPDATE_HEADER=1 to regenerate the header after changing any of them (see cbindgen.toml).
+
+use std::path::Path;
+
+#[test]
+fn header_is_up_to_date() {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml")).expect("cbindgen.toml is valid");
+    let bindings = cbindgen::Builder::new()
+        .with_crate(crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("the header generates");
+    let mut generated = Vec::new();
+    bindings.write(&mut generated);
+    let generated = String::from_utf8(generated).expect("the header is utf-8");
+
+    let path = crate_dir.join("include").join("render_api.h");
+    if std::env::var_os("UPDATE_HEADER").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, &generated).unwrap();
+    }
+    let current = std::fs::read_to_string(&path).unwrap_or_default();
+    assert!(current == generated, "{} is out of date, rerun this test with UPDATE_HEADER=1", path.display());
+}
@@ -0,0 +1,39 @@
+#![cfg(feature = "client")]
+
+use std::path::{Path, PathBuf};
+use render_api::locate::{self, RENDER_ENV};
+
+// NOTE: the environment is shared by every test in this file, so the precedence is checked by a single test
+
+#[test]
+fn selects_servers_by_precedence() {
+    let explicit = Some(Path::new("explicit.so"));
+    let configured = Some(Path::new("configured.so"));
+
+    std::env::remove_var(RENDER_ENV);
+    assert_eq!(locate::select_server(explicit, configured), PathBuf::from("explicit.so"));
+    assert_eq!(locate::select_server(None, configured), PathBuf::from("configured.so"));
+    assert_eq!(locate::select_server(None, None), locate::default_server());
+    assert_eq!(locate::selected_server(None, None), None);
+
+    std::env::set_var(RENDER_ENV, "env.so");
+    assert_eq!(locate::select_server(explicit, configured), PathBuf::from("explicit.so"));
+    assert_eq!(locate::select_server(None, configured), PathBuf::from("env.so"));
+    assert_eq!(locate::select_server(None, None), PathBuf::from("env.so"));
+
+    std::env::set_var(RENDER_ENV, "");
+    assert_eq!(locate::select_server(None, configured), PathBuf::from("configured.so"), "an empty override is ignored");
+    std::env::remove_var(RENDER_ENV);
+}
+
+#[test]
+fn finds_server_libraries() {
+    let dir = std::env::temp_dir().join(format!("exploritron-locate-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("nested.so")).unwrap();
+    let library = dir.join(locate::default_server());
+    std::fs::write(&library, b"").unwrap();
+    std::fs::write(dir.join("notes.txt"), b"").unwrap();
+    let servers = locate::find_servers([&dir, &dir.join("missing")]);
+    let _ = std::fs::remove_dir_all(&dir);
+    assert_eq!(servers, vec![library]);
+}
@@ -0,0 +1,151 @@
+use std::ffi::CString;
+use render_api::v0::menu::{Menu, MenuError, MenuItem, OPEN_ATTR};
+use render_api::v0::owned::{MenuState, Section};
+
+/// pause is a menu of two top level sections, the first with a two level deep subsection
+fn pause() -> MenuState {
+    let video = Section::new("video")
+        .with_text("resolution")
+        .with_subsection(Section::new("advanced").with_text("vsync").with_text("shadows"));
+    MenuState::new("pause")
+        .with_section(Section::new("options").with_text("volume").with_subsection(video).with_text("back"))
+        .with_section(Section::new("quit").with_text("yes").with_text("no"))
+}
+
+fn text(text: &str) -> Option<MenuItem> {
+    Some(MenuItem::Text(text.to_string()))
+}
+
+#[test]
+fn decodes_and_encodes_items() {
+    assert_eq!("t:hello".parse(), Ok(MenuItem::Text("hello".to_string())));
+    assert_eq!("t:".parse(), Ok(MenuItem::Text(String::new())));
+    assert_eq!("t:s:1".parse(), Ok(MenuItem::Text("s:1".to_string())));
+    assert_eq!("s:12".parse(), Ok(MenuItem::Section(12)));
+    for item in [MenuItem::Text("a:b".to_string()), MenuItem::Section(3)] {
+        assert_eq!(item.to_string().parse(), Ok(item.clone()));
+        assert_eq!(MenuItem::from_c_str(&CString::new(item.to_string()).unwrap()), Ok(item));
+    }
+}
+
+#[test]
+fn rejects_invalid_items() {
+    for item in ["", "hello", "x:1", "s:", "s:-1", "s:one", "S:1"] {
+        assert_eq!(item.parse::<MenuItem>(), Err(MenuError::InvalidItem(item.to_string())), "{}", item);
+    }
+    assert!(MenuItem::from_c_str(c"t:\xff").is_err());
+}
+
+#[test]
+fn rejects_out_of_bounds_sections() {
+    let menu = MenuState::new("pause").with_section(Section::new("options").with_item(MenuItem::Section(1)));
+    assert_eq!(Menu::new(menu), Err(MenuError::SectionOutOfBounds { index: 1, subsections: 0 }));
+}
+
+#[test]
+fn wraps_item_selection() {
+    let mut menu = Menu::new(pause()).unwrap();
+    assert_eq!(menu.selected_item().cloned(), text("volume"));
+    menu.select_previous();
+    assert_eq!(menu.selected_item().cloned(), text("back"));
+    menu.select_next();
+    assert_eq!(menu.selected_item().cloned(), text("volume"));
+    menu.select_next();
+    assert_eq!(menu.selected_item(), Some(&MenuItem::Section(0)));
+}
+
+#[test]
+fn wraps_section_selection() {
+    let mut menu = Menu::new(pause()).unwrap();
+    menu.next_section();
+    assert_eq!(menu.current().unwrap().title, "quit");
+    menu.next_section();
+    assert_eq!(menu.current().unwrap().title, "options");
+    menu.previous_section();
+    assert_eq!(menu.current().unwrap().title, "quit");
+}
+
+#[test]
+fn descends_and_ascends() {
+    let mut menu = Menu::new(pause()).unwrap();
+    assert!(!menu.descend(), "volume is not a subsection");
+    assert!(!menu.ascend(), "options is a top level section");
+
+    menu.select_next();
+    assert!(menu.descend());
+    assert_eq!(menu.current().unwrap().title, "video");
+    assert!(menu.current().unwrap().is_open());
+    menu.select_next();
+    assert!(menu.descend());
+    assert_eq!(menu.current().unwrap().title, "advanced");
+    assert_eq!(menu.path(), &[0, 0]);
+
+    assert!(menu.ascend());
+    assert_eq!(menu.current().unwrap().title, "video");
+    assert!(!menu.current().unwrap().subsections[0].is_open());
+    assert!(menu.ascend());
+    assert_eq!(menu.current().unwrap().title, "options");
+    assert!(menu.path().is_empty());
+    assert!(!menu.current().unwrap().subsections[0].is_open());
+}
+
+#[test]
+fn switching_sections_leaves_subsections() {
+    let mut menu = Menu::new(pause()).unwrap();
+    menu.select_next();
+    menu.descend();
+    menu.next_section();
+    assert!(menu.path().is_empty());
+    assert!(menu.state().sections.iter().flat_map(|s| &s.subsections).all(|s| !s.is_open()));
+}
+
+#[test]
+fn recovers_the_path_from_open_attributes() {
+    let mut menu = Menu::new(pause()).unwrap();
+    menu.select_next();
+    menu.descend();
+    menu.select_next();
+    menu.descend();
+    let state = menu.into_state();
+    assert!(state.sections[0].subsections[0].attrs.contains(&OPEN_ATTR.to_string()));
+
+    let menu = Menu::new(state).unwrap();
+    assert_eq!(menu.path(), &[0, 0]);
+    assert_eq!(menu.current().unwrap().title, "advanced");
+}
+
+#[test]
+fn ignores_open_sections_that_are_not_selected() {
+    let mut state = pause();
+    state.sections[0].subsections[0].attrs.push(OPEN_ATTR.to_string());
+    let menu = Menu::new(state).unwrap();
+    assert!(menu.path().is_empty(), "the selected item of options is not the video subsection");
+    assert_eq!(menu.current().unwrap().title, "options");
+}
+
+#[test]
+fn clamps_selections() {
+    let mut state = pause().with_selected_section(5);
+    state.sections[0].selected_item = 9;
+    state.sections[0].subsections[0].selected_item = -3;
+    state.sections[1].selected_item = -1;
+    let menu = Menu::new(state).unwrap();
+    assert_eq!(menu.state().selected_section, 1);
+    assert_eq!(menu.state().sections[0].selected_item, 2);
+    assert_eq!(menu.state().sections[0].subsections[0].selected_item, 0);
+    assert_eq!(menu.state().sections[1].selected_item, 0);
+
+    let menu = Menu::new(MenuState::new("invisible").with_selected_section(2)).unwrap();
+    assert_eq!(menu.state().selected_section, -1);
+    assert!(menu.current().is_none());
+    assert!(menu.selected_item().is_none());
+}
+
+#[test]
+fn empty_sections_select_nothing() {
+    let mut menu = Menu::new(MenuState::new("pause").with_section(Section::new("empty"))).unwrap();
+    menu.select_next();
+    assert_eq!(menu.current().unwrap().selected_item, -1);
+    assert!(menu.selected_item().is_none());
+    assert!(!menu.descend());
+}
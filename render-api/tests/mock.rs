@@ -0,0 +1,201 @@
+#![cfg(feature = "client")]
+
+use std::ffi::c_void;
+use std::sync::Mutex;
+use render_api::alloc;
+use render_api::error::{ErrorCode, ExprError};
+use render_api::extension::{Extension, ExtensionRegistry};
+use render_api::negotiate::ClientMetadata;
+use render_api::RenderState;
+use render_api::v0::backend::RenderBackend;
+use render_api::v0::diff::{ActorChange, Deltas, StateDelta, DELTA_WORLD};
+use render_api::v0::handle::{RenderError, RenderServer};
+use render_api::v0::mock::{EntryPoint, MockBackend};
+use render_api::v0::owned::{Actor, ActorState, State, Terrain, WorldState};
+use render_api::v0::UserEvent;
+
+fn open(mock: &MockBackend) -> Result<RenderServer, RenderError> {
+    RenderServer::with_backend(mock.clone(), ClientMetadata::new("test"))
+}
+
+fn world() -> State {
+    State::default().with_world(WorldState::fill(2, 2, 1, &Terrain::new("passable")))
+}
+
+#[test]
+fn records_pushed_states() {
+    let mock = MockBackend::new();
+    let mut server = open(&mock).unwrap();
+    server.push(&State::default()).unwrap();
+    server.push(&world()).unwrap();
+
+    assert!(server.path().is_none());
+    assert_eq!(mock.pushed(), vec![State::default(), world()]);
+}
+
+#[test]
+fn rejects_pushes_after_disconnecting() {
+    let mock = MockBackend::new();
+    let mut server = open(&mock).unwrap();
+    server.disconnect().unwrap();
+    assert_eq!(server.push(&world()), Err(RenderError::Disconnected));
+    let world = world();
+    let mut lowered = world.lower().unwrap();
+    assert_eq!(server.push_state(lowered.state_mut()), Err(RenderError::Disconnected));
+    assert_eq!(server.on_user(|_, _| ()), Err(RenderError::Disconnected));
+    assert!(mock.pushed().is_empty());
+
+    server.reconnect().unwrap();
+    server.push(&world).unwrap();
+    assert_eq!(mock.pushed(), vec![world]);
+}
+
+type UserEvents = Mutex<Vec<(UserEvent, String)>>;
+
+unsafe extern "C" fn on_user_event(event: UserEvent, text: *mut i8, user_data: *mut c_void) {
+    let text = std::ffi::CStr::from_ptr(text).to_string_lossy().into_owned();
+    (*(user_data as *const UserEvents)).lock().unwrap().push((event, text));
+}
+
+#[test]
+fn delivers_injected_user_events() {
+    let mock = MockBackend::new();
+    let mut server = open(&mock).unwrap();
+    assert!(!mock.send_user_event(UserEvent::Input, "x"));
+
+    let events = UserEvents::default();
+    unsafe { server.user_callback(on_user_event, &events as *const UserEvents as *mut c_void).unwrap() };
+    assert!(mock.send_user_event(UserEvent::Command, "quit"));
+    assert_eq!(*events.lock().unwrap(), vec![(UserEvent::Command, "quit".to_string())]);
+    drop(server);
+}
+
+#[test]
+fn reports_configured_errors() {
+    let mock = MockBackend::new();
+    mock.fail(EntryPoint::Init, "no display");
+    assert_eq!(open(&mock).err(), Some(RenderError::Init("no display".to_string())));
+
+    mock.succeed(EntryPoint::Init);
+    let mut server = open(&mock).unwrap();
+    mock.fail_with(EntryPoint::PushState, ErrorCode::DeviceLost, false, "device lost");
+    let error = server.push(&world()).unwrap_err();
+    assert_eq!(error, RenderError::Server { code: ErrorCode::DeviceLost, recoverable: false, message: Some("device lost".to_string()) });
+    assert!(!error.recoverable());
+    assert!(mock.pushed().is_empty());
+
+    mock.fail_with(EntryPoint::PushState, ErrorCode::InvalidState, true, "no terrain");
+    assert_eq!(server.push(&world()), Err(RenderError::InvalidState("no terrain".to_string())));
+}
+
+#[test]
+fn reloads_and_reinitializes() {
+    let mock = MockBackend::new();
+    let mut server = open(&mock).unwrap();
+    server.reload().unwrap();
+    assert_eq!(mock.inits(), 1);
+    assert!(mock.is_connected());
+
+    mock.request_reinit(true);
+    server.reload().unwrap();
+    assert_eq!(mock.inits(), 2);
+
+    server.disconnect().unwrap();
+    assert!(!mock.is_connected());
+}
+
+#[test]
+fn releases_every_error() {
+    let mock = MockBackend::new();
+    mock.fail(EntryPoint::Init, "no display");
+    assert!(open(&mock).is_err());
+    mock.succeed(EntryPoint::Init);
+
+    let mut server = open(&mock).unwrap();
+    for entry in [EntryPoint::PushState, EntryPoint::UserCallback, EntryPoint::Disconnect, EntryPoint::Reconnect] {
+        mock.fail(entry, "failed");
+    }
+    assert!(server.push(&world()).is_err());
+    assert!(server.on_user(|_, _| ()).is_err());
+    assert!(server.disconnect().is_err());
+    assert!(server.reconnect().is_err());
+    drop(server);
+    assert_eq!(mock.outstanding(), 0);
+}
+
+#[test]
+fn ignores_foreign_and_released_strings() {
+    let mock = MockBackend::new();
+    mock.fail(EntryPoint::PushState, "failed");
+    let error = unsafe { mock.push_state(std::ptr::null_mut(), std::ptr::null_mut()) };
+    assert_eq!(mock.outstanding(), 1);
+
+    let foreign = alloc::string("foreign");
+    unsafe { mock.free(foreign) };
+    assert_eq!(mock.outstanding(), 1);
+    unsafe { alloc::free(foreign) };
+
+    unsafe { mock.free(error.message_ptr()) };
+    unsafe { mock.free(error.message_ptr()) };
+    assert_eq!(mock.outstanding(), 0);
+}
+
+/// DELTAS is every delta received by push_delta, as its changed flags, changed terrain and actor changes
+static DELTAS: Mutex<Vec<(u32, usize, Vec<ActorChange>)>> = Mutex::new(Vec::new());
+
+unsafe extern "C" fn push_delta(_render_state: *mut RenderState, delta: *mut StateDelta) -> ExprError {
+    let delta = &*delta;
+    let actors = delta.actors().iter().map(|a| a.change()).collect();
+    DELTAS.lock().unwrap().push((delta.changed(), delta.terrain().len(), actors));
+    ExprError::none()
+}
+
+#[test]
+fn pushes_deltas_after_the_first_state() {
+    let mock = MockBackend::new().with_symbol(Deltas::NAME, "expr_push_delta", push_delta as *const c_void);
+    let mut extensions = ExtensionRegistry::new();
+    extensions.register::<Deltas>(());
+    let mut server = RenderServer::with_backend(mock.clone(), ClientMetadata::new("test").with_extensions(extensions)).unwrap();
+    assert!(server.has_extension::<Deltas>());
+    server.validate_states(true);
+
+    let first = world();
+    let mut second = first.clone().with_actors(ActorState::new().with_actor(Actor::new("hero", "player")));
+    second.world_state.set(1, 0, 0, Terrain::new("impassable"));
+    let third = second.clone().with_world(WorldState::fill(1, 1, 1, &Terrain::new("passable")));
+    server.push(&first).unwrap();
+    server.push(&second).unwrap();
+
+    let mut invalid = third.clone();
+    invalid.actor_state.actors[0].draw.kind = "dragon".to_string();
+    assert!(matches!(server.push(&invalid), Err(RenderError::Violations(_))));
+    server.push(&third).unwrap();
+
+    assert_eq!(mock.pushed(), vec![first], "only the first state is pushed whole");
+    assert_eq!(*DELTAS.lock().unwrap(), vec![
+        (0, 1, vec![ActorChange::Added]),
+        (DELTA_WORLD, 0, vec![])
+    ]);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn records_user_events_before_forwarding_them() {
+    use std::sync::Arc;
+    use render_api::v0::record::{Event, Recorder, Session};
+
+    let path = std::env::temp_dir().join(format!("exploritron-mock-{}.json", std::process::id()));
+    let mock = MockBackend::new();
+    let mut server = open(&mock).unwrap();
+    let events = Arc::new(UserEvents::default());
+    let forwarded = events.clone();
+    server.on_user(move |event, text| forwarded.lock().unwrap().push((event, text.to_string()))).unwrap();
+    server.record(Recorder::create(&path).unwrap()).unwrap();
+
+    assert!(mock.send_user_event(UserEvent::Input, "up"));
+    server.stop_recording().unwrap().unwrap().finish().unwrap();
+    assert_eq!(*events.lock().unwrap(), vec![(UserEvent::Input, "up".to_string())]);
+    let recorded: Vec<Event> = Session::open(&path).unwrap().map(|entry| entry.unwrap().event).collect();
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(recorded, vec![Event::User { event: UserEvent::Input, text: "up".to_string() }]);
+}
@@ -0,0 +1,96 @@
+use render_api::v0::menu::MenuItem;
+use render_api::v0::owned::{Actor, ActorState, Drawable, LoweredState, MenuState, Section, State, Terrain, WorldState};
+use render_api::v0::state::{RenderContext, StateView};
+
+fn state() -> State {
+    let world = WorldState::fill(3, 2, 2, &Terrain::new("passable").with_attr("height:1"))
+        .with_terrain(1, 1, 0, Terrain::new("impassable"))
+        .with_terrain(2, 0, 1, Terrain::new("entrance").with_draw(Drawable::new("door").with_attr("open")))
+        .with_attr("weather:rain");
+    let actors = ActorState::new()
+        .with_actor(Actor::new("hero", "player").with_description("the protagonist").with_attr("hp:10"))
+        .with_actor(Actor::new("rat", "enemy"))
+        .with_attr("turn:3");
+    let menu = MenuState::new("pause")
+        .with_section(Section::new("options")
+            .with_description("settings")
+            .with_item(MenuItem::Text("volume".to_string()))
+            .with_item(MenuItem::Section(0))
+            .with_subsection(Section::new("video").with_text("resolution"))
+            .with_attr("columns:2"))
+        .with_section(Section::new("quit"))
+        .with_attr("theme:dark");
+    State::new(RenderContext::Battle)
+        .with_world(world)
+        .with_actors(actors)
+        .with_menu(menu)
+        .with_attr("tick:42")
+}
+
+/// round_trip reads lowered back into an owned state through its raw pointer
+fn round_trip(lowered: &mut LoweredState) -> State {
+    let view = unsafe { StateView::from_ptr(lowered.as_mut_ptr()) }.unwrap();
+    State::from_view(&view).unwrap()
+}
+
+#[test]
+fn lowered_states_round_trip() {
+    let state = state();
+    let mut lowered = state.lower().unwrap();
+    assert_eq!(round_trip(&mut lowered), state);
+}
+
+#[test]
+fn empty_states_round_trip() {
+    let state = State::default();
+    let mut lowered = state.lower().unwrap();
+    assert_eq!(round_trip(&mut lowered), state);
+}
+
+#[test]
+fn lowered_strings_survive_moving_the_lowered_state() {
+    let state = state();
+    let lowered = state.lower().unwrap();
+    // NOTE: every string lives in the arena, which moves along with the state pointing into it
+    let mut moved = vec![lowered];
+    let mut lowered = moved.pop().unwrap();
+    assert_eq!(round_trip(&mut lowered), state);
+}
+
+#[test]
+fn lowered_states_outlive_one_another() {
+    let state = state();
+    let other = State::default().with_attr("other");
+    let mut first = state.lower().unwrap();
+    let second = other.lower().unwrap();
+    drop(second);
+    assert_eq!(round_trip(&mut first), state);
+}
+
+#[test]
+fn lowering_fails_on_nul_bytes() {
+    assert!(State::default().with_attr("nul\0byte").lower().is_err());
+    let actors = ActorState::new().with_actor(Actor::new("nul\0", "player"));
+    assert!(State::default().with_actors(actors).lower().is_err());
+}
+
+#[test]
+fn new_worlds_have_single_tile_spans() {
+    let world = WorldState::new(2, 2, 1);
+    assert!(world.terrain().iter().all(|t| t.draw.span == (1, 1, 1)));
+    assert_eq!(world.get(1, 1, 0).unwrap().draw.position, (1, 1, 0));
+    assert_eq!(Terrain::default().draw, Drawable::new("impassable"));
+}
+
+#[test]
+fn new_worlds_are_valid() {
+    let world = State::default().with_world(WorldState::new(2, 3, 1));
+    let lowered = world.lower().unwrap();
+    assert_eq!(unsafe { lowered.state().validate(&[]) }, Ok(()));
+}
+
+#[test]
+#[should_panic(expected = "world dimensions overflow")]
+fn rejects_overflowing_worlds() {
+    WorldState::new(usize::MAX, 2, 1);
+}
@@ -0,0 +1,86 @@
+#![cfg(feature = "client")]
+
+use std::path::PathBuf;
+use render_api::negotiate::ClientMetadata;
+use render_api::v0::handle::RenderServer;
+use render_api::v0::mock::{EntryPoint, MockBackend};
+use render_api::v0::reload::HotReload;
+
+/// Library is a file standing in for a server library, removed on drop
+struct Library(PathBuf);
+
+impl Library {
+    fn new(name: &str) -> Library {
+        let path = std::env::temp_dir().join(format!("exploritron-reload-{}-{}.so", name, std::process::id()));
+        std::fs::write(&path, b"v1").unwrap();
+        Library(path)
+    }
+
+    /// rebuild rewrites the library, each rebuild changes its size so it is seen even within the mtime resolution
+    fn rebuild(&self, contents: &[u8]) {
+        std::fs::write(&self.0, contents).unwrap();
+    }
+}
+
+impl Drop for Library {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn watch(mock: &MockBackend) -> HotReload {
+    HotReload::new(RenderServer::with_backend(mock.clone(), ClientMetadata::new("test")).unwrap())
+}
+
+#[test]
+fn reloads_once_a_change_settles() {
+    let library = Library::new("settles");
+    let mock = MockBackend::new().with_path(&library.0);
+    let mut server = watch(&mock);
+    assert_eq!(server.poll(), Ok(false));
+
+    library.rebuild(b"v2 rebuilt");
+    assert_eq!(server.poll(), Ok(false));
+    assert_eq!(mock.reloads(), 0);
+    assert_eq!(server.poll(), Ok(true));
+    assert_eq!(mock.reloads(), 1);
+    assert!(mock.is_connected());
+
+    assert_eq!(server.poll(), Ok(false));
+    assert_eq!(server.poll(), Ok(false));
+    assert_eq!(mock.reloads(), 1);
+}
+
+#[test]
+fn waits_for_a_missing_library() {
+    let library = Library::new("missing");
+    let mock = MockBackend::new().with_path(&library.0);
+    let mut server = watch(&mock);
+    std::fs::remove_file(&library.0).unwrap();
+
+    assert_eq!(server.poll(), Ok(false));
+    assert_eq!(server.poll(), Ok(false));
+    assert_eq!(mock.reloads(), 0);
+}
+
+#[test]
+fn surfaces_failed_reloads() {
+    let library = Library::new("fails");
+    let mock = MockBackend::new().with_path(&library.0);
+    let mut server = watch(&mock);
+    mock.fail(EntryPoint::Reconnect, "bad build");
+
+    library.rebuild(b"v2 broken");
+    assert_eq!(server.poll(), Ok(false));
+    assert!(server.poll().is_err());
+    assert_eq!(mock.reloads(), 1);
+    // NOTE: the failed build is not retried until the library changes again
+    assert_eq!(server.poll(), Ok(false));
+
+    mock.succeed(EntryPoint::Reconnect);
+    library.rebuild(b"v3 fixed build");
+    assert_eq!(server.poll(), Ok(false));
+    assert_eq!(server.poll(), Ok(true));
+    assert!(mock.is_connected());
+    assert_eq!(mock.outstanding(), 0);
+}
@@ -0,0 +1,118 @@
+#![cfg(feature = "serde")]
+
+use std::path::PathBuf;
+use render_api::v0::menu::MenuItem;
+use render_api::v0::owned::{Actor, ActorState, MenuState, Section, State, Terrain, WorldState};
+use render_api::v0::record::{Event, Recorder, Session};
+use render_api::v0::serial::{self, Format, SerialError, BINARY_VERSION, MAGIC, MAX_FRAME};
+use render_api::v0::state::RenderContext;
+use render_api::v0::UserEvent;
+
+fn state() -> State {
+    let menu = MenuState::new("pause")
+        .with_section(Section::new("options").with_text("a:b").with_item(MenuItem::Section(0)).with_subsection(Section::new("video")));
+    State::new(RenderContext::BuildingTraversal)
+        .with_world(WorldState::fill(2, 3, 1, &Terrain::new("passable")).with_terrain(1, 2, 0, Terrain::new("entrance")))
+        .with_actors(ActorState::new().with_actor(Actor::new("hero", "player").with_description("ünïcode").with_attr("hp:10")))
+        .with_menu(menu)
+        .with_attr("tick:9")
+}
+
+/// temp is a path in the temporary directory unique to this process and test
+fn temp(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("exploritron-serial-{}-{}", std::process::id(), name))
+}
+
+#[test]
+fn json_round_trips() {
+    let json = serial::to_json(&state()).unwrap();
+    assert_eq!(serial::from_json::<State>(&json).unwrap(), state());
+    assert!(matches!(serial::from_json::<State>("{\"render_context\": 3}"), Err(SerialError::Json(_))));
+}
+
+#[test]
+fn binary_round_trips() {
+    let bytes = serial::to_binary(&state()).unwrap();
+    assert_eq!(&bytes[..4], MAGIC);
+    assert_eq!(bytes[4], BINARY_VERSION);
+    assert_eq!(serial::from_binary::<State>(&bytes).unwrap(), state());
+    for format in [Format::Json, Format::Binary] {
+        let bytes = serial::to_bytes(&state(), format).unwrap();
+        assert_eq!(serial::from_bytes::<State>(&bytes, format).unwrap(), state());
+    }
+}
+
+#[test]
+fn rejects_bad_magic() {
+    let mut bytes = serial::to_binary(&state()).unwrap();
+    bytes[0] = b'X';
+    assert!(matches!(serial::from_binary::<State>(&bytes), Err(SerialError::BadMagic)));
+    assert!(matches!(serial::from_binary::<State>(b"EX"), Err(SerialError::BadMagic)));
+    assert!(matches!(serial::from_binary::<State>(MAGIC), Err(SerialError::BadMagic)));
+}
+
+#[test]
+fn rejects_unsupported_versions() {
+    let mut bytes = serial::to_binary(&state()).unwrap();
+    bytes[4] = BINARY_VERSION + 1;
+    assert!(matches!(serial::from_binary::<State>(&bytes), Err(SerialError::UnsupportedVersion(v)) if v == BINARY_VERSION + 1));
+}
+
+#[test]
+fn rejects_truncated_values() {
+    let bytes = serial::to_binary(&state()).unwrap();
+    assert!(matches!(serial::from_binary::<State>(&bytes[..bytes.len() - 3]), Err(SerialError::Binary(_))));
+}
+
+#[test]
+fn saves_in_the_format_of_the_path() {
+    assert_eq!(Format::of("frame.json"), Format::Json);
+    assert_eq!(Format::of("frame.bin"), Format::Binary);
+    for name in ["saved.json", "saved.bin"] {
+        let path = temp(name);
+        serial::save(&path, &state()).unwrap();
+        let loaded = serial::load::<State>(&path);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(loaded.unwrap(), state());
+    }
+}
+
+/// record writes a binary session of a state and a user event, returning its bytes
+fn record(path: &PathBuf) -> Vec<u8> {
+    let recorder = Recorder::create(path).unwrap();
+    recorder.record_state(&state()).unwrap();
+    recorder.record_user(UserEvent::Input, "up").unwrap();
+    recorder.finish().unwrap();
+    std::fs::read(path).unwrap()
+}
+
+#[test]
+fn framed_sessions_round_trip() {
+    let path = temp("session.bin");
+    record(&path);
+    let events: Vec<Event> = Session::open(&path).unwrap().map(|entry| entry.unwrap().event).collect();
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(events, vec![Event::State(Box::new(state())), Event::User { event: UserEvent::Input, text: "up".to_string() }]);
+}
+
+#[test]
+fn rejects_truncated_frames() {
+    let path = temp("truncated.bin");
+    let bytes = record(&path);
+    let first = u32::from_le_bytes(bytes[..4].try_into().unwrap()) as usize;
+    std::fs::write(&path, &bytes[..4 + first + 6]).unwrap();
+    let entries: Vec<_> = Session::open(&path).unwrap().collect();
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(entries.len(), 2);
+    assert!(entries[0].is_ok());
+    assert!(matches!(&entries[1], Err(SerialError::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof));
+}
+
+#[test]
+fn rejects_oversized_frames_before_reading_them() {
+    let path = temp("oversized.bin");
+    std::fs::write(&path, (MAX_FRAME as u32 + 1).to_le_bytes()).unwrap();
+    let entry = Session::open(&path).unwrap().next();
+    let _ = std::fs::remove_file(&path);
+    assert!(matches!(entry, Some(Err(SerialError::FrameTooLarge(length))) if length == MAX_FRAME + 1));
+}
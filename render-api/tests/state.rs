@@ -0,0 +1,306 @@
+use std::ptr::null_mut;
+use render_api::v0::menu::{MenuError, MenuItem};
+use render_api::v0::owned::{Actor, ActorState, MenuState, Section, State, Terrain, WorldState};
+use render_api::v0::state::{self, RenderContext, StateView, ViewErrorKind, ViolationKind};
+
+// NOTE: the state types only expose getters, so bad states are built the way a C client would,
+//       through structs of the same layout as the ones in render_api.h
+
+#[repr(C)]
+struct RawDrawable {
+    kind: *mut i8,
+    pos: [i64; 3],
+    span: [i64; 3],
+    attrs: *mut *mut i8,
+    attrs_length: isize
+}
+
+#[repr(C)]
+struct RawActor {
+    name: *mut i8,
+    description: *mut i8,
+    draw: RawDrawable,
+    attrs: *mut *mut i8,
+    attrs_length: isize
+}
+
+#[repr(C)]
+struct RawTerrain {
+    draw: RawDrawable,
+    attrs: *mut *mut i8,
+    attrs_length: isize
+}
+
+#[repr(C)]
+struct RawWorldState {
+    terrain: *mut RawTerrain,
+    terrain_len_x: i64,
+    terrain_len_y: i64,
+    terrain_len_z: i64,
+    attrs: *mut *mut i8,
+    attrs_length: isize
+}
+
+#[repr(C)]
+struct RawActorState {
+    actors: *mut RawActor,
+    actors_length: isize,
+    attrs: *mut *mut i8,
+    attrs_length: isize
+}
+
+#[repr(C)]
+struct RawSection {
+    title: *mut i8,
+    description: *mut i8,
+    subsections: *mut RawSection,
+    subsections_length: isize,
+    items: *mut *mut i8,
+    items_length: isize,
+    selected_item: isize,
+    attrs: *mut *mut i8,
+    attrs_length: isize
+}
+
+#[repr(C)]
+struct RawMenuState {
+    kind: *mut i8,
+    sections: *mut RawSection,
+    sections_length: isize,
+    selected_section: isize,
+    attrs: *mut *mut i8,
+    attrs_length: isize
+}
+
+#[repr(C)]
+struct RawState {
+    render_context: RenderContext,
+    world_state: RawWorldState,
+    actor_state: RawActorState,
+    menu_state: RawMenuState,
+    attrs: *mut *mut i8,
+    attrs_length: isize
+}
+
+fn state() -> State {
+    let menu = MenuState::new("pause")
+        .with_section(Section::new("options")
+            .with_item(MenuItem::Section(0))
+            .with_subsection(Section::new("video").with_text("resolution")));
+    State::default()
+        .with_world(WorldState::fill(2, 2, 1, &Terrain::new("passable")))
+        .with_actors(ActorState::new().with_actor(Actor::new("hero", "player").with_attr("hp:10").with_attr("mp:5")))
+        .with_menu(menu)
+        .with_attr("tick:1")
+}
+
+/// validate validates state after breaking it with corrupt, returning the path and kind of every violation
+fn validate(state: State, kinds: &[&str], corrupt: impl FnOnce(&mut RawState)) -> Vec<(String, ViolationKind)> {
+    let mut lowered = state.lower().unwrap();
+    let raw = lowered.as_mut_ptr();
+    unsafe {
+        corrupt(&mut *(raw as *mut RawState));
+        (*raw).validate(kinds).err().unwrap_or_default().into_iter()
+            .map(|v| (v.path().to_string(), v.kind().clone()))
+            .collect()
+    }
+}
+
+/// check views lowered after breaking it with corrupt, returning the path and kind of the error
+fn check(corrupt: impl FnOnce(&mut RawState)) -> (String, ViewErrorKind) {
+    let state = state();
+    let mut lowered = state.lower().unwrap();
+    let raw = lowered.as_mut_ptr();
+    unsafe {
+        corrupt(&mut *(raw as *mut RawState));
+        let error = StateView::from_ptr(raw).err().expect("corrupt state was viewed");
+        (error.path().to_string(), error.kind())
+    }
+}
+
+#[test]
+fn views_valid_states() {
+    let state = state();
+    let mut lowered = state.lower().unwrap();
+    assert!(unsafe { StateView::from_ptr(lowered.as_mut_ptr()) }.is_ok());
+}
+
+#[test]
+fn rejects_null_states() {
+    let error = unsafe { StateView::from_ptr(null_mut::<state::State>()) }.err().unwrap();
+    assert_eq!(error.path(), "state");
+    assert_eq!(error.kind(), ViewErrorKind::Null);
+    assert_eq!(error.to_string(), "state is null");
+}
+
+#[test]
+fn rejects_negative_dimensions() {
+    assert_eq!(check(|s| s.world_state.terrain_len_y = -2),
+               ("world_state.terrain_len_y".to_string(), ViewErrorKind::NegativeLength(-2)));
+}
+
+#[test]
+fn rejects_overflowing_dimensions() {
+    assert_eq!(check(|s| {
+        s.world_state.terrain_len_x = i64::MAX;
+        s.world_state.terrain_len_y = 2;
+    }), ("world_state.terrain".to_string(), ViewErrorKind::TooLarge));
+}
+
+#[test]
+fn rejects_null_terrain() {
+    assert_eq!(check(|s| s.world_state.terrain = null_mut()),
+               ("world_state.terrain".to_string(), ViewErrorKind::Null));
+}
+
+#[test]
+fn rejects_null_terrain_kinds() {
+    assert_eq!(check(|s| unsafe { (*s.world_state.terrain.add(3)).draw.kind = null_mut() }),
+               ("world_state.terrain[3].draw.kind".to_string(), ViewErrorKind::Null));
+}
+
+#[test]
+fn rejects_negative_actor_lengths() {
+    assert_eq!(check(|s| s.actor_state.actors_length = -1),
+               ("actor_state.actors".to_string(), ViewErrorKind::NegativeLength(-1)));
+}
+
+#[test]
+fn rejects_null_actor_names() {
+    assert_eq!(check(|s| unsafe { (*s.actor_state.actors).name = null_mut() }),
+               ("actor_state.actors[0].name".to_string(), ViewErrorKind::Null));
+}
+
+#[test]
+fn rejects_null_attributes() {
+    assert_eq!(check(|s| unsafe { *(*s.actor_state.actors).attrs.add(1) = null_mut() }),
+               ("actor_state.actors[0].attrs[1]".to_string(), ViewErrorKind::Null));
+}
+
+#[test]
+fn rejects_null_menu_kinds() {
+    assert_eq!(check(|s| s.menu_state.kind = null_mut()), ("menu_state.kind".to_string(), ViewErrorKind::Null));
+}
+
+#[test]
+fn rejects_null_subsection_titles() {
+    assert_eq!(check(|s| unsafe { (*(*s.menu_state.sections).subsections).title = null_mut() }),
+               ("menu_state.sections[0].subsections[0].title".to_string(), ViewErrorKind::Null));
+}
+
+#[test]
+fn rejects_negative_state_attribute_lengths() {
+    let (path, kind) = check(|s| s.attrs_length = -4);
+    assert_eq!((path.as_str(), kind), ("attrs", ViewErrorKind::NegativeLength(-4)));
+}
+
+#[test]
+fn describes_errors() {
+    let state = state();
+    let mut lowered = state.lower().unwrap();
+    let raw = lowered.as_mut_ptr();
+    unsafe { (*(raw as *mut RawState)).world_state.terrain_len_z = -1 };
+    let error = unsafe { StateView::from_ptr(raw) }.err().unwrap();
+    assert_eq!(error.to_string(), "world_state.terrain_len_z has negative length -1");
+}
+
+#[test]
+fn validates_valid_states() {
+    assert_eq!(validate(state(), &[], |_| ()), vec![]);
+}
+
+#[test]
+fn reports_states_that_can_not_be_viewed() {
+    assert_eq!(validate(state(), &[], |s| s.world_state.terrain_len_x = -1), vec![
+        ("world_state.terrain_len_x".to_string(), ViolationKind::View(ViewErrorKind::NegativeLength(-1)))
+    ]);
+}
+
+#[test]
+fn reports_items_out_of_range() {
+    let mut state = state();
+    state.menu_state.sections[0].subsections[0].selected_item = -1;
+    state.menu_state.sections.push(Section::new("empty").with_selected_item(0));
+    assert_eq!(validate(state, &[], |_| ()), vec![
+        ("menu_state.sections[0].subsections[0].selected_item".to_string(),
+         ViolationKind::SelectionOutOfRange { selected: -1, length: 1 }),
+        ("menu_state.sections[1].selected_item".to_string(), ViolationKind::SelectionOutOfRange { selected: 0, length: 0 })
+    ]);
+}
+
+#[test]
+fn reports_sections_out_of_range() {
+    let mut state = state();
+    state.menu_state.selected_section = 1;
+    assert_eq!(validate(state, &[], |_| ()), vec![
+        ("menu_state.selected_section".to_string(), ViolationKind::SelectionOutOfRange { selected: 1, length: 1 })
+    ]);
+}
+
+#[test]
+fn reports_invalid_kinds() {
+    let mut state = state();
+    state.world_state.set(1, 0, 0, Terrain::new("lava"));
+    state.actor_state.actors.push(Actor::new("slime", "monster"));
+    assert_eq!(validate(state.clone(), &[], |_| ()), vec![
+        ("world_state.terrain[1].draw.kind".to_string(), ViolationKind::InvalidKind("lava".to_string())),
+        ("actor_state.actors[1].draw.kind".to_string(), ViolationKind::InvalidKind("monster".to_string()))
+    ]);
+    assert_eq!(validate(state, &["lava", "monster"], |_| ()), vec![], "kinds added by extensions are valid");
+}
+
+#[test]
+fn reports_invalid_items() {
+    let mut state = state();
+    state.menu_state.sections[0].items.push(MenuItem::Section(3));
+    let invalid = c"x:1";
+    assert_eq!(validate(state, &[], |s| unsafe {
+        *(*(*s.menu_state.sections).subsections).items = invalid.as_ptr() as *mut i8;
+    }), vec![
+        ("menu_state.sections[0].subsections[0].items[0]".to_string(),
+         ViolationKind::InvalidItem(MenuError::InvalidItem("x:1".to_string()))),
+        ("menu_state.sections[0].items[2]".to_string(),
+         ViolationKind::InvalidItem(MenuError::SectionOutOfBounds { index: 3, subsections: 1 }))
+    ]);
+}
+
+#[test]
+fn describes_violations() {
+    let mut state = state();
+    state.world_state.set(0, 0, 0, Terrain::new("lava"));
+    let mut lowered = state.lower().unwrap();
+    let violations = unsafe { lowered.state().validate(&[]) }.unwrap_err();
+    assert_eq!(violations[0].to_string(), "world_state.terrain[0].draw.kind is not a valid kind: \"lava\"");
+
+    let raw = lowered.as_mut_ptr();
+    unsafe { (*(raw as *mut RawState)).attrs_length = -1 };
+    let violations = unsafe { (*raw).validate(&[]) }.unwrap_err();
+    assert_eq!(violations.len(), 2);
+    assert_eq!(violations[1].to_string(), "attrs has negative length -1");
+}
+
+#[test]
+fn reports_every_violation() {
+    let mut state = state();
+    state.world_state.set(1, 1, 0, Terrain::new("lava"));
+    state.actor_state.actors.push(Actor::new("slime", "player"));
+    state.menu_state.sections[0].subsections[0].selected_item = 4;
+    assert_eq!(validate(state, &[], |s| unsafe {
+        (*s.world_state.terrain).draw.kind = null_mut();
+        (*s.actor_state.actors).name = null_mut();
+        (*s.actor_state.actors.add(1)).attrs_length = -2;
+        (*(*s.menu_state.sections).subsections).title = null_mut();
+        s.menu_state.kind = null_mut();
+        s.attrs = null_mut();
+    }), vec![
+        ("world_state.terrain[0].draw.kind".to_string(), ViolationKind::View(ViewErrorKind::Null)),
+        ("world_state.terrain[3].draw.kind".to_string(), ViolationKind::InvalidKind("lava".to_string())),
+        ("actor_state.actors[0].name".to_string(), ViolationKind::View(ViewErrorKind::Null)),
+        ("actor_state.actors[1].attrs".to_string(), ViolationKind::View(ViewErrorKind::NegativeLength(-2))),
+        ("menu_state.kind".to_string(), ViolationKind::View(ViewErrorKind::Null)),
+        ("menu_state.sections[0].subsections[0].title".to_string(), ViolationKind::View(ViewErrorKind::Null)),
+        ("menu_state.sections[0].subsections[0].selected_item".to_string(),
+         ViolationKind::SelectionOutOfRange { selected: 4, length: 1 }),
+        ("attrs".to_string(), ViolationKind::View(ViewErrorKind::Null))
+    ]);
+}
@@ -0,0 +1,106 @@
+#![cfg(feature = "client")]
+
+use std::time::Duration;
+use render_api::error::ErrorCode;
+use render_api::negotiate::ClientMetadata;
+use render_api::v0::handle::{RenderError, RenderServer};
+use render_api::v0::mock::{EntryPoint, MockBackend};
+use render_api::v0::owned::{State, Terrain, WorldState};
+use render_api::v0::thread::{RenderMessage, RenderThread};
+use render_api::v0::{RenderEvent, UserEvent};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn spawn(mock: &MockBackend) -> Result<RenderThread, RenderError> {
+    let mock = mock.clone();
+    RenderThread::spawn(move || RenderServer::with_backend(mock, ClientMetadata::new("test")))
+}
+
+fn world() -> State {
+    State::default().with_world(WorldState::fill(2, 2, 1, &Terrain::new("passable")))
+}
+
+#[test]
+fn returns_init_errors() {
+    let mock = MockBackend::new();
+    mock.fail(EntryPoint::Init, "no display");
+    assert_eq!(spawn(&mock).err(), Some(RenderError::Init("no display".to_string())));
+}
+
+#[test]
+fn pushes_on_the_render_thread() {
+    let mock = MockBackend::new();
+    let render = spawn(&mock).unwrap();
+    render.push(State::default()).unwrap();
+    render.push(world()).unwrap();
+    assert_eq!(render.stop(), vec![]);
+    assert_eq!(mock.last_pushed(), Some(world()));
+    assert!(!mock.is_connected());
+}
+
+#[test]
+fn delivers_user_events() {
+    let mock = MockBackend::new();
+    let render = spawn(&mock).unwrap();
+    assert!(mock.send_user_event(UserEvent::Command, "quit"));
+    assert_eq!(render.wait_message(TIMEOUT), Ok(Some(RenderMessage::User { event: UserEvent::Command, text: "quit".to_string() })));
+}
+
+#[test]
+fn delivers_frame_events() {
+    let mock = MockBackend::new();
+    let render = spawn(&mock).unwrap();
+    assert!(mock.send_frame_event(RenderEvent::DeviceError, true, 3, Some("device lost")));
+    assert_eq!(render.wait_message(TIMEOUT), Ok(Some(RenderMessage::Frame {
+        event: RenderEvent::DeviceError,
+        critical: true,
+        frame: 3,
+        message: Some("device lost".to_string())
+    })));
+}
+
+#[test]
+fn reports_failures_when_stopping() {
+    let mock = MockBackend::new();
+    let render = spawn(&mock).unwrap();
+    mock.fail(EntryPoint::Disconnect, "already gone");
+    assert_eq!(render.stop(), vec![RenderMessage::Failed(RenderError::Server {
+        code: ErrorCode::Unknown,
+        recoverable: true,
+        message: Some("already gone".to_string())
+    })]);
+}
+
+#[test]
+fn routes_events_to_their_own_thread() {
+    let (first_mock, second_mock) = (MockBackend::new(), MockBackend::new());
+    let first = spawn(&first_mock).unwrap();
+    let second = spawn(&second_mock).unwrap();
+    assert!(first_mock.send_user_event(UserEvent::Input, "first"));
+    assert!(second_mock.send_user_event(UserEvent::Input, "second"));
+
+    let user = |text: &str| Ok(Some(RenderMessage::User { event: UserEvent::Input, text: text.to_string() }));
+    assert_eq!(first.wait_message(TIMEOUT), user("first"));
+    assert_eq!(second.wait_message(TIMEOUT), user("second"));
+    assert_eq!(first.stop(), vec![]);
+    assert_eq!(second.stop(), vec![]);
+}
+
+#[test]
+fn recovers_lost_servers() {
+    let mock = MockBackend::new();
+    let render = spawn(&mock).unwrap();
+    mock.request_reinit(true);
+    mock.fail_once_with(EntryPoint::PushState, ErrorCode::DeviceLost, false, "device lost");
+    render.push(world()).unwrap();
+
+    assert_eq!(render.wait_message(TIMEOUT), Ok(Some(RenderMessage::Failed(RenderError::Server {
+        code: ErrorCode::DeviceLost,
+        recoverable: false,
+        message: Some("device lost".to_string())
+    }))));
+    assert_eq!(render.wait_message(TIMEOUT), Ok(Some(RenderMessage::Recovered)));
+    assert_eq!(render.stop(), vec![]);
+    assert_eq!(mock.inits(), 2);
+    assert_eq!(mock.pushed(), vec![world()], "the lost state is pushed again");
+}
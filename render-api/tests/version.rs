@@ -0,0 +1,50 @@
+use std::cmp::Ordering;
+use render_api::negotiate::*;
+
+#[test]
+fn compare_orders_numerically() {
+    assert_eq!(compare_versions("0", "0"), Ordering::Equal);
+    assert_eq!(compare_versions("2", "10"), Ordering::Less);
+    assert_eq!(compare_versions("1.10", "1.9"), Ordering::Greater);
+    assert_eq!(compare_versions("1", "1.0"), Ordering::Less);
+}
+
+#[test]
+fn negotiate_match() {
+    assert_eq!(negotiate_version(&["0"], &["0"]), Some("0"));
+}
+
+#[test]
+fn negotiate_mismatch() {
+    assert_eq!(negotiate_version(&["1"], &["0"]), None);
+    assert_eq!(negotiate_version::<&str, &str>(&[], &["0"]), None);
+}
+
+#[test]
+fn negotiate_downgrade() {
+    // the client is newer than the server, the highest common version wins
+    assert_eq!(negotiate_version(&["0", "1", "2"], &["1", "0"]), Some("1"));
+    // the server is newer than the client
+    assert_eq!(negotiate_version(&["0"], &["0", "1"]), Some("0"));
+}
+
+#[test]
+fn client_rejects_unlisted_server_version() {
+    assert_eq!(check_server_version(&["0", "1"], "1"), Ok("1"));
+    let error = check_server_version(&["0", "1"], "2").unwrap_err();
+    assert!(error.contains("server version 2"), "{}", error);
+}
+
+#[test]
+fn incoming_metadata_round_trips_versions() {
+    let mut metadata = ClientMetadata::with_versions("1.2.3", &["0", "1"]);
+    let incoming = metadata.as_incoming();
+    unsafe {
+        assert_eq!(incoming.client_version().unwrap().to_str(), Ok("1.2.3"));
+        let supported: Vec<&str> = incoming.supported_versions().iter()
+            .map(|v| v.to_str().unwrap())
+            .collect();
+        assert_eq!(supported, ["0", "1"]);
+        assert_eq!(negotiate_version(&supported, API_VERSIONS), Some("0"));
+    }
+}
@@ -0,0 +1,137 @@
+//! ascii draws a State as plain text
+//!
+//! The world is drawn top down: every (x, y) column shows the highest terrain in it with a kind,
+//! actors are drawn over the terrain and the menu, if any, is listed under the map.
+//! The map is cut to the size of the terminal, actors outside of it are listed under the map instead.
+
+use std::fmt::Write;
+use render_api::v0::menu::MenuItem;
+use render_api::v0::state::*;
+
+/// terrain_glyph is the glyph drawn for a terrain kind
+fn terrain_glyph(kind: &[u8]) -> char {
+    match kind {
+        b"terminal" => '.',
+        b"entrance" => '>',
+        b"no_entrance" => 'x',
+        b"passable" => ',',
+        b"impassable" => '#',
+        _ => '?'
+    }
+}
+
+/// actor_glyph is the glyph drawn for an actor kind
+fn actor_glyph(kind: &[u8]) -> char {
+    match kind {
+        b"player" => '@',
+        b"computer" => '&',
+        _ => '?'
+    }
+}
+
+fn context_name(context: RenderContext) -> &'static str {
+    match context {
+        RenderContext::WorldTraversal => "world",
+        RenderContext::BuildingTraversal => "building",
+        RenderContext::Battle => "battle"
+    }
+}
+
+/// DEFAULT_SIZE is the (columns, rows) assumed when the size of the terminal cannot be queried
+pub const DEFAULT_SIZE: (usize, usize) = (80, 24);
+
+/// terminal_size is the (columns, rows) of the terminal stdout is written to
+#[cfg(unix)]
+pub fn terminal_size() -> (usize, usize) {
+    let mut size = unsafe { std::mem::zeroed::<libc::winsize>() };
+    if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } != 0 || size.ws_col == 0 || size.ws_row == 0 {
+        return DEFAULT_SIZE;
+    }
+    (size.ws_col as usize, size.ws_row as usize)
+}
+
+/// terminal_size is the (columns, rows) of the terminal stdout is written to
+#[cfg(not(unix))]
+pub fn terminal_size() -> (usize, usize) {
+    DEFAULT_SIZE
+}
+
+/// render draws state into a text frame, the map is at most size (columns, rows)
+pub fn render(state: &StateView, size: (usize, usize)) -> String {
+    let mut frame = String::new();
+    let _ = writeln!(frame, "[{}]", context_name(state.render_context()));
+
+    // NOTE: the dimensions come from the client, only terrain that exists is drawn
+    let world = state.world();
+    let (width, height, depth) = match world.terrain().len() {
+        0 => (0, 0, 0),
+        _ => world.dimensions()
+    };
+    let (width, height) = (width.min(size.0), height.min(size.1));
+    let mut map = vec![' '; width.checked_mul(height).unwrap_or(0)];
+    for z in 0..depth {
+        for y in 0..height {
+            for x in 0..width {
+                let kind = world.get(x, y, z).map(|t| t.draw().kind().to_bytes()).unwrap_or_default();
+                if !kind.is_empty() {
+                    map[x + y * width] = terrain_glyph(kind);
+                }
+            }
+        }
+    }
+
+    let mut offscreen = Vec::new();
+    for actor in state.actors() {
+        let (x, y, _) = actor.draw().position();
+        let glyph = actor_glyph(actor.draw().kind().to_bytes());
+        if (0..width as i64).contains(&x) && (0..height as i64).contains(&y) {
+            map[x as usize + y as usize * width] = glyph;
+        } else {
+            offscreen.push(actor);
+        }
+    }
+
+    for row in map.chunks(width.max(1)) {
+        frame.extend(row);
+        frame.push('\n');
+    }
+    for actor in offscreen {
+        let (x, y, z) = actor.draw().position();
+        let _ = writeln!(frame, "{} {} at ({}, {}, {})", actor_glyph(actor.draw().kind().to_bytes()),
+                         actor.name().to_string_lossy(), x, y, z);
+    }
+
+    render_menu(&state.menu(), &mut frame);
+    frame
+}
+
+/// render_menu lists the top level sections of menu and the items of the section the user is in,
+/// nothing is drawn for an invisible menu
+fn render_menu(menu: &MenuView, frame: &mut String) {
+    if menu.kind().to_bytes() == b"invisible" {
+        return;
+    }
+    let _ = writeln!(frame, "== {} ==", menu.kind().to_string_lossy());
+    for (idx, section) in menu.sections().enumerate() {
+        let marker = if idx as isize == menu.selected_section() { '>' } else { ' ' };
+        let _ = writeln!(frame, "{} {}", marker, section.title().to_string_lossy());
+    }
+    let current = match menu.current() {
+        Some(current) => current,
+        None => return
+    };
+    let _ = writeln!(frame, "-- {} --", current.title().to_string_lossy());
+    if let Some(description) = current.description() {
+        let _ = writeln!(frame, "{}", description.to_string_lossy());
+    }
+    for (idx, item) in current.menu_items().enumerate() {
+        let marker = if idx as isize == current.selected_item() { '>' } else { ' ' };
+        let text = match item.and_then(|item| current.resolve(&item).map(|s| (item, s))) {
+            Ok((MenuItem::Text(text), _)) => text,
+            Ok((_, Some(subsection))) => format!("{}/", subsection.title().to_string_lossy()),
+            Ok((_, None)) => continue,
+            Err(e) => format!("<{}>", e)
+        };
+        let _ = writeln!(frame, "  {} {}", marker, text);
+    }
+}
@@ -0,0 +1,184 @@
+//! frames runs the frame loop of the stdout server
+//!
+//! expr_push_state only hands the newest state to the loop, the loop thread draws it once the next frame is due.
+//! A state replaced before it was drawn is reported as a skipped frame, a frame that cannot be written to stdout
+//! as a display error. Once the other end of stdout is gone every following push fails.
+
+use std::ffi::CString;
+use std::io::{Error, ErrorKind, Write};
+use std::ptr::null_mut;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use render_api::v0::owned;
+use render_api::v0::state::StateView;
+use render_api::v0::{RenderEvent, RenderResult};
+use crate::{ascii, FrameCallback, Registered};
+
+/// CLEAR moves the cursor home and clears the terminal before each frame
+const CLEAR: &str = "\x1b[H\x1b[2J";
+
+/// Frames is the state shared by the frame loop and the entry points
+///
+/// # Fields
+/// * frame is the index of the last frame pushed
+/// * pending is the newest frame pushed and not drawn yet, along with its index
+/// * frame_callback is the callback frame events are reported to
+/// * lost is why nothing can be displayed anymore, if stdout is gone
+/// * stop asks the loop thread to finish
+#[derive(Default)]
+struct Frames {
+    frame: i32,
+    pending: Option<(i32, owned::State)>,
+    frame_callback: Option<Registered<FrameCallback>>,
+    lost: Option<String>,
+    stop: bool
+}
+
+/// Shared is Frames along with the condition the loop thread waits on for a frame to be pushed
+#[derive(Default)]
+struct Shared {
+    frames: Mutex<Frames>,
+    pushed: Condvar
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Frames> {
+        self.frames.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// FrameLoop draws pushed states on its own thread, at most once per interval
+pub(crate) struct FrameLoop {
+    shared: Arc<Shared>,
+    interval: Duration,
+    thread: Option<JoinHandle<()>>
+}
+
+impl FrameLoop {
+    /// new is a loop drawing at most once per interval, it draws nothing until it is started
+    pub(crate) fn new(interval: Duration) -> FrameLoop {
+        FrameLoop { shared: Arc::default(), interval, thread: None }
+    }
+
+    /// start starts the loop thread if it is not running
+    pub(crate) fn start(&mut self) -> std::io::Result<()> {
+        if self.thread.is_some() {
+            return Ok(());
+        }
+        let shared = self.shared.clone();
+        let interval = self.interval;
+        self.thread = Some(std::thread::Builder::new()
+            .name("render-stdout frames".to_string())
+            .spawn(move || run(&shared, interval))?);
+        Ok(())
+    }
+
+    /// stop stops the loop thread, a frame that was not drawn yet is dropped
+    pub(crate) fn stop(&mut self) {
+        let thread = match self.thread.take() {
+            Some(thread) => thread,
+            None => return
+        };
+        self.shared.lock().stop = true;
+        self.shared.pushed.notify_all();
+        let _ = thread.join();
+        let mut frames = self.shared.lock();
+        frames.stop = false;
+        frames.pending = None;
+    }
+
+    /// set_callback sets the callback frame events are reported to
+    pub(crate) fn set_callback(&self, callback: Registered<FrameCallback>) {
+        self.shared.lock().frame_callback = Some(callback);
+    }
+
+    /// push hands state to the loop to be drawn as the next frame
+    ///
+    /// # Notes
+    /// Fails with the reason once nothing can be displayed anymore
+    pub(crate) fn push(&self, state: owned::State) -> Result<(), String> {
+        let mut frames = self.shared.lock();
+        if let Some(lost) = &frames.lost {
+            return Err(lost.clone());
+        }
+        frames.frame = frames.frame.wrapping_add(1);
+        let frame = frames.frame;
+        let skipped = frames.pending.replace((frame, state));
+        let callback = frames.frame_callback;
+        drop(frames);
+        self.shared.pushed.notify_all();
+        // NOTE: reported unlocked, so the callback may push from within
+        if let Some((frame, state)) = skipped {
+            report(callback, RenderEvent::FrameSkipped, false, frame, &state, "frame was pushed before the previous one was drawn");
+        }
+        Ok(())
+    }
+}
+
+impl Drop for FrameLoop {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// run draws every pending frame once it is due, until it is asked to stop or stdout is gone
+fn run(shared: &Shared, interval: Duration) {
+    let mut last_drawn: Option<Instant> = None;
+    let mut frames = shared.lock();
+    while !frames.stop {
+        let due = last_drawn.map(|last| last + interval).filter(|due| *due > Instant::now());
+        match (&frames.pending, due) {
+            (None, _) => {
+                frames = shared.pushed.wait(frames).unwrap_or_else(|e| e.into_inner());
+                continue;
+            }
+            (Some(_), Some(due)) => {
+                let timeout = due.saturating_duration_since(Instant::now());
+                frames = shared.pushed.wait_timeout(frames, timeout).unwrap_or_else(|e| e.into_inner()).0;
+                continue;
+            }
+            (Some(_), None) => ()
+        }
+        let (frame, state) = frames.pending.take().unwrap();
+        let callback = frames.frame_callback;
+        drop(frames);
+
+        last_drawn = Some(Instant::now());
+        let drawn = draw(&state);
+        if let Err(e) = &drawn {
+            // NOTE: nothing will ever be displayed again once the other end of stdout is gone
+            let critical = e.kind() == ErrorKind::BrokenPipe;
+            report(callback, RenderEvent::DisplayError, critical, frame, &state, e);
+            if critical {
+                shared.lock().lost = Some(e.to_string());
+                return;
+            }
+        }
+        frames = shared.lock();
+    }
+}
+
+/// draw writes state to stdout as a single frame
+fn draw(state: &owned::State) -> std::io::Result<()> {
+    let lowered = state.lower().map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    let view = unsafe { StateView::new(lowered.state()) }.map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    let frame = ascii::render(&view, ascii::terminal_size());
+    let mut stdout = std::io::stdout().lock();
+    stdout.write_all(CLEAR.as_bytes())
+        .and_then(|_| stdout.write_all(frame.as_bytes()))
+        .and_then(|_| stdout.flush())
+}
+
+/// report calls callback, if any, with an event on frame, which was drawing state
+fn report(callback: Option<Registered<FrameCallback>>, event: RenderEvent, critical: bool, frame: i32,
+          state: &owned::State, message: impl ToString) {
+    if let Some(Registered { callback, user_data }) = callback {
+        // NOTE: message and game_state are only valid for the duration of the callback
+        let message = CString::new(message.to_string().replace('\0', " ")).unwrap_or_default();
+        let message = message.as_ptr() as *mut i8;
+        let mut lowered = state.lower().ok();
+        let game_state = lowered.as_mut().map_or(null_mut(), |l| l.as_mut_ptr());
+        unsafe { callback(RenderResult::new(event, critical, message), frame, game_state, message, user_data) };
+    }
+}
@@ -0,0 +1,262 @@
+//! input captures keystrokes from the terminal and turns them into user events
+//!
+//! While input is captured the terminal is in raw mode, keys are reported as UserEvent::Input
+//! with the key name (e.g. "a", "up", "enter", "escape", "ctrl+c").
+//! Typing : opens a command line, enter submits it as UserEvent::Command and escape abandons it.
+
+use std::ffi::CString;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use render_api::v0::UserEvent;
+use crate::{Registered, UserCallback};
+
+/// POLL_MS is how long the input thread waits for a key before checking whether it should stop
+const POLL_MS: i32 = 100;
+
+/// Event is a decoded piece of terminal input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    Input(String),
+    Command(String),
+    /// the command line changed, None means it was closed
+    Prompt(Option<String>)
+}
+
+/// Decoder turns raw terminal bytes into events
+#[derive(Default)]
+pub struct Decoder {
+    command: Option<String>
+}
+
+impl Decoder {
+    /// feed decodes a chunk of bytes read from the terminal
+    ///
+    /// # Notes
+    /// An escape byte at the end of a chunk is treated as the escape key,
+    /// escape sequences are expected to arrive in a single read
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Event> {
+        let mut events = Vec::new();
+        let mut idx = 0;
+        while idx < bytes.len() {
+            let (key, used) = decode_key(&bytes[idx..]);
+            idx += used;
+            self.key(key, &mut events);
+        }
+        events
+    }
+
+    fn key(&mut self, key: String, events: &mut Vec<Event>) {
+        let command = match &mut self.command {
+            Some(command) => command,
+            None if key == ":" => {
+                self.command = Some(String::new());
+                events.push(Event::Prompt(Some(String::new())));
+                return;
+            }
+            None => {
+                events.push(Event::Input(key));
+                return;
+            }
+        };
+        match key.as_str() {
+            "enter" => {
+                events.push(Event::Command(std::mem::take(command)));
+                self.command = None;
+                events.push(Event::Prompt(None));
+                return;
+            }
+            "escape" | "ctrl+c" => {
+                self.command = None;
+                events.push(Event::Prompt(None));
+                return;
+            }
+            "backspace" => {
+                command.pop();
+            }
+            "space" => command.push(' '),
+            key if key.chars().count() == 1 => command.push_str(key),
+            // NOTE: other named keys (arrows, tab, ...) do nothing on the command line
+            _ => return
+        }
+        events.push(Event::Prompt(Some(command.clone())));
+    }
+}
+
+/// decode_key decodes the key at the start of bytes, returning its name and the number of bytes used
+fn decode_key(bytes: &[u8]) -> (String, usize) {
+    match bytes {
+        [0x1b, b'[', _, ..] => decode_csi(bytes),
+        [0x1b, b'O', code, ..] => match cursor_key(*code) {
+            Some(name) => (name.to_string(), 3),
+            None => ("escape".to_string(), 1)
+        },
+        [0x1b, ..] => ("escape".to_string(), 1),
+        [b'\r', ..] | [b'\n', ..] => ("enter".to_string(), 1),
+        [b'\t', ..] => ("tab".to_string(), 1),
+        [0x7f, ..] | [0x08, ..] => ("backspace".to_string(), 1),
+        [b' ', ..] => ("space".to_string(), 1),
+        [byte @ 0x01..=0x1a, ..] => (format!("ctrl+{}", (b'a' + byte - 1) as char), 1),
+        [byte, ..] if byte.is_ascii() => ((*byte as char).to_string(), 1),
+        _ => {
+            // NOTE: a multibyte utf-8 character, take as many bytes as the leading byte claims
+            let length = match bytes[0] {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                _ => 4
+            }.min(bytes.len());
+            (String::from_utf8_lossy(&bytes[..length]).into_owned(), length)
+        }
+    }
+}
+
+/// decode_csi decodes a control sequence, ESC [ followed by parameters and ending at the first final byte
+///
+/// Parameters are the key code and an optional modifier, e.g. ESC [ 3 ~ is delete and ESC [ 1 ; 5 A is ctrl+up
+fn decode_csi(bytes: &[u8]) -> (String, usize) {
+    let end = match bytes[2..].iter().position(|b| (0x40..=0x7e).contains(b)) {
+        Some(end) => end + 2,
+        None => return ("escape".to_string(), 1)
+    };
+    let parameters = String::from_utf8_lossy(&bytes[2..end]);
+    let mut parameters = parameters.split(';');
+    let code = parameters.next().unwrap_or_default();
+    let modifier = parameters.next().and_then(|m| m.parse::<u8>().ok()).unwrap_or(1);
+    let name = match (bytes[end], code) {
+        (b'~', "2") => "insert",
+        (b'~', "3") => "delete",
+        (b'~', "5") => "page_up",
+        (b'~', "6") => "page_down",
+        (b'~', _) => "unknown",
+        (last, _) => cursor_key(last).unwrap_or("unknown")
+    };
+    // NOTE: the modifier is one more than a set of flags: shift 1, alt 2 and ctrl 4
+    let flags = modifier.saturating_sub(1);
+    let mut key = String::new();
+    for (flag, prefix) in [(4, "ctrl+"), (2, "alt+"), (1, "shift+")] {
+        if flags & flag != 0 {
+            key.push_str(prefix);
+        }
+    }
+    key.push_str(name);
+    (key, end + 1)
+}
+
+/// cursor_key is the name of the cursor key a control sequence ending in code stands for
+fn cursor_key(code: u8) -> Option<&'static str> {
+    match code {
+        b'A' => Some("up"),
+        b'B' => Some("down"),
+        b'C' => Some("right"),
+        b'D' => Some("left"),
+        b'H' => Some("home"),
+        b'F' => Some("end"),
+        _ => None
+    }
+}
+
+/// RawMode puts the terminal into raw mode, the previous mode is restored on drop
+struct RawMode {
+    original: libc::termios
+}
+
+impl RawMode {
+    fn enable() -> std::io::Result<RawMode> {
+        unsafe {
+            let mut original = std::mem::zeroed::<libc::termios>();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            let mut raw = original;
+            libc::cfmakeraw(&mut raw);
+            // NOTE: keep output processing so frames written with \n still return the carriage
+            raw.c_oflag |= libc::OPOST;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(RawMode { original })
+        }
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+/// Input is a running input capture, stopping it restores the terminal
+pub(crate) struct Input {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+    _raw: RawMode
+}
+
+impl Input {
+    /// start puts the terminal in raw mode and reports decoded input to the current callback
+    ///
+    /// callback is read for every event, so it can be replaced while input is captured
+    pub(crate) fn start(callback: Arc<Mutex<Option<Registered<UserCallback>>>>) -> std::io::Result<Input> {
+        let raw = RawMode::enable()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let thread = std::thread::Builder::new()
+            .name("render-stdout input".to_string())
+            .spawn(move || read_loop(&thread_stop, &callback))?;
+        Ok(Input { stop, thread: Some(thread), _raw: raw })
+    }
+}
+
+impl Drop for Input {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn read_loop(stop: &AtomicBool, callback: &Mutex<Option<Registered<UserCallback>>>) {
+    let mut decoder = Decoder::default();
+    let mut buffer = [0u8; 64];
+    while !stop.load(Ordering::Relaxed) {
+        let mut fd = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+        if unsafe { libc::poll(&mut fd, 1, POLL_MS) } <= 0 {
+            continue;
+        }
+        let read = unsafe { libc::read(libc::STDIN_FILENO, buffer.as_mut_ptr().cast(), buffer.len()) };
+        if read <= 0 {
+            // NOTE: stdin was closed, there is no more input to capture
+            return;
+        }
+        for event in decoder.feed(&buffer[..read as usize]) {
+            let (event, text) = match event {
+                Event::Input(key) => (UserEvent::Input, key),
+                Event::Command(command) => (UserEvent::Command, command),
+                Event::Prompt(prompt) => {
+                    show_prompt(prompt.as_deref());
+                    continue;
+                }
+            };
+            // NOTE: the callback is called locked, so it cannot be called once expr_user_callback replaced it
+            let registered = callback.lock().unwrap_or_else(|e| e.into_inner());
+            if let (Some(Registered { callback, user_data }), Ok(text)) = (*registered, CString::new(text)) {
+                // NOTE: text is only valid for the duration of the callback
+                unsafe { callback(event, text.as_ptr() as *mut i8, user_data) };
+            }
+        }
+    }
+}
+
+/// show_prompt draws the command line on the last terminal line, None clears it
+fn show_prompt(prompt: Option<&str>) {
+    let mut stdout = std::io::stdout().lock();
+    let _ = match prompt {
+        Some(prompt) => write!(stdout, "\r\x1b[K:{}", prompt),
+        None => write!(stdout, "\r\x1b[K")
+    };
+    let _ = stdout.flush();
+}
@@ -1,36 +1,248 @@
-use std::mem::MaybeUninit;
+pub mod ascii;
+mod frames;
+#[cfg(unix)]
+pub mod input;
+
+use std::ffi::{c_void, CStr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use render_api::{*, v0::*, v0::state::*};
-use std::ptr::null_mut;
+use render_api::alloc;
+use render_api::error::{ErrorCode, ExprError};
+use render_api::negotiate::negotiate_version;
+
+/// VERSIONS is every protocol version this server speaks
+const VERSIONS: &[&CStr] = &[c"0"];
+
+const NO_COMMON_VERSION: &CStr = c"render-stdout supports none of the client's versions";
+
+/// FPS_ENV is the environment variable overriding the target frame rate
+const FPS_ENV: &str = "RENDER_STDOUT_FPS";
+
+/// DEFAULT_FPS is the target frame rate when FPS_ENV is not set
+const DEFAULT_FPS: u32 = 30;
+
+/// DISCONNECTED lists the address of every ServerState disconnected and not reconnected since
+///
+/// # Notes
+/// The client abandons a disconnected state when it reruns expr_init (see render_api::v0::client),
+/// so expr_init frees every state listed here
+static DISCONNECTED: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+
+/// disconnected locks DISCONNECTED
+fn disconnected() -> std::sync::MutexGuard<'static, Vec<usize>> {
+    DISCONNECTED.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// FrameCallback is the callback registered through expr_frame_callback
+type FrameCallback = unsafe extern "C" fn(RenderResult, i32, *mut State, *mut i8, *mut c_void);
+
+/// UserCallback is the callback registered through expr_user_callback
+type UserCallback = unsafe extern "C" fn(UserEvent, *mut i8, *mut c_void);
 
+/// Registered is a callback and the user data it was registered with
+#[derive(Clone, Copy)]
+struct Registered<C> {
+    callback: C,
+    user_data: *mut c_void
+}
+
+// NOTE: user data is only handed back to the callback it was registered with, it is never dereferenced here
+unsafe impl<C: Send> Send for Registered<C> {}
+
+/// ServerState is the persistent state of the stdout server, handed to the client as RenderState
+///
+/// # Fields
+/// * frames is the frame loop drawing pushed states, running while connected
+/// * user_callback is the callback keystrokes and commands are reported to
+/// * input is the running input capture, only present while connected and a user callback is set
+/// * allocations counts the error messages handed to the client and not yet released through expr_free
+///
+/// # Notes
+/// A disconnected state is kept for expr_reconnect (and hot reloads), it is freed by the next expr_init
+/// unless it was reconnected by then (see DISCONNECTED)
+struct ServerState {
+    allocations: alloc::Allocations,
+    frames: frames::FrameLoop,
+    user_callback: Arc<Mutex<Option<Registered<UserCallback>>>>,
+    #[cfg(unix)]
+    input: Option<input::Input>
+}
+
+impl ServerState {
+    /// from_render_state recovers the state handed out by expr_init
+    ///
+    /// # Safety
+    /// render_state must be null or a pointer returned by expr_init that has not been freed (see ServerState)
+    unsafe fn from_render_state<'a>(render_state: *mut RenderState) -> Result<&'a mut ServerState, ExprError> {
+        (render_state as *mut ServerState).as_mut()
+            .ok_or_else(|| ExprError::new(ErrorCode::InvalidRenderState, alloc::string("render state is null")))
+    }
+
+    /// error is a recoverable error with code, its message is released by the client through expr_free
+    fn error(&self, code: ErrorCode, message: impl ToString) -> ExprError {
+        ExprError::new(code, self.allocations.string(message))
+    }
+
+    /// fatal is an unrecoverable error with code, its message is released by the client through expr_free
+    fn fatal(&self, code: ErrorCode, message: impl ToString) -> ExprError {
+        ExprError::fatal(code, self.allocations.string(message))
+    }
+
+    /// input_error is the error for input that could not be captured, frames are still rendered without it
+    fn input_error(&self, e: std::io::Error) -> ExprError {
+        self.error(ErrorCode::Unsupported, format!("input cannot be captured: {}", e))
+    }
+
+    /// start_input starts capturing input if a user callback is set and input is not already captured
+    fn start_input(&mut self) -> Result<(), std::io::Error> {
+        #[cfg(unix)]
+        if self.input.is_none() && self.user_callback.lock().map(|c| c.is_some()).unwrap_or(false) {
+            self.input = Some(input::Input::start(self.user_callback.clone())?);
+        }
+        Ok(())
+    }
+
+    /// stop_input stops capturing input and restores the terminal
+    fn stop_input(&mut self) {
+        #[cfg(unix)]
+        drop(self.input.take());
+    }
+}
+
+/// # Safety
+/// client_metadata must point to valid memory for the duration of the call
 #[no_mangle]
-pub extern "C" fn expr_init() -> InitResult {
-    unsafe {
-        // TODO: this is undefined behavior, fix it once we actually start implementing logic
-        MaybeUninit::uninit().assume_init()
+pub unsafe extern "C" fn expr_init(client_metadata: IncomingMetadata) -> InitResult {
+    for state in disconnected().drain(..) {
+        drop(Box::from_raw(state as *mut ServerState));
     }
+    let client: Vec<&str> = client_metadata.supported_versions().iter()
+        .filter_map(|v| v.to_str().ok())
+        .collect();
+    let server: Vec<&str> = VERSIONS.iter().filter_map(|v| v.to_str().ok()).collect();
+    let version = match negotiate_version(&client, &server) {
+        Some(version) => VERSIONS[server.iter().position(|v| *v == version).unwrap()],
+        None => {
+            let message = alloc::string(NO_COMMON_VERSION.to_string_lossy());
+            return InitResult::failed(ExprError::fatal(ErrorCode::UnsupportedVersion, message));
+        }
+    };
+    let fps = std::env::var(FPS_ENV).ok()
+        .and_then(|fps| fps.parse::<u32>().ok())
+        .filter(|fps| *fps > 0)
+        .unwrap_or(DEFAULT_FPS);
+    let mut server = ServerState {
+        allocations: alloc::Allocations::new(),
+        frames: frames::FrameLoop::new(Duration::from_secs(1) / fps),
+        user_callback: Arc::default(),
+        #[cfg(unix)]
+        input: None
+    };
+    if let Err(e) = server.frames.start() {
+        return InitResult::failed(ExprError::fatal(ErrorCode::Unknown, alloc::string(format!("frame loop cannot be started: {}", e))));
+    }
+    let state = Box::into_raw(Box::new(server));
+    InitResult::new(version.as_ptr() as *mut i8, state as *mut RenderState)
 }
 
+/// # Safety
+/// render_state must be a pointer returned by expr_init that has not been disconnected
+///
+/// # Notes
+/// Frames and input are stopped, the render state is kept until it is reconnected or expr_init is rerun
 #[no_mangle]
-pub extern "C" fn expr_disconnect(render_state: *mut RenderState) -> *mut i8 {
-    null_mut()
+pub unsafe extern "C" fn expr_disconnect(render_state: *mut RenderState) -> ExprError {
+    match ServerState::from_render_state(render_state) {
+        Ok(server) => {
+            server.stop_input();
+            server.frames.stop();
+            disconnected().push(render_state as usize);
+            ExprError::none()
+        }
+        Err(e) => e
+    }
 }
 
+/// # Safety
+/// render_state must be a pointer returned by expr_init that was disconnected, and expr_init must not have
+/// been rerun since
 #[no_mangle]
-pub extern "C" fn expr_reconnect(render_state: *mut RenderState) -> *mut i8 {
-    null_mut()
+pub unsafe extern "C" fn expr_reconnect(render_state: *mut RenderState) -> ExprError {
+    let server = match ServerState::from_render_state(render_state) {
+        Ok(server) => server,
+        Err(e) => return e
+    };
+    disconnected().retain(|state| *state != render_state as usize);
+    match server.frames.start() {
+        Ok(()) => server.start_input().map_or_else(|e| server.input_error(e), |_| ExprError::none()),
+        Err(e) => server.fatal(ErrorCode::Unknown, format!("frame loop cannot be started: {}", e))
+    }
 }
 
+/// # Safety
+/// * render_state must be a pointer returned by expr_init that has not been disconnected
+/// * game_state must be null or valid for the duration of the call
+///
+/// # Notes
+/// The state is copied and drawn by the frame loop once the next frame is due,
+/// a state replaced by another push before that is reported as FrameSkipped
 #[no_mangle]
-pub extern "C" fn expr_push_state(render_state: *mut RenderState, game_state: *mut State) -> *mut i8 {
-    null_mut()
+pub unsafe extern "C" fn expr_push_state(render_state: *mut RenderState, game_state: *mut State) -> ExprError {
+    let server = match ServerState::from_render_state(render_state) {
+        Ok(server) => server,
+        Err(e) => return e
+    };
+    let state = StateView::from_ptr(game_state)
+        .map_err(|e| e.to_string())
+        .and_then(|view| owned::State::from_view(&view).map_err(|e| e.to_string()));
+    let state = match state {
+        Ok(state) => state,
+        Err(e) => return server.error(ErrorCode::InvalidState, format!("invalid game state: {}", e))
+    };
+    match server.frames.push(state) {
+        Ok(()) => ExprError::none(),
+        Err(lost) => server.fatal(ErrorCode::DisplayError, lost)
+    }
 }
 
+/// # Safety
+/// render_state must be a pointer returned by expr_init that has not been disconnected
 #[no_mangle]
-pub extern "C" fn expr_frame_callback(render_state: *mut RenderState, callback: extern fn(RenderResult, i32, *mut State, *mut i8)) -> *mut i8 {
-    null_mut()
+pub unsafe extern "C" fn expr_frame_callback(render_state: *mut RenderState, callback: FrameCallback, user_data: *mut c_void) -> ExprError {
+    match ServerState::from_render_state(render_state) {
+        Ok(server) => {
+            server.frames.set_callback(Registered { callback, user_data });
+            ExprError::none()
+        }
+        Err(e) => e
+    }
 }
 
+/// # Safety
+/// render_state must be a pointer returned by expr_init that has not been disconnected
 #[no_mangle]
-pub extern "C" fn expr_user_callback(render_state: *mut RenderState, callback: extern fn(UserEvent, *mut i8)) -> *mut i8 {
-    null_mut()
-}
\ No newline at end of file
+pub unsafe extern "C" fn expr_user_callback(render_state: *mut RenderState, callback: UserCallback, user_data: *mut c_void) -> ExprError {
+    let server = match ServerState::from_render_state(render_state) {
+        Ok(server) => server,
+        Err(e) => return e
+    };
+    *server.user_callback.lock().unwrap_or_else(|e| e.into_inner()) = Some(Registered { callback, user_data });
+    server.start_input().map_or_else(|e| server.input_error(e), |_| ExprError::none())
+}
+
+/// outstanding is the number of error messages handed out for render_state and not released through expr_free yet,
+/// so a session can be checked for leaks
+///
+/// # Safety
+/// render_state must be a pointer returned by expr_init that has not been freed (see ServerState)
+pub unsafe fn outstanding(render_state: *mut RenderState) -> usize {
+    (render_state as *mut ServerState).as_ref().map_or(0, |server| server.allocations.outstanding())
+}
+
+/// # Safety
+/// error must be the message of an ExprError returned by one of the functions above that has not been freed yet
+#[no_mangle]
+pub unsafe extern "C" fn expr_free(error: *mut i8) {
+    alloc::free(error)
+}
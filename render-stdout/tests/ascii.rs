@@ -0,0 +1,74 @@
+use render::ascii::render;
+use render_api::v0::menu::Menu;
+use render_api::v0::owned::{Actor, ActorState, MenuState, Section, State, Terrain, WorldState};
+use render_api::v0::state::{RenderContext, StateView};
+
+const SIZE: (usize, usize) = (80, 24);
+
+fn draw(state: &State, size: (usize, usize)) -> String {
+    let lowered = state.lower().unwrap();
+    render(&unsafe { StateView::new(lowered.state()) }.unwrap(), size)
+}
+
+#[test]
+fn draws_terrain_and_actors() {
+    let world = WorldState::fill(4, 2, 1, &Terrain::new("terminal"))
+        .with_terrain(0, 0, 0, Terrain::new("impassable"))
+        .with_terrain(3, 1, 0, Terrain::new("entrance"));
+    let actors = ActorState::new()
+        .with_actor(Actor::new("hero", "player").with_position(1, 0, 0))
+        .with_actor(Actor::new("wolf", "computer").with_position(2, 1, 0));
+    let state = State::new(RenderContext::Battle).with_world(world).with_actors(actors);
+
+    assert_eq!(draw(&state, SIZE), "[battle]\n#@..\n..&>\n");
+}
+
+#[test]
+fn draws_the_highest_terrain_with_a_kind() {
+    let world = WorldState::new(2, 1, 2)
+        .with_terrain(0, 0, 0, Terrain::new("terminal"))
+        .with_terrain(1, 0, 0, Terrain::new("terminal"))
+        .with_terrain(1, 0, 1, Terrain::new("passable"))
+        .with_terrain(0, 0, 1, Terrain::new(""));
+
+    assert_eq!(draw(&State::default().with_world(world), SIZE), "[world]\n.,\n");
+}
+
+#[test]
+fn lists_actors_outside_the_map() {
+    let actors = ActorState::new().with_actor(Actor::new("ghost", "computer").with_position(5, -1, 2));
+    let state = State::default().with_world(WorldState::fill(2, 1, 1, &Terrain::new("passable"))).with_actors(actors);
+
+    assert_eq!(draw(&state, SIZE), "[world]\n,,\n& ghost at (5, -1, 2)\n");
+}
+
+#[test]
+fn cuts_the_map_to_the_terminal() {
+    let actors = ActorState::new().with_actor(Actor::new("hero", "player").with_position(3, 0, 0));
+    let state = State::default().with_world(WorldState::fill(5, 3, 1, &Terrain::new("terminal"))).with_actors(actors);
+
+    assert_eq!(draw(&state, (2, 1)), "[world]\n..\n@ hero at (3, 0, 0)\n");
+}
+
+#[test]
+fn ignores_the_dimensions_of_a_world_without_terrain() {
+    // NOTE: a world of depth 0 is valid with empty terrain, whatever its other dimensions are
+    let state = State::default().with_world(WorldState::new(1 << 30, 1 << 30, 0));
+    assert_eq!(draw(&state, SIZE), "[world]\n");
+}
+
+#[test]
+fn draws_the_current_section() {
+    let options = Section::new("Options").with_text("Sound").with_text("Video").with_selected_item(1);
+    let pause = Section::new("Pause").with_description("Game paused").with_text("Resume").with_subsection(options);
+    let menu = MenuState::new("pause").with_section(pause).with_section(Section::new("Map"));
+
+    let expected = "[world]\n== pause ==\n> Pause\n  Map\n-- Pause --\nGame paused\n  > Resume\n    Options/\n";
+    assert_eq!(draw(&State::default().with_menu(menu.clone()), SIZE), expected);
+
+    let mut menu = Menu::new(menu).unwrap();
+    menu.select_next();
+    assert!(menu.descend());
+    let expected = "[world]\n== pause ==\n> Pause\n  Map\n-- Options --\n    Sound\n  > Video\n";
+    assert_eq!(draw(&State::default().with_menu(menu.into_state()), SIZE), expected);
+}
@@ -0,0 +1,75 @@
+#![cfg(unix)]
+
+use render::input::{Decoder, Event};
+
+fn keys(bytes: &[u8]) -> Vec<Event> {
+    Decoder::default().feed(bytes)
+}
+
+fn input(names: &[&str]) -> Vec<Event> {
+    names.iter().map(|name| Event::Input(name.to_string())).collect()
+}
+
+fn prompt(text: &str) -> Event {
+    Event::Prompt(Some(text.to_string()))
+}
+
+#[test]
+fn decodes_arrows() {
+    assert_eq!(keys(b"\x1b[A\x1b[B\x1b[C\x1b[D"), input(&["up", "down", "right", "left"]));
+    assert_eq!(keys(b"\x1bOA\x1bOH\x1b[F"), input(&["up", "home", "end"]));
+}
+
+#[test]
+fn decodes_tilde_sequences() {
+    assert_eq!(keys(b"\x1b[2~\x1b[3~\x1b[5~\x1b[6~\x1b[9~"), input(&["insert", "delete", "page_up", "page_down", "unknown"]));
+}
+
+#[test]
+fn decodes_modified_keys() {
+    assert_eq!(keys(b"\x1b[1;5A\x1b[1;2D\x1b[3;3~\x1b[1;6C"), input(&["ctrl+up", "shift+left", "alt+delete", "ctrl+shift+right"]));
+}
+
+#[test]
+fn stops_a_sequence_at_its_final_byte() {
+    assert_eq!(keys(b"\x1b[1;5Ax~"), input(&["ctrl+up", "x", "~"]));
+    assert_eq!(keys(b"\x1b[Cq"), input(&["right", "q"]));
+}
+
+#[test]
+fn decodes_escape() {
+    assert_eq!(keys(b"\x1b"), input(&["escape"]));
+    assert_eq!(keys(b"\x1bx"), input(&["escape", "x"]));
+    // NOTE: a sequence split across reads is not put back together
+    assert_eq!(keys(b"\x1b[1;"), input(&["escape", "[", "1", ";"]));
+}
+
+#[test]
+fn decodes_control_keys() {
+    assert_eq!(keys(b"\x01\x03\x1a\r\n\t\x7f\x08 "),
+               input(&["ctrl+a", "ctrl+c", "ctrl+z", "enter", "enter", "tab", "backspace", "backspace", "space"]));
+}
+
+#[test]
+fn decodes_utf8() {
+    assert_eq!(keys("aé€😀".as_bytes()), input(&["a", "é", "€", "😀"]));
+}
+
+#[test]
+fn submits_commands() {
+    let mut decoder = Decoder::default();
+    assert_eq!(decoder.feed(b":"), vec![prompt("")]);
+    assert_eq!(decoder.feed(b"go n"), vec![prompt("g"), prompt("go"), prompt("go "), prompt("go n")]);
+    assert_eq!(decoder.feed(b"\x7fs\x1b[A"), vec![prompt("go "), prompt("go s")]);
+    assert_eq!(decoder.feed(b"\r"), vec![Event::Command("go s".to_string()), Event::Prompt(None)]);
+    assert_eq!(decoder.feed(b"x"), input(&["x"]));
+}
+
+#[test]
+fn abandons_commands() {
+    let mut decoder = Decoder::default();
+    assert_eq!(decoder.feed(b":q\x1b"), vec![prompt(""), prompt("q"), Event::Prompt(None)]);
+    assert_eq!(decoder.feed(b":\x03"), vec![prompt(""), Event::Prompt(None)]);
+    assert_eq!(decoder.feed(b":"), vec![prompt("")]);
+    assert_eq!(decoder.feed(b"\x7f\x7f:"), vec![prompt(""), prompt(""), prompt(":")]);
+}
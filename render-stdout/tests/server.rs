@@ -0,0 +1,106 @@
+use std::ffi::{c_void, CStr};
+use std::ptr::null_mut;
+use std::sync::{Mutex, MutexGuard};
+use render::{expr_disconnect, expr_frame_callback, expr_free, expr_init, expr_push_state, expr_reconnect, expr_user_callback};
+use render_api::error::{ErrorCode, ExprError};
+use render_api::negotiate::ClientMetadata;
+use render_api::v0::owned::{State, Terrain, WorldState};
+use render_api::v0::state;
+use render_api::v0::{RenderResult, UserEvent};
+use render_api::{InitResult, RenderState};
+
+// NOTE: InitResult only exposes constructors, so the state is read through a struct of the same layout
+
+#[repr(C)]
+struct RawInitResult {
+    server_version: *mut i8,
+    server_state: *mut RenderState,
+    server_extensions: *mut *mut i8,
+    server_extensions_length: isize,
+    accepted_extensions: *mut *mut i8,
+    accepted_extensions_length: isize,
+    error: ExprError
+}
+
+// NOTE: expr_init frees every state left disconnected, so tests must not init while another one is disconnected
+static SERVER: Mutex<()> = Mutex::new(());
+
+fn lock() -> MutexGuard<'static, ()> {
+    SERVER.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// init runs expr_init, returning the render state
+fn init() -> *mut RenderState {
+    let mut metadata = ClientMetadata::new("test");
+    let result = unsafe { expr_init(metadata.as_incoming()) };
+    let raw = unsafe { &*(&result as *const InitResult as *const RawInitResult) };
+    assert!(!raw.error.is_error());
+    assert_eq!(unsafe { CStr::from_ptr(raw.server_version) }, c"0");
+    assert!(!raw.server_state.is_null());
+    raw.server_state
+}
+
+/// code is the code of error, releasing its message
+fn code(error: ExprError) -> ErrorCode {
+    unsafe { expr_free(error.message_ptr()) };
+    error.code()
+}
+
+unsafe extern "C" fn on_frame(_result: RenderResult, _frame: i32, _game_state: *mut state::State, _message: *mut i8, _user_data: *mut c_void) {}
+
+unsafe extern "C" fn on_user(_event: UserEvent, _text: *mut i8, _user_data: *mut c_void) {}
+
+#[test]
+fn rejects_invalid_states() {
+    let _lock = lock();
+    let server = init();
+    assert_eq!(code(unsafe { expr_push_state(server, null_mut()) }), ErrorCode::InvalidState);
+    assert_eq!(code(unsafe { expr_disconnect(server) }), ErrorCode::None);
+}
+
+#[test]
+fn reconnects_after_disconnecting() {
+    let _lock = lock();
+    let server = init();
+    assert_eq!(code(unsafe { expr_disconnect(server) }), ErrorCode::None);
+    assert_eq!(code(unsafe { expr_reconnect(server) }), ErrorCode::None, "the state is kept while disconnected");
+    assert_eq!(code(unsafe { expr_push_state(server, null_mut()) }), ErrorCode::InvalidState);
+    assert_eq!(code(unsafe { expr_disconnect(server) }), ErrorCode::None);
+    let server = init();
+    assert_eq!(code(unsafe { expr_disconnect(server) }), ErrorCode::None);
+}
+
+#[test]
+fn rejects_null_render_states() {
+    assert_eq!(code(unsafe { expr_push_state(null_mut(), null_mut()) }), ErrorCode::InvalidRenderState);
+    assert_eq!(code(unsafe { expr_disconnect(null_mut()) }), ErrorCode::InvalidRenderState);
+    assert_eq!(code(unsafe { expr_reconnect(null_mut()) }), ErrorCode::InvalidRenderState);
+}
+
+#[test]
+fn releases_every_string_of_a_session() {
+    let _lock = lock();
+    let server = init();
+    let world = State::default().with_world(WorldState::fill(2, 2, 1, &Terrain::new("passable")));
+    let mut lowered = world.lower().unwrap();
+    let errors = unsafe {
+        vec![
+            expr_frame_callback(server, on_frame, null_mut()),
+            // NOTE: input cannot be captured without a terminal, which is reported as an error
+            expr_user_callback(server, on_user, null_mut()),
+            expr_push_state(server, null_mut()),
+            expr_push_state(server, lowered.as_mut_ptr())
+        ]
+    };
+    assert!(unsafe { render::outstanding(server) } >= 1, "the invalid state was reported");
+    for error in errors {
+        code(error);
+    }
+    assert_eq!(code(unsafe { expr_disconnect(server) }), ErrorCode::None);
+    assert_eq!(unsafe { render::outstanding(server) }, 0);
+    // NOTE: reconnecting restarts the input capture, so it fails the same way without a terminal
+    let reconnected = code(unsafe { expr_reconnect(server) });
+    assert!(matches!(reconnected, ErrorCode::None | ErrorCode::Unsupported), "{:?}", reconnected);
+    assert_eq!(code(unsafe { expr_disconnect(server) }), ErrorCode::None);
+    assert_eq!(unsafe { render::outstanding(server) }, 0);
+}
@@ -0,0 +1,26 @@
+//! conformance checks a render server library against the rules of the render API
+//!
+//! Every rule is printed with its outcome, the exit status is 1 if any rule was violated.
+
+use std::path::PathBuf;
+use render_api::locate;
+use render_api::v0::conformance::check_library;
+
+const USAGE: &str = "usage: conformance [<path>]";
+
+fn main() {
+    let mut argv = std::env::args_os().skip(1);
+    let path = argv.next().map(PathBuf::from);
+    if argv.next().is_some() {
+        eprintln!("{}", USAGE);
+        std::process::exit(1);
+    }
+
+    let path = locate::select_server(path.as_deref(), None);
+    println!("checking {}", path.display());
+    let report = check_library(&path);
+    print!("{}", report);
+    if !report.conforms() {
+        std::process::exit(1);
+    }
+}
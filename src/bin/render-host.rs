@@ -0,0 +1,28 @@
+//! render-host runs a render server library for a client in another process
+//!
+//! The client spawns it with the library and the socket to connect to, see render_api::v0::remote.
+//! Like remote, it is only available on unix.
+
+#[cfg(unix)]
+use render_api::v0::remote::serve;
+
+const USAGE: &str = "usage: render-host <library> <socket>";
+
+#[cfg(unix)]
+fn main() {
+    let argv: Vec<_> = std::env::args_os().skip(1).collect();
+    if argv.len() != 2 {
+        eprintln!("{}", USAGE);
+        std::process::exit(1);
+    }
+    if let Err(e) = serve(&argv[0], &argv[1]) {
+        eprintln!("render-host: {}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(not(unix))]
+fn main() {
+    eprintln!("render-host: isolated render servers are only supported on unix");
+    std::process::exit(1);
+}
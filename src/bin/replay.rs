@@ -0,0 +1,87 @@
+//! replay drives a render server with a recorded session
+//!
+//! Pushed states are replayed at the pace they were recorded (scaled by --speed), recorded user events
+//! are printed so they can be matched against what the server shows.
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use render_api::locate;
+use render_api::negotiate::ClientMetadata;
+use render_api::v0::diff::Deltas;
+use render_api::v0::handle::RenderServer;
+use render_api::v0::record::{Event, Session};
+use render_api::extension::ExtensionRegistry;
+
+const USAGE: &str = "usage: replay <session> [--render <path>] [--speed <factor>]";
+
+/// Args is the parsed command line
+///
+/// # Notes
+/// speed is how many times faster than recorded the session is replayed, 0 replays without waiting
+struct Args {
+    session: PathBuf,
+    render: Option<PathBuf>,
+    speed: f64
+}
+
+impl Args {
+    fn parse() -> Result<Args, String> {
+        let mut session = None;
+        let mut render = None;
+        let mut speed = 1.0;
+        let mut argv = std::env::args_os().skip(1);
+        while let Some(arg) = argv.next() {
+            match arg.to_str() {
+                Some("--render") => {
+                    let path = argv.next().ok_or("--render requires a path")?;
+                    render = Some(PathBuf::from(path));
+                }
+                Some("--speed") => {
+                    let factor = argv.next().ok_or("--speed requires a factor")?;
+                    speed = factor.to_str()
+                        .and_then(|f| f.parse::<f64>().ok())
+                        .filter(|f| f.is_finite() && *f >= 0.0)
+                        .ok_or_else(|| format!("invalid speed {}", factor.to_string_lossy()))?;
+                }
+                _ if session.is_none() => session = Some(PathBuf::from(arg)),
+                _ => return Err(format!("unexpected argument {}", arg.to_string_lossy()))
+            }
+        }
+        Ok(Args { session: session.ok_or("missing session file")?, render, speed })
+    }
+}
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
+
+fn main() {
+    let args = Args::parse().unwrap_or_else(|e| fail(format!("{}\n{}", e, USAGE)));
+    let session = Session::open(&args.session).unwrap_or_else(|e| fail(e));
+
+    let path = locate::select_server(args.render.as_deref(), None);
+    let mut extensions = ExtensionRegistry::new();
+    extensions.register::<Deltas>(());
+    let metadata = ClientMetadata::new(env!("CARGO_PKG_VERSION")).with_extensions(extensions);
+    let mut server = RenderServer::open_with(&path, metadata).unwrap_or_else(|e| fail(e));
+
+    let start = Instant::now();
+    let mut states = 0;
+    for entry in session {
+        let entry = entry.unwrap_or_else(|e| fail(e));
+        if args.speed > 0.0 {
+            let due = entry.at.div_f64(args.speed);
+            std::thread::sleep(due.saturating_sub(start.elapsed()));
+        }
+        match entry.event {
+            Event::State(state) => {
+                server.push(&state).unwrap_or_else(|e| fail(e));
+                states += 1;
+            }
+            Event::User { event, text } => eprintln!("[{:>10.3}s] {:?} {}", entry.at.as_secs_f64(), event, text)
+        }
+    }
+
+    eprintln!("replayed {} states in {:.3}s", states, Duration::as_secs_f64(&start.elapsed()));
+}
@@ -0,0 +1,58 @@
+//! config reads the core configuration file
+//!
+//! The configuration is a list of `key = value` lines, blank lines and lines starting with # are ignored.
+//!
+//! # Keys
+//! * render - path of the render server library to load
+//! * render_path - directories searched when listing render servers (may occur more than once)
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// CONFIG_ENV is the environment variable naming the configuration file to read
+pub const CONFIG_ENV: &str = "EXPLORITRON_CONFIG";
+
+/// CONFIG_PATH is the configuration file read when CONFIG_ENV is not set
+pub const CONFIG_PATH: &str = "exploritron.cfg";
+
+/// Config is the parsed core configuration
+#[derive(Debug, Default)]
+pub struct Config {
+    pub render: Option<PathBuf>,
+    pub render_path: Vec<PathBuf>
+}
+
+impl Config {
+    /// load reads the configuration file, a missing file yields the default configuration
+    pub fn load() -> io::Result<Config> {
+        let path = std::env::var_os(CONFIG_ENV)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(CONFIG_PATH));
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Config::parse(&text, &path),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// parse reads configuration text, path is only used to describe errors
+    pub fn parse(text: &str, path: &Path) -> io::Result<Config> {
+        let mut config = Config::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData,
+                format!("{}:{}: {}", path.display(), number + 1, message));
+            let (key, value) = line.split_once('=').ok_or_else(|| invalid("expected key = value"))?;
+            let value = PathBuf::from(value.trim());
+            match key.trim() {
+                "render" => config.render = Some(value),
+                "render_path" => config.render_path.push(value),
+                key => return Err(invalid(&format!("unknown key {}", key)))
+            }
+        }
+        Ok(config)
+    }
+}
@@ -1,12 +1,112 @@
-use std::mem::MaybeUninit;
-use render_api::v0::client::push_state;
-use std::ptr::{null, null_mut};
+mod config;
+
+use std::path::PathBuf;
+use render_api::locate;
+use render_api::negotiate::ClientMetadata;
+#[cfg(all(feature = "static-render", not(debug_assertions)))]
+use render_api::v0::backend::StaticBackend;
+use render_api::v0::handle::{RenderError, RenderServer};
+#[cfg(unix)]
+use render_api::v0::remote::RemoteBackend;
+use render_api::v0::thread::{RenderMessage, RenderThread};
+use config::Config;
+
+#[cfg(unix)]
+const USAGE: &str = "usage: exploritron [--render <path>] [--isolated] [--list-renderers]";
+#[cfg(not(unix))]
+const USAGE: &str = "usage: exploritron [--render <path>] [--list-renderers]";
+
+/// Args is the parsed command line
+///
+/// # Notes
+/// isolated is only available on unix, where render-host is supported (see render_api::v0::remote)
+#[derive(Default)]
+struct Args {
+    render: Option<PathBuf>,
+    #[cfg(unix)]
+    isolated: bool,
+    list_renderers: bool
+}
+
+impl Args {
+    fn parse() -> Result<Args, String> {
+        let mut args = Args::default();
+        let mut argv = std::env::args_os().skip(1);
+        while let Some(arg) = argv.next() {
+            match arg.to_str() {
+                Some("--render") => {
+                    let path = argv.next().ok_or("--render requires a path")?;
+                    args.render = Some(PathBuf::from(path));
+                }
+                #[cfg(unix)]
+                Some("--isolated") => args.isolated = true,
+                Some("--list-renderers") => args.list_renderers = true,
+                _ => return Err(format!("unexpected argument {}", arg.to_string_lossy()))
+            }
+        }
+        Ok(args)
+    }
+}
+
+/// open_server opens the selected render server
+///
+/// When no server is selected release builds use the linked in server (see the static-render feature),
+/// debug builds load the default library so the server can be rebuilt without relinking the core.
+/// Isolated servers always run from a library, in a render-host process next to the core's executable.
+fn open_server(args: &Args, config: &Config) -> Result<RenderServer, RenderError> {
+    let metadata = ClientMetadata::new(env!("CARGO_PKG_VERSION"));
+    let selected = locate::selected_server(args.render.as_deref(), config.render.as_deref());
+    #[cfg(unix)]
+    if args.isolated {
+        let host = std::env::current_exe().map_err(|e| RenderError::Load(e.to_string()))?.with_file_name("render-host");
+        let library = selected.unwrap_or_else(locate::default_server);
+        return RenderServer::with_backend(RemoteBackend::spawn(host, library)?, metadata);
+    }
+    match selected {
+        Some(path) => RenderServer::open_with(path, metadata),
+        #[cfg(all(feature = "static-render", not(debug_assertions)))]
+        None => RenderServer::with_backend(linked_server(), metadata),
+        #[cfg(not(all(feature = "static-render", not(debug_assertions))))]
+        None => RenderServer::open_with(locate::default_server(), metadata)
+    }
+}
+
+/// linked_server is render-stdout, linked into the core
+#[cfg(all(feature = "static-render", not(debug_assertions)))]
+fn linked_server() -> StaticBackend {
+    StaticBackend::new(render::expr_init, render::expr_push_state, render::expr_frame_callback, render::expr_user_callback)
+        .with_disconnect(render::expr_disconnect)
+        .with_reconnect(render::expr_reconnect)
+        .with_free(render::expr_free)
+}
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
 
 fn main() {
-    unsafe {
-        let _ = render_api::init(MaybeUninit::uninit().assume_init());
-        let _ = push_state(null_mut(), null_mut());
+    let args = Args::parse().unwrap_or_else(|e| fail(format!("{}\n{}", e, USAGE)));
+    let config = Config::load().unwrap_or_else(|e| fail(e));
+
+    if args.list_renderers {
+        let mut search_path = locate::search_path();
+        search_path.extend(config.render_path);
+        for server in locate::find_servers(search_path) {
+            println!("{}", server.display());
+        }
+        return;
     }
 
-    println!("no panic!")
+    let render = RenderThread::spawn(move || open_server(&args, &config)).unwrap_or_else(|e| fail(e));
+
+    println!("connected to render server version {}", render.server_version());
+
+    for message in render.stop() {
+        match message {
+            RenderMessage::Failed(e) => eprintln!("{}", e),
+            RenderMessage::Recovered => eprintln!("render server recovered"),
+            _ => ()
+        }
+    }
 }
@@ -0,0 +1,43 @@
+#[path = "../src/config.rs"]
+mod config;
+
+use std::path::{Path, PathBuf};
+use config::{Config, CONFIG_ENV, CONFIG_PATH};
+
+fn parse(text: &str) -> std::io::Result<Config> {
+    Config::parse(text, Path::new(CONFIG_PATH))
+}
+
+#[test]
+fn parses_keys() {
+    let config = parse("# servers\n\nrender = ./render.so\n  render_path=a\nrender_path = b c \n").unwrap();
+    assert_eq!(config.render, Some(PathBuf::from("./render.so")));
+    assert_eq!(config.render_path, vec![PathBuf::from("a"), PathBuf::from("b c")]);
+}
+
+#[test]
+fn later_renders_replace_earlier_ones() {
+    assert_eq!(parse("render = a.so\nrender = b.so").unwrap().render, Some(PathBuf::from("b.so")));
+}
+
+#[test]
+fn rejects_invalid_lines() {
+    let error = parse("render = a.so\nrender a.so").unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(error.to_string(), "exploritron.cfg:2: expected key = value");
+    assert_eq!(parse("renderer = a.so").unwrap_err().to_string(), "exploritron.cfg:1: unknown key renderer");
+}
+
+#[test]
+fn loads_the_file_named_by_the_environment() {
+    let path = std::env::temp_dir().join(format!("exploritron-config-{}", std::process::id()));
+    std::fs::write(&path, "render = loaded.so\n").unwrap();
+    std::env::set_var(CONFIG_ENV, &path);
+    let loaded = Config::load();
+    std::env::set_var(CONFIG_ENV, path.with_extension("missing"));
+    let missing = Config::load();
+    std::env::remove_var(CONFIG_ENV);
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(loaded.unwrap().render, Some(PathBuf::from("loaded.so")));
+    assert!(missing.unwrap().render.is_none(), "a missing file is the default configuration");
+}
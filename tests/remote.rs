@@ -0,0 +1,48 @@
+#![cfg(unix)]
+
+use std::path::{Path, PathBuf};
+use render_api::error::ErrorCode;
+use render_api::negotiate::ClientMetadata;
+use render_api::v0::handle::RenderServer;
+use render_api::v0::owned::{State, Terrain, WorldState};
+use render_api::v0::remote::RemoteBackend;
+
+/// compile_c_server compiles the example C server of render-api, with $CC if set
+fn compile_c_server() -> PathBuf {
+    let render_api = Path::new(env!("CARGO_MANIFEST_DIR")).join("render-api");
+    let library = Path::new(env!("CARGO_TARGET_TMPDIR"))
+        .join(format!("{}render_c{}", std::env::consts::DLL_PREFIX, std::env::consts::DLL_SUFFIX));
+    let compiler = std::env::var_os("CC").unwrap_or_else(|| "cc".into());
+    let status = std::process::Command::new(&compiler)
+        .args(["-std=c99", "-shared", "-fPIC"])
+        .arg("-I").arg(render_api.join("include"))
+        .arg(render_api.join("examples").join("c").join("render_c.c"))
+        .arg("-o").arg(&library)
+        .status()
+        .unwrap_or_else(|e| panic!("{} could not be run: {}", compiler.to_string_lossy(), e));
+    assert!(status.success(), "render_c.c failed to compile");
+    library
+}
+
+fn world() -> State {
+    State::default().with_world(WorldState::fill(2, 2, 1, &Terrain::new("passable")))
+}
+
+#[test]
+fn recovers_killed_hosts() {
+    let backend = RemoteBackend::spawn(env!("CARGO_BIN_EXE_render-host"), compile_c_server()).unwrap();
+    let host = backend.process_id().unwrap();
+    let mut server = RenderServer::with_backend(backend, ClientMetadata::new("test")).unwrap();
+    server.push(&world()).unwrap();
+
+    let status = std::process::Command::new("kill").arg("-9").arg(host.to_string()).status().unwrap();
+    assert!(status.success(), "render-host {} could not be killed", host);
+    let lost = server.push(&world()).unwrap_err();
+    assert_eq!(lost.code(), ErrorCode::DeviceLost, "{}", lost);
+
+    // NOTE: the new host rejects pushes until expr_init is rerun on it, so a successful push shows recover reinitialized it
+    server.recover().unwrap();
+    assert_eq!(server.server_version(), "0");
+    server.push(&world()).unwrap();
+    server.disconnect().unwrap();
+}